@@ -0,0 +1,85 @@
+use std::io::Write;
+
+use crate::{config::{Config, StoredConfig, defines}, crypto::PrivateKey, log::AuditTrail};
+
+pub const USAGE: &str = "Usage: mokaccino [export-identity <file> | import-identity <file> | rotate-identity]";
+
+/// Identity management that can be done without starting the UI
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command
+{
+    ExportIdentity(String),
+    ImportIdentity(String),
+    RotateIdentity,
+}
+
+impl Command
+{
+    pub fn from_args(args: &[String]) -> Result<Option<Self>,String>
+    {
+        match args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>().as_slice()
+        {
+            [] => Ok(None),
+            ["export-identity", path] => Ok(Some(Self::ExportIdentity(path.to_string()))),
+            ["import-identity", path] => Ok(Some(Self::ImportIdentity(path.to_string()))),
+            ["rotate-identity"] => Ok(Some(Self::RotateIdentity)),
+            _ => Err(USAGE.to_string()),
+        }
+    }
+
+    pub fn run(&self, config_path: &str) -> Result<(),String>
+    {
+        let mut config = match StoredConfig::from_file(config_path)?
+        {
+            StoredConfig::Unlocked(config) => config,
+            StoredConfig::Locked(locked) => locked.unlock(&prompt("Config passphrase: ")?)?,
+        };
+        let audit_trail = AuditTrail::new(&Config::path_next_to(config_path, defines::AUDIT_TRAIL_PATH));
+        match self
+        {
+            Self::ExportIdentity(path) =>
+            {
+                let passphrase = prompt("Export passphrase (empty for none): ")?;
+                let pem = config.network.private_key.to_pem(optional(&passphrase)).map_err(|e| e.to_string())?;
+                std::fs::write(path, pem).map_err(|e| e.to_string())?;
+                audit_trail.record(&format!("Identity key exported to {}", path))?;
+                println!("Identity exported to {}", path);
+            },
+            Self::ImportIdentity(path) =>
+            {
+                let pem = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+                let passphrase = prompt("Import passphrase (empty for none): ")?;
+                let private_key = PrivateKey::from_pem(&pem, optional(&passphrase)).map_err(|e| e.to_string())?;
+                let fingerprint = private_key.public_key().fingerprint_string();
+                config.network.import_private_key(private_key);
+                config.to_file(config_path)?;
+                audit_trail.record(&format!("Identity key imported from {} ({})", path, fingerprint))?;
+                println!("Identity {} imported", fingerprint);
+            },
+            Self::RotateIdentity =>
+            {
+                let old_fingerprint = config.network.private_key.public_key().fingerprint_string();
+                config.network.rotate_private_key();
+                let new_fingerprint = config.network.private_key.public_key().fingerprint_string();
+                config.to_file(config_path)?;
+                audit_trail.record(&format!("Identity key rotated from {} to {}", old_fingerprint, new_fingerprint))?;
+                println!("Identity rotated from {} to {}", old_fingerprint, new_fingerprint);
+            },
+        }
+        Ok(())
+    }
+}
+
+fn optional(passphrase: &str) -> Option<&str>
+{
+    if passphrase.is_empty() {None} else {Some(passphrase)}
+}
+
+fn prompt(message: &str) -> Result<String,String>
+{
+    print!("{}", message);
+    std::io::stdout().flush().map_err(|e| e.to_string())?;
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line).map_err(|e| e.to_string())?;
+    Ok(line.trim_end_matches(['\r','\n']).to_string())
+}
@@ -0,0 +1,83 @@
+use std::net::SocketAddr;
+
+use serde::{Serialize, Deserialize};
+
+use crate::crypto::PublicKey;
+
+use super::{AclRule, AclAction};
+
+/// Decides what to do with incoming connection requests, the first matching rule wins
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Acl
+{
+    #[serde(default = "Acl::default_rules")]
+    pub rules: Vec<AclRule>,
+    #[serde(default = "Acl::default_default_action")]
+    pub default_action: AclAction,
+    /// new peers accepted each minute before the others are dropped
+    pub max_strangers_per_minute: Option<u32>,
+}
+
+impl Acl
+{
+    pub fn matching_rule(&self, name: &str, public_key: &PublicKey, address: &SocketAddr) -> Option<&AclRule>
+    {
+        self.rules.iter().find(|rule| rule.pattern.matches(name, public_key, address))
+    }
+
+    pub fn evaluate(&self, name: &str, public_key: &PublicKey, address: &SocketAddr) -> AclAction
+    {
+        self.matching_rule(name, public_key, address)
+            .map(|rule| rule.action)
+            .unwrap_or(self.default_action)
+    }
+
+    fn default_rules() -> Vec<AclRule> { Vec::new() }
+    fn default_default_action() -> AclAction { AclAction::Accept }
+}
+
+impl Default for Acl
+{
+    fn default() -> Self
+    {
+        Self
+        {
+            rules: Acl::default_rules(),
+            default_action: Acl::default_default_action(),
+            max_strangers_per_minute: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::{crypto::PrivateKey, config::AclPattern};
+
+    use super::*;
+
+    #[test]
+    fn first_matching_rule()
+    {
+        let key = PrivateKey::new().public_key();
+        let fingerprint: String = key.fingerprint()[..4].iter().map(|b| format!("{:02x}", b)).collect();
+        let acl = Acl
+        {
+            rules: vec![
+                AclRule::new(AclPattern::Fingerprint(fingerprint), AclAction::Accept),
+                AclRule::new(AclPattern::Name("spam*".to_string()), AclAction::Drop),
+                AclRule::new(AclPattern::Address("10.0.0.0/8".to_string()), AclAction::Ask),
+            ],
+            default_action: AclAction::Reject,
+            max_strangers_per_minute: None,
+        };
+        let other_key = PrivateKey::new().public_key();
+        let lan = "10.1.2.3:4848".parse().unwrap();
+        let wan = "203.0.113.7:4848".parse().unwrap();
+        assert_eq!(acl.evaluate("spammer", &key, &lan), AclAction::Accept);
+        assert_eq!(acl.evaluate("spammer", &other_key, &lan), AclAction::Drop);
+        assert_eq!(acl.evaluate("friend", &other_key, &lan), AclAction::Ask);
+        assert_eq!(acl.evaluate("friend", &other_key, &wan), AclAction::Reject);
+        assert_eq!(acl.evaluate("nospam", &other_key, &wan), AclAction::Reject);
+    }
+}
@@ -0,0 +1,33 @@
+use serde::{Serialize, Deserialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AclAction
+{
+    Accept,
+    /// Tells the peer the connection was refused
+    Reject,
+    /// Lets the user decide when the request arrives
+    Ask,
+    /// Ignores the request without answering
+    Drop,
+}
+
+impl AclAction
+{
+    pub fn all() -> Vec<Self>
+    {
+        vec![Self::Accept, Self::Reject, Self::Ask, Self::Drop]
+    }
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Self::Accept => "Accept",
+            Self::Reject => "Reject",
+            Self::Ask => "Ask",
+            Self::Drop => "Drop",
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::net::{SocketAddr, IpAddr};
+
+use serde::{Serialize, Deserialize};
+
+use crate::crypto::PublicKey;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AclPattern
+{
+    /// Hex prefix of the identity key fingerprint, spaces and colons are ignored
+    Fingerprint(String),
+    /// Self-declared name, `*` matches any sequence of characters
+    Name(String),
+    /// An address or a CIDR block like `192.168.0.0/16`
+    Address(String),
+}
+
+impl AclPattern
+{
+    pub fn kind(&self) -> &'static str
+    {
+        match self
+        {
+            Self::Fingerprint(_) => "Fingerprint",
+            Self::Name(_) => "Name",
+            Self::Address(_) => "Address",
+        }
+    }
+
+    pub fn value(&self) -> &str
+    {
+        match self
+        {
+            Self::Fingerprint(value) |
+            Self::Name(value) |
+            Self::Address(value) => value,
+        }
+    }
+
+    pub fn value_mut(&mut self) -> &mut String
+    {
+        match self
+        {
+            Self::Fingerprint(value) |
+            Self::Name(value) |
+            Self::Address(value) => value,
+        }
+    }
+
+    pub fn matches(&self, name: &str, public_key: &PublicKey, address: &SocketAddr) -> bool
+    {
+        match self
+        {
+            Self::Fingerprint(prefix) =>
+            {
+                let prefix: String = prefix.chars().filter(|c| c.is_ascii_hexdigit()).collect::<String>().to_lowercase();
+                let fingerprint: String = public_key.fingerprint().iter().map(|b| format!("{:02x}", b)).collect();
+                !prefix.is_empty() && fingerprint.starts_with(&prefix)
+            },
+            Self::Name(pattern) => glob_matches(pattern, name),
+            Self::Address(block) => cidr_matches(block, &address.ip()),
+        }
+    }
+}
+
+fn glob_matches(pattern: &str, text: &str) -> bool
+{
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else { return false };
+    let parts: Vec<&str> = parts.collect();
+    if let Some((last, middle)) = parts.split_last()
+    {
+        for part in middle
+        {
+            match rest.find(part)
+            {
+                Some(index) => rest = &rest[index + part.len()..],
+                None => return false,
+            }
+        }
+        rest.len() >= last.len() && rest.ends_with(last)
+    }
+    else
+    {
+        // no wildcard, the whole name must match
+        rest.is_empty()
+    }
+}
+
+fn cidr_matches(block: &str, ip: &IpAddr) -> bool
+{
+    let (network, prefix_len) = match block.split_once('/')
+    {
+        Some((network, prefix_len)) => (network, prefix_len.parse::<u32>().ok()),
+        None => (block, None),
+    };
+    let Ok(network) = network.trim().parse::<IpAddr>() else { return false };
+    match (network, ip)
+    {
+        (IpAddr::V4(network), IpAddr::V4(ip)) =>
+        {
+            let prefix_len = prefix_len.unwrap_or(32).min(32);
+            let mask = u32::MAX.checked_shl(32 - prefix_len).unwrap_or(0);
+            u32::from(network) & mask == u32::from(*ip) & mask
+        },
+        (IpAddr::V6(network), IpAddr::V6(ip)) =>
+        {
+            let prefix_len = prefix_len.unwrap_or(128).min(128);
+            let mask = u128::MAX.checked_shl(128 - prefix_len).unwrap_or(0);
+            u128::from(network) & mask == u128::from(*ip) & mask
+        },
+        _ => false,
+    }
+}
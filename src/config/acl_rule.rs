@@ -0,0 +1,18 @@
+use serde::{Serialize, Deserialize};
+
+use super::{AclPattern, AclAction};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AclRule
+{
+    pub pattern: AclPattern,
+    pub action: AclAction,
+}
+
+impl AclRule
+{
+    pub fn new(pattern: AclPattern, action: AclAction) -> Self
+    {
+        Self { pattern, action }
+    }
+}
@@ -1,6 +1,8 @@
+use std::collections::BTreeSet;
+
 use serde::{Deserialize, Serialize};
 
-use super::{NetworkConfig, VoiceConfig};
+use super::{NetworkConfig, VoiceConfig, TextConfig, ConfigProtection, ProtectionMode, StoredConfig, Hook, defines};
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Config
 {
@@ -8,24 +10,152 @@ pub struct Config
     pub network: NetworkConfig,
     #[serde(default)]
     pub voice: VoiceConfig,
+    #[serde(default)]
+    pub text: TextConfig,
+    /// commands run on incoming messages, connections and calls
+    #[serde(default = "Config::default_hooks")]
+    pub hooks: Vec<Hook>,
+    /// plugins in the plugins folder that are allowed to run
+    #[serde(default = "Config::default_plugins")]
+    pub plugins: BTreeSet<String>,
+    /// Set when the config is protected by a passphrase, never written in clear
+    #[serde(skip)]
+    pub protection: Option<ConfigProtection>,
+    /// File the config was read from, the history and the other files of the app are kept next to it
+    #[serde(skip)]
+    pub path: Option<String>,
 }
 
 impl Config
 {
     pub fn from_file(path: &str) -> Result<Config,String>
     {
-        let file = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
-        //read toml config
-        let config: Config = toml::from_str(&file).map_err(|e| e.to_string())?;
-        Ok(config)
+        match StoredConfig::from_file(path)?
+        {
+            StoredConfig::Unlocked(config) => Ok(config),
+            StoredConfig::Locked(_) => Err("The config is protected by a passphrase".to_string()),
+        }
+    }
+
+    fn default_hooks() -> Vec<Hook> { Vec::new() }
+
+    /// Path of a file kept in the same folder as the config
+    pub fn path_next_to(config_path: &str, name: &str) -> String
+    {
+        std::path::Path::new(config_path)
+            .with_file_name(name)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    fn default_plugins() -> BTreeSet<String> { BTreeSet::new() }
+
+    /// Path of a file kept next to the config, in the working directory if the config has no file
+    pub fn data_path(&self, name: &str) -> String
+    {
+        match &self.path
+        {
+            Some(path) => Config::path_next_to(path, name),
+            None => name.to_string(),
+        }
     }
 
     pub fn to_file(&self, path: &str) -> Result<(),String>
     {
-        let config = toml::to_string_pretty(self).map_err(|e| e.to_string())?;
-        std::fs::write(path, config).map_err(|e| e.to_string())?;
+        let mut table = toml::Table::try_from(self).map_err(|e| e.to_string())?;
+        if let Some(protection) = &self.protection
+        {
+            let data = match protection.mode
+            {
+                ProtectionMode::PrivateKey =>
+                {
+                    let secrets = Config::take_secrets(&mut table);
+                    protection.seal(toml::to_string(&secrets).map_err(|e| e.to_string())?.as_bytes())
+                },
+                ProtectionMode::Full =>
+                {
+                    let data = protection.seal(toml::to_string_pretty(&table).map_err(|e| e.to_string())?.as_bytes());
+                    table.clear();
+                    data
+                },
+            };
+            let mut protection_table = toml::Table::new();
+            protection_table.insert("mode".to_string(), toml::Value::try_from(protection.mode).map_err(|e| e.to_string())?);
+            protection_table.insert("salt".to_string(), toml::Value::String(openssl::base64::encode_block(protection.salt())));
+            protection_table.insert("data".to_string(), toml::Value::String(data));
+            table.insert("protection".to_string(), toml::Value::Table(protection_table));
+        }
+        let config = toml::to_string_pretty(&table).map_err(|e| e.to_string())?;
+        // a crash while writing must not leave half a config
+        let temporary = format!("{}.tmp", path);
+        std::fs::write(&temporary, config).map_err(|e| e.to_string())?;
+        std::fs::rename(&temporary, path).map_err(|e| e.to_string())?;
         Ok(())
     }
+
+    /// Writes the config to the file it was read from, a config without a file is only kept in memory
+    pub fn save(&self) -> Result<(),String>
+    {
+        match &self.path
+        {
+            Some(path) => self.to_file(path),
+            None => Ok(()),
+        }
+    }
+
+    /// Moves the keys and the other secrets out of the table, into a table with the same layout
+    pub(super) fn take_secrets(table: &mut toml::Table) -> toml::Table
+    {
+        let mut secrets = toml::Table::new();
+        for path in defines::CONFIG_SECRETS
+        {
+            move_value(table, &mut secrets, path);
+        }
+        secrets
+    }
+
+    /// Puts back the secrets taken with take_secrets
+    pub(super) fn restore_secrets(table: &mut toml::Table, secrets: toml::Table)
+    {
+        for (key, value) in secrets
+        {
+            match (table.get_mut(&key).and_then(|old| old.as_table_mut()), value)
+            {
+                (Some(old), toml::Value::Table(value)) => Config::restore_secrets(old, value),
+                (_, value) => { table.insert(key, value); },
+            }
+        }
+    }
+}
+
+/// Moves the value at the path, "*" stands for every key of a table
+fn move_value(from: &mut toml::Table, to: &mut toml::Table, path: &[&str])
+{
+    match path
+    {
+        [] => {},
+        [key] =>
+        {
+            if let Some(value) = from.remove(*key)
+            {
+                to.insert(key.to_string(), value);
+            }
+        },
+        [key, rest @ ..] =>
+        {
+            for (name, value) in from.iter_mut().filter(|(name, _)| *key == "*" || name == key)
+            {
+                if let Some(from) = value.as_table_mut()
+                {
+                    let to = to.entry(name.clone()).or_insert_with(|| toml::Value::Table(toml::Table::new()));
+                    if let Some(to) = to.as_table_mut()
+                    {
+                        move_value(from, to, rest);
+                    }
+                }
+            }
+        },
+    }
 }
 
 impl Default for Config
@@ -34,7 +164,11 @@ impl Default for Config
         Config{
             network: NetworkConfig::default(),
             voice: VoiceConfig::default(),
+            text: TextConfig::default(),
+            hooks: Config::default_hooks(),
+            plugins: Config::default_plugins(),
+            protection: None,
+            path: None,
         }
     }
 }
-
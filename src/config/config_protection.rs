@@ -0,0 +1,52 @@
+use serializable::Serializable;
+
+use crate::{crypto::{SymmetricKey, Ciphertext}, config::defines};
+
+use super::ProtectionMode;
+
+/// Key derived from the passphrase, kept in memory to encrypt the config every time it's saved
+#[derive(Clone, Debug)]
+pub struct ConfigProtection
+{
+    pub mode: ProtectionMode,
+    salt: Vec<u8>,
+    key: SymmetricKey,
+}
+
+impl ConfigProtection
+{
+    pub fn new(passphrase: &str, mode: ProtectionMode) -> Result<Self,String>
+    {
+        let mut salt = vec![0; defines::PASSPHRASE_SALT_LEN];
+        openssl::rand::rand_bytes(&mut salt).map_err(|e| e.to_string())?;
+        Self::with_salt(passphrase, mode, &salt)
+    }
+
+    pub fn with_salt(passphrase: &str, mode: ProtectionMode, salt: &[u8]) -> Result<Self,String>
+    {
+        let key = SymmetricKey::from_passphrase(passphrase, salt).map_err(|e| e.to_string())?;
+        Ok(Self { mode, salt: salt.to_vec(), key })
+    }
+
+    pub fn check_passphrase(&self, passphrase: &str) -> bool
+    {
+        SymmetricKey::from_passphrase(passphrase, &self.salt).map(|key| key == self.key).unwrap_or(false)
+    }
+
+    pub fn salt(&self) -> &[u8]
+    {
+        &self.salt
+    }
+
+    pub fn seal(&self, data: &[u8]) -> String
+    {
+        openssl::base64::encode_block(&self.key.encrypt(data).serialize())
+    }
+
+    pub fn open(&self, data: &str) -> Result<Vec<u8>,String>
+    {
+        let bytes = openssl::base64::decode_block(data).map_err(|e| e.to_string())?;
+        let (ciphertext, _len) = Ciphertext::deserialize(&bytes).map_err(|e| e.to_string())?;
+        self.key.decrypt(&ciphertext).map_err(|_| "Wrong passphrase".to_string())
+    }
+}
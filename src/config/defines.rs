@@ -9,6 +9,10 @@ pub const THREAD_QUEUE_TIMEOUT: std::time::Duration = std::time::Duration::from_
 pub const THREAD_SUPERVISOR_SLEEP_TIME: std::time::Duration = std::time::Duration::from_millis(200);
 pub const MAX_THREAD_JOIN_TRIES: u32 = 10;
 pub const MAX_PACKET_SIZE: usize = 1024;
+/// room left for the packet framing, the iv and the tag
+pub const PADDED_PACKET_LEN: usize = MAX_PACKET_SIZE - 64;
+pub const PADDING_BUCKETS: [usize; 5] = [64, 128, 256, 512, PADDED_PACKET_LEN];
+pub const PADDING_MARKER: u8 = 0x80;
 pub const MAX_FIND_TTL: u8 = 10;
 pub const VOICE_ENDED_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(1000);
 /// Must be one of 120, 240, 480, 960, 1920, and 2880. For 120 and 240 the encoder can't use LPC or hybrid modes.
@@ -17,6 +21,26 @@ pub const VOICE_TRANSMISSION_SAMPLE_RATE: usize = 48000;
 pub const VOICE_TRANSMISSION_BITRATE: opus::Bitrate = opus::Bitrate::Max;
 pub const VOICE_MAX_TRANSMISSION_SIZE: usize = 512;
 pub const UPDATE_UI_INTERVAL: std::time::Duration = std::time::Duration::from_millis(120);
+/// typing notifications are sent at most this often
+pub const TYPING_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3);
+/// how often the messages are checked against the disappearing timers
+pub const EXPIRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+/// how often the queued messages, the read receipts, the groups and the timers are sent to the connected contacts
+pub const ANNOUNCE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+/// hooks still running after this are killed
+/// identity key changes the user didn't decide on are forgotten after this, they are asked again on the next connection
+pub const KEY_CHANGE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+pub const HOOK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+/// events waiting for a busy hook, the ones after are dropped
+pub const HOOK_QUEUE_SIZE: usize = 4;
+/// instructions a plugin can run for each event or command before it's stopped
+pub const PLUGIN_FUEL: u64 = 50_000_000;
+pub const PLUGIN_MAX_MEMORY: usize = 16 * 1024 * 1024;
+/// a plugin that goes over these is stopped
+pub const PLUGIN_MAX_ACTIONS: usize = 64;
+pub const PLUGIN_MAX_PANELS: usize = 8;
+pub const PLUGIN_MAX_STRING_LEN: usize = 64 * 1024;
+pub const TYPING_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
 pub const MIN_GAIN: i32 = -32768;
 pub const MAX_GAIN: i32 = 32767;
 
@@ -27,11 +51,60 @@ pub const SYMMETRIC_ALGORITHM: fn() -> openssl::symm::Cipher = openssl::symm::Ci
 pub const SYMMETRIC_ALGORITHM_KEY_LEN: usize = 32;
 pub const SYMMETRIC_ALGORITHM_IV_LEN: usize = 12;
 pub const SYMMETRIC_ALGORITHM_TAG_LEN: usize = 16;
+pub const RATCHET_MAX_SKIPPED_MESSAGES: u32 = 256;
+pub const FINGERPRINT_DISPLAY_LEN: usize = 16;
+pub const SAFETY_NUMBER_CHUNKS: usize = 6;
+pub const MAX_KEY_ROTATIONS: usize = 8;
+pub const KEY_ROTATION_CONTEXT: &[u8] = b"mokaccino key rotation";
+pub const INTRODUCTION_CONTEXT: &[u8] = b"mokaccino introduction";
+pub const REJECTION_CONTEXT: &[u8] = b"mokaccino rejection";
+pub const PSK_CONTEXT: &[u8] = b"mokaccino psk";
+pub const GROUP_CONTEXT: &[u8] = b"mokaccino group";
+pub const PASSPHRASE_SALT_LEN: usize = 16;
+pub const SCRYPT_N: u64 = 1 << 15;
+pub const SCRYPT_R: u64 = 8;
+pub const SCRYPT_P: u64 = 1;
+pub const SCRYPT_MAX_MEM: u64 = 64 * 1024 * 1024;
 
 pub const CONFIG_PATH: &str = "config.toml";
+pub const HISTORY_PATH: &str = "history";
+pub const OUTBOX_PATH: &str = "outbox";
+pub const PLUGINS_PATH: &str = "plugins";
+/// paths in the config of what is encrypted when only the secrets are protected, "*" is every key of a table
+pub const CONFIG_SECRETS: &[&[&str]] = &[
+    &["network", "private_key"],
+    &["network", "prekey"],
+    &["network", "psk"],
+    &["text", "ratchet_sessions"],
+    &["text", "history_key"],
+    &["text", "groups", "*", "key"],
+];
+pub const AUDIT_TRAIL_PATH: &str = "security_audit.log";
+pub const IDENTITY_EXPORT_PATH: &str = "identity.pem";
+pub const INVITE_SCHEME: &str = "mokaccino://";
+pub const INVITE_FINGERPRINT_LEN: usize = 32;
+pub const INVITE_TOKEN_LEN: usize = 16;
+pub const INVITE_ROUTE_PROBE: (std::net::Ipv4Addr, u16) = (std::net::Ipv4Addr::new(192, 0, 2, 1), 9);
+pub const DEFAULT_MAX_STRANGERS_PER_MINUTE: u32 = 10;
+pub const DEFAULT_COVER_TRAFFIC_MS: u64 = 5000;
+pub const CHAT_PAGE_SIZE: usize = 100;
+/// lines of the message composer shown before it starts scrolling
+pub const COMPOSER_MAX_ROWS: usize = 6;
+pub const REPLY_PREVIEW_LEN: usize = 40;
+pub const SEARCH_RESULTS_LIMIT: usize = 50;
+/// words shown before and after the match in a search result
+pub const SEARCH_SNIPPET_WORDS: usize = 5;
+pub const COMMAND_USAGE: &str = "export <contact|#group|system> <file.json|file.html|file.txt>";
+pub const REACTIONS: &[&str] = &["👍", "❤", "😂", "😮", "😢", "🙏"];
+/// longer reactions are ignored, an emoji can take a few chars
+pub const MAX_REACTION_LEN: usize = 8;
+/// longest message in bytes, the rest of a packet goes to the headers of a group text sealed with a new P-521 ratchet session
+pub const MAX_TEXT_LEN: usize = 400;
+pub const DEFAULT_HISTORY_RETENTION_DAYS: u32 = 30;
 
 pub const LOG_COMMAND_COLOR: Color32 = Color32::from_rgb(60, 255, 60);
 pub const LOG_ERROR_COLOR: Color32 = Color32::from_rgb(255, 60, 60);
+pub const LOG_SECURITY_COLOR: Color32 = Color32::from_rgb(255, 170, 30);
 
 pub const ACCENT_COLOR_DARK: Color32 = Color32::from_rgb(116, 77, 169);
 pub const ACCENT_COLOR_LIGHT: Color32 = Color32::from_rgb(146, 89, 209);
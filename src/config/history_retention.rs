@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use serde::{Serialize, Deserialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryRetention
+{
+    /// Messages are never written to disk
+    Off,
+    Forever,
+    Days(u32),
+}
+
+impl HistoryRetention
+{
+    pub fn max_age(&self) -> Option<Duration>
+    {
+        match self
+        {
+            Self::Days(days) => Some(Duration::from_secs(*days as u64 * 24 * 60 * 60)),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> String
+    {
+        match self
+        {
+            Self::Off => "Off".to_string(),
+            Self::Forever => "Forever".to_string(),
+            Self::Days(days) => format!("{} days", days),
+        }
+    }
+}
+
+impl Default for HistoryRetention
+{
+    fn default() -> Self
+    {
+        Self::Forever
+    }
+}
@@ -0,0 +1,18 @@
+use serde::{Serialize, Deserialize};
+
+use crate::hooks::HookEventKind;
+
+/// An external command run on some events, it reads the event as JSON from stdin and writes actions to stdout
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hook
+{
+    pub command: String,
+    #[serde(default = "Hook::default_args")]
+    pub args: Vec<String>,
+    pub events: Vec<HookEventKind>,
+}
+
+impl Hook
+{
+    fn default_args() -> Vec<String> { Vec::new() }
+}
@@ -0,0 +1,114 @@
+use std::str::FromStr;
+
+use crate::crypto::PrivateKey;
+
+use super::{Config, ConfigProtection, ProtectionMode};
+
+/// A config file protected by a passphrase that was not unlocked yet
+#[derive(Clone, Debug)]
+pub struct LockedConfig
+{
+    table: toml::Table,
+    mode: ProtectionMode,
+    salt: Vec<u8>,
+    data: String,
+}
+
+impl LockedConfig
+{
+    pub fn from_table(table: toml::Table) -> Result<Self,String>
+    {
+        let protection = table.get("protection")
+            .and_then(|protection| protection.as_table())
+            .ok_or("Missing protection table")?;
+        let mode = protection.get("mode")
+            .ok_or("Missing protection mode")?
+            .clone().try_into::<ProtectionMode>().map_err(|e| e.to_string())?;
+        let salt = protection.get("salt").and_then(|salt| salt.as_str()).ok_or("Missing protection salt")?;
+        let salt = openssl::base64::decode_block(salt).map_err(|e| e.to_string())?;
+        let data = protection.get("data").and_then(|data| data.as_str()).ok_or("Missing protected data")?.to_string();
+        Ok(Self { table, mode, salt, data })
+    }
+
+    pub fn unlock(&self, passphrase: &str) -> Result<Config,String>
+    {
+        let protection = ConfigProtection::with_salt(passphrase, self.mode, &self.salt)?;
+        let data = protection.open(&self.data)?;
+        let data = String::from_utf8(data).map_err(|e| e.to_string())?;
+        let mut config = match self.mode
+        {
+            ProtectionMode::PrivateKey =>
+            {
+                let mut table = self.table.clone();
+                table.remove("protection");
+                let secrets = match toml::from_str::<toml::Table>(&data)
+                {
+                    Ok(secrets) => secrets,
+                    // older configs only sealed the identity key
+                    Err(_) =>
+                    {
+                        let private_key = PrivateKey::from_str(&data).map_err(|e| e.to_string())?;
+                        let mut network = toml::Table::new();
+                        network.insert("private_key".to_string(), toml::Value::String(private_key.to_string()));
+                        let mut secrets = toml::Table::new();
+                        secrets.insert("network".to_string(), toml::Value::Table(network));
+                        secrets
+                    },
+                };
+                Config::restore_secrets(&mut table, secrets);
+                table.try_into::<Config>().map_err(|e| e.to_string())?
+            },
+            ProtectionMode::Full => toml::from_str(&data).map_err(|e| e.to_string())?,
+        };
+        config.network.migrate_whitelist();
+        config.protection = Some(protection);
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::{config::StoredConfig, text::{Group, GroupMember}};
+
+    use super::*;
+
+    #[test]
+    fn protected_config_roundtrip()
+    {
+        for mode in [ProtectionMode::PrivateKey, ProtectionMode::Full]
+        {
+            let path = std::env::temp_dir().join(format!("mokaccino_protected_{:x}.toml", rand::random::<u64>()));
+            let path = path.to_str().unwrap();
+            let mut config = Config::default();
+            config.network.psk = Some("closed network".to_string());
+            let owner = GroupMember::new(&config.network.name, &config.network.private_key.public_key());
+            let group = Group::new("team", owner);
+            config.text.groups.insert(group.info.id.clone(), group.clone());
+            config.protection = Some(ConfigProtection::new("passphrase", mode).unwrap());
+            config.to_file(path).unwrap();
+            let file = std::fs::read_to_string(path).unwrap();
+            assert!(!file.contains(&config.network.private_key.to_string()));
+            assert!(!file.contains(&config.network.prekey.to_string()));
+            assert!(!file.contains("closed network"));
+            assert!(!file.contains("history_key"));
+            assert!(!file.contains(&group.key.to_string()));
+            let locked = match StoredConfig::from_file(path).unwrap()
+            {
+                StoredConfig::Locked(locked) => locked,
+                StoredConfig::Unlocked(_) => panic!("The config was saved in clear"),
+            };
+            std::fs::remove_file(path).unwrap();
+            assert!(locked.unlock("wrong").is_err());
+            let unlocked = locked.unlock("passphrase").unwrap();
+            assert_eq!(unlocked.network.name, config.network.name);
+            assert_eq!(unlocked.network.private_key.to_string(), config.network.private_key.to_string());
+            assert_eq!(unlocked.network.prekey.to_string(), config.network.prekey.to_string());
+            assert_eq!(unlocked.network.psk, config.network.psk);
+            assert!(unlocked.text.history_key == config.text.history_key);
+            assert!(unlocked.text.groups[&group.info.id].key == group.key);
+            assert_eq!(unlocked.text.groups[&group.info.id].info, group.info);
+            assert_eq!(unlocked.protection.map(|protection| protection.mode), Some(mode));
+        }
+    }
+}
@@ -1,8 +1,32 @@
 pub mod config;
 pub mod network_config;
 pub mod voice_config;
+pub mod text_config;
+pub mod protection_mode;
+pub mod config_protection;
+pub mod locked_config;
+pub mod stored_config;
+pub mod acl;
+pub mod acl_rule;
+pub mod acl_pattern;
+pub mod acl_action;
+pub mod padding_policy;
+pub mod history_retention;
+pub mod hook;
 pub mod defines;
 
 pub use config::Config;
 pub use network_config::NetworkConfig;
-pub use voice_config::VoiceConfig;
\ No newline at end of file
+pub use voice_config::VoiceConfig;
+pub use text_config::TextConfig;
+pub use protection_mode::ProtectionMode;
+pub use config_protection::ConfigProtection;
+pub use locked_config::LockedConfig;
+pub use stored_config::StoredConfig;
+pub use acl::Acl;
+pub use acl_rule::AclRule;
+pub use acl_pattern::AclPattern;
+pub use acl_action::AclAction;
+pub use padding_policy::PaddingPolicy;
+pub use history_retention::HistoryRetention;
+pub use hook::Hook;
\ No newline at end of file
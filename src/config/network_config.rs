@@ -2,7 +2,9 @@ use std::collections::HashMap;
 
 use serde::{Serialize, Deserialize};
 
-use crate::{crypto::PrivateKey, network::LastingContactInfo};
+use crate::{crypto::{PrivateKey, KeyRotation, CipherSuite, SymmetricKey}, network::LastingContactInfo};
+
+use super::{defines, Acl, AclRule, AclPattern, AclAction, PaddingPolicy};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NetworkConfig
@@ -11,28 +13,108 @@ pub struct NetworkConfig
     pub name: String,
     #[serde(default = "NetworkConfig::default_port")]
     pub port: u16,
-    pub whitelist: Option<Vec<String>>,
+    /// replaced by the acl, only read to migrate old configs
+    #[serde(default, skip_serializing)]
+    whitelist: Option<Vec<String>>,
+    #[serde(default)]
+    pub acl: Acl,
     #[serde(default = "NetworkConfig::default_timeout_ms")]
     pub timeout_ms: u64,
     #[serde(default = "NetworkConfig::default_ping_ms")]
     pub ping_ms: u64,
     #[serde(default = "NetworkConfig::default_timeout_strikes")]
     pub timeout_strikes: u16,
-    #[serde(default = "NetworkConfig::default_private_key")]
+    #[serde(default = "NetworkConfig::default_identity_key")]
     pub private_key: PrivateKey,
+    #[serde(default = "NetworkConfig::default_prekey")]
+    pub prekey: PrivateKey,
+    /// accepted suites in order of preference
+    #[serde(default = "NetworkConfig::default_cipher_suites")]
+    pub cipher_suites: Vec<CipherSuite>,
+    #[serde(default = "NetworkConfig::default_key_rotations")]
+    pub key_rotations: Vec<KeyRotation>,
     #[serde(default = "NetworkConfig::default_known_hosts")]
     pub known_hosts: HashMap<String,LastingContactInfo>,
+    /// fingerprints from invites of peers we never met, checked on the first handshake
+    #[serde(default = "NetworkConfig::default_pinned_fingerprints")]
+    pub pinned_fingerprints: HashMap<String,String>,
+    #[serde(default)]
+    pub padding: PaddingPolicy,
+    /// average time between dummy packets sent to each peer while idle
+    pub cover_traffic_ms: Option<u64>,
+    /// shared by the members of a closed network, peers without it can't connect
+    pub psk: Option<String>,
+    /// one-time tokens of the invites we created
+    #[serde(default = "NetworkConfig::default_invite_tokens")]
+    pub invite_tokens: Vec<String>,
 }
 
 impl NetworkConfig
 {
+    /// Turns the names of the old whitelist into acl rules, other peers are still ignored
+    pub fn migrate_whitelist(&mut self)
+    {
+        if let Some(whitelist) = self.whitelist.take()
+        {
+            self.acl.rules.extend(whitelist.into_iter().map(|name| AclRule::new(AclPattern::Name(name), AclAction::Accept)));
+            self.acl.default_action = AclAction::Drop;
+        }
+    }
+
+    pub fn psk_key(&self) -> Option<SymmetricKey>
+    {
+        self.psk.as_ref()
+            .filter(|psk| !psk.is_empty())
+            .map(|psk| SymmetricKey::from_shared_secret(&[defines::PSK_CONTEXT, psk.as_bytes()].concat()))
+    }
+
+    /// Replaces the identity key with a new one certified by the old one
+    pub fn rotate_private_key(&mut self)
+    {
+        let private_key = self.cipher_suites.first().copied().unwrap_or_default().generate_identity_key();
+        self.key_rotations.push(KeyRotation::new(&self.private_key, &private_key.public_key()));
+        if self.key_rotations.len() > defines::MAX_KEY_ROTATIONS
+        {
+            self.key_rotations.remove(0);
+        }
+        self.private_key = private_key;
+        self.match_prekey();
+    }
+
+    /// Replaces the identity key with one moved from another machine
+    pub fn import_private_key(&mut self, private_key: PrivateKey)
+    {
+        // the rotations are only useful if they lead to the imported key
+        if self.key_rotations.last().map(|rotation| rotation.new_key != private_key.public_key()).unwrap_or(false)
+        {
+            self.key_rotations.clear();
+        }
+        self.private_key = private_key;
+        self.match_prekey();
+    }
+
+    /// The prekey stands in for signing-only identities in the key agreement, it must use the same suite
+    fn match_prekey(&mut self)
+    {
+        let cipher_suite = self.private_key.cipher_suite();
+        if self.prekey.cipher_suite() != cipher_suite
+        {
+            self.prekey = cipher_suite.generate_exchange_key();
+        }
+    }
+
     fn default_name() -> String { format!("Anon#{:x}", rand::random::<u64>()) }
     fn default_port() -> u16 { 4848 }
     fn default_timeout_ms() -> u64 { 100 }
     fn default_ping_ms() -> u64 { 1000 }
     fn default_timeout_strikes() -> u16 { 10 }
-    fn default_private_key() -> PrivateKey { PrivateKey::new() }
+    fn default_identity_key() -> PrivateKey { CipherSuite::all()[0].generate_identity_key() }
+    fn default_prekey() -> PrivateKey { CipherSuite::all()[0].generate_exchange_key() }
+    fn default_cipher_suites() -> Vec<CipherSuite> { CipherSuite::all() }
+    fn default_key_rotations() -> Vec<KeyRotation> { Vec::new() }
     fn default_known_hosts() -> HashMap<String,LastingContactInfo> { HashMap::new() }
+    fn default_pinned_fingerprints() -> HashMap<String,String> { HashMap::new() }
+    fn default_invite_tokens() -> Vec<String> { Vec::new() }
 }
 
 impl Default for NetworkConfig
@@ -42,11 +124,20 @@ impl Default for NetworkConfig
             name: NetworkConfig::default_name(),
             port: NetworkConfig::default_port(),
             whitelist: None,
+            acl: Acl::default(),
             timeout_ms: NetworkConfig::default_timeout_ms(),
             ping_ms: NetworkConfig::default_ping_ms(),
             timeout_strikes: NetworkConfig::default_timeout_strikes(),
-            private_key: NetworkConfig::default_private_key(),
+            private_key: NetworkConfig::default_identity_key(),
+            prekey: NetworkConfig::default_prekey(),
+            cipher_suites: NetworkConfig::default_cipher_suites(),
+            key_rotations: NetworkConfig::default_key_rotations(),
             known_hosts: NetworkConfig::default_known_hosts(),
+            pinned_fingerprints: NetworkConfig::default_pinned_fingerprints(),
+            invite_tokens: NetworkConfig::default_invite_tokens(),
+            psk: None,
+            padding: PaddingPolicy::default(),
+            cover_traffic_ms: None,
         }
     }
 }
\ No newline at end of file
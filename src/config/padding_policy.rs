@@ -0,0 +1,112 @@
+use serde::{Serialize, Deserialize};
+
+use super::defines;
+
+/// How encrypted packets are padded before encryption, so their length tells less about their content
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaddingPolicy
+{
+    /// Packets are sent as they are
+    None,
+    /// Padded to the next of a few fixed sizes
+    Buckets,
+    /// Every packet is padded to the biggest size that fits in a datagram
+    Mtu,
+}
+
+impl PaddingPolicy
+{
+    pub fn all() -> Vec<Self>
+    {
+        vec![Self::None, Self::Buckets, Self::Mtu]
+    }
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Self::None => "None",
+            Self::Buckets => "Buckets",
+            Self::Mtu => "Pad to MTU",
+        }
+    }
+
+    /// Length of the padded data, the data is left as it is when no size fits it and the marker
+    pub fn padded_len(&self, len: usize) -> usize
+    {
+        let padded_len = match self
+        {
+            Self::None => None,
+            Self::Buckets => defines::PADDING_BUCKETS.iter().copied().find(|bucket| *bucket > len),
+            Self::Mtu => Some(defines::PADDED_PACKET_LEN).filter(|padded_len| *padded_len > len),
+        };
+        padded_len.unwrap_or(len)
+    }
+
+    /// ISO/IEC 7816-4 padding: a marker byte followed by zeros, nothing at all if the data is not padded
+    pub fn pad(&self, mut data: Vec<u8>) -> Vec<u8>
+    {
+        let padded_len = self.padded_len(data.len());
+        if padded_len > data.len()
+        {
+            data.push(defines::PADDING_MARKER);
+            data.resize(padded_len, 0);
+        }
+        data
+    }
+
+    /// Checks what follows the data, the data itself knows its own length
+    pub fn check_padding(padding: &[u8]) -> Result<(),String>
+    {
+        match padding.split_first()
+        {
+            None => Ok(()),
+            Some((marker, zeros)) if *marker == defines::PADDING_MARKER && zeros.iter().all(|b| *b == 0) => Ok(()),
+            _ => Err("Invalid padding".to_string()),
+        }
+    }
+}
+
+impl Default for PaddingPolicy
+{
+    fn default() -> Self
+    {
+        Self::Buckets
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use serializable::Serializable;
+
+    use crate::{crypto::SymmetricKey, network::SecurePacket};
+
+    use super::*;
+
+    #[test]
+    fn padding_round_trip()
+    {
+        for policy in PaddingPolicy::all()
+        {
+            for len in [0, 1, 63, 64, 500, defines::PADDED_PACKET_LEN - 1, defines::PADDED_PACKET_LEN, defines::MAX_PACKET_SIZE]
+            {
+                let data = vec![1u8; len];
+                let padded = policy.pad(data.clone());
+                assert_eq!(padded.len(), policy.padded_len(len));
+                assert_eq!(&padded[..len], data.as_slice());
+                PaddingPolicy::check_padding(&padded[len..]).unwrap();
+            }
+        }
+        // data that no size fits is not made any longer
+        assert_eq!(PaddingPolicy::None.padded_len(100), 100);
+        assert_eq!(PaddingPolicy::Buckets.padded_len(defines::PADDED_PACKET_LEN), defines::PADDED_PACKET_LEN);
+        assert!(PaddingPolicy::check_padding(&[1, 2, 0, 0]).is_err());
+        assert!(PaddingPolicy::check_padding(&[defines::PADDING_MARKER, 0, 1]).is_err());
+        // the biggest padded packet still fits in a datagram
+        let padded = PaddingPolicy::Mtu.pad(Vec::new());
+        let secure_packet = SecurePacket::Ciphertext(SymmetricKey::random().encrypt(&padded));
+        assert!(secure_packet.serialize().len() <= defines::MAX_PACKET_SIZE);
+    }
+}
@@ -0,0 +1,11 @@
+use serde::{Serialize, Deserialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProtectionMode
+{
+    /// The keys and the other secrets are encrypted, the contacts and the settings stay readable
+    PrivateKey,
+    /// The whole config is encrypted
+    Full,
+}
@@ -0,0 +1,28 @@
+use super::{Config, LockedConfig};
+
+/// What was found in a config file, a locked config needs a passphrase before it can be used
+pub enum StoredConfig
+{
+    Unlocked(Config),
+    Locked(LockedConfig),
+}
+
+impl StoredConfig
+{
+    pub fn from_file(path: &str) -> Result<Self,String>
+    {
+        let file = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let table: toml::Table = toml::from_str(&file).map_err(|e| e.to_string())?;
+        if table.contains_key("protection")
+        {
+            Ok(Self::Locked(LockedConfig::from_table(table)?))
+        }
+        else
+        {
+            //read toml config
+            let mut config: Config = table.try_into().map_err(|e: toml::de::Error| e.to_string())?;
+            config.network.migrate_whitelist();
+            Ok(Self::Unlocked(config))
+        }
+    }
+}
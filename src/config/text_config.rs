@@ -0,0 +1,68 @@
+use std::{collections::HashMap, time::SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{RatchetSession, SymmetricKey};
+
+use crate::text::{Group, DisappearingTimer};
+
+use super::HistoryRetention;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TextConfig
+{
+    #[serde(default = "TextConfig::default_ratchet_sessions")]
+    pub ratchet_sessions: HashMap<String,RatchetSession>,
+    /// encrypts the chat history and the outbox on disk, it's only protected when the config is
+    #[serde(default = "TextConfig::default_history_key")]
+    pub history_key: SymmetricKey,
+    #[serde(default)]
+    pub history_retention: HistoryRetention,
+    /// overrides of the history retention for single contacts
+    #[serde(default = "TextConfig::default_contact_history_retention")]
+    pub contact_history_retention: HashMap<String,HistoryRetention>,
+    /// tells the peers when their messages are shown
+    #[serde(default = "TextConfig::default_read_receipts")]
+    pub read_receipts: bool,
+    /// groups we are a member of by id
+    #[serde(default = "TextConfig::default_groups")]
+    pub groups: HashMap<String,Group>,
+    /// contacts whose messages are removed after a while
+    #[serde(default = "TextConfig::default_disappearing")]
+    pub disappearing: HashMap<String,DisappearingTimer>,
+    /// when the timer of each contact was last changed by either side, the latest change wins
+    #[serde(default = "TextConfig::default_disappearing_changed")]
+    pub disappearing_changed: HashMap<String,SystemTime>,
+}
+
+impl TextConfig
+{
+    pub fn history_retention_for(&self, name: &str) -> HistoryRetention
+    {
+        self.contact_history_retention.get(name).copied().unwrap_or(self.history_retention)
+    }
+
+    fn default_ratchet_sessions() -> HashMap<String,RatchetSession> { HashMap::new() }
+    fn default_history_key() -> SymmetricKey { SymmetricKey::random() }
+    fn default_contact_history_retention() -> HashMap<String,HistoryRetention> { HashMap::new() }
+    fn default_read_receipts() -> bool { true }
+    fn default_groups() -> HashMap<String,Group> { HashMap::new() }
+    fn default_disappearing() -> HashMap<String,DisappearingTimer> { HashMap::new() }
+    fn default_disappearing_changed() -> HashMap<String,SystemTime> { HashMap::new() }
+}
+
+impl Default for TextConfig
+{
+    fn default() -> Self {
+        Self { 
+            ratchet_sessions: TextConfig::default_ratchet_sessions(),
+            history_key: TextConfig::default_history_key(),
+            history_retention: HistoryRetention::default(),
+            contact_history_retention: TextConfig::default_contact_history_retention(),
+            read_receipts: TextConfig::default_read_receipts(),
+            groups: TextConfig::default_groups(),
+            disappearing: TextConfig::default_disappearing(),
+            disappearing_changed: TextConfig::default_disappearing_changed(),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use serde::{Serialize, Deserialize};
+use serializable::Serializable;
+
+use crate::config::defines;
+
+use super::PrivateKey;
+
+/// Algorithms used for a connection, the peers agree on one during the handshake
+#[derive(Serializable, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CipherSuite
+{
+    /// secp521r1 ECDH and ECDSA with AES-256-GCM
+    P521Aes256Gcm,
+    /// X25519 key exchange and Ed25519 signatures with ChaCha20-Poly1305, faster without AES-NI
+    X25519Ed25519ChaCha20Poly1305,
+}
+
+impl CipherSuite
+{
+    pub fn all() -> Vec<Self>
+    {
+        vec![Self::X25519Ed25519ChaCha20Poly1305, Self::P521Aes256Gcm]
+    }
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Self::P521Aes256Gcm => "P-521 / AES-256-GCM",
+            Self::X25519Ed25519ChaCha20Poly1305 => "X25519 / Ed25519 / ChaCha20-Poly1305",
+        }
+    }
+
+    pub fn generate_exchange_key(&self) -> PrivateKey
+    {
+        match self
+        {
+            Self::P521Aes256Gcm => PrivateKey::new(),
+            Self::X25519Ed25519ChaCha20Poly1305 => PrivateKey::from_pkey(openssl::pkey::PKey::generate_x25519().unwrap()),
+        }
+    }
+
+    pub fn generate_identity_key(&self) -> PrivateKey
+    {
+        match self
+        {
+            Self::P521Aes256Gcm => PrivateKey::new(),
+            Self::X25519Ed25519ChaCha20Poly1305 => PrivateKey::from_pkey(openssl::pkey::PKey::generate_ed25519().unwrap()),
+        }
+    }
+
+    pub fn symmetric_algorithm(&self) -> openssl::symm::Cipher
+    {
+        match self
+        {
+            Self::P521Aes256Gcm => defines::SYMMETRIC_ALGORITHM(),
+            Self::X25519Ed25519ChaCha20Poly1305 => openssl::symm::Cipher::chacha20_poly1305(),
+        }
+    }
+
+    pub(super) fn of_key_id(id: openssl::pkey::Id) -> Self
+    {
+        match id
+        {
+            openssl::pkey::Id::X25519 | openssl::pkey::Id::ED25519 => Self::X25519Ed25519ChaCha20Poly1305,
+            _ => Self::P521Aes256Gcm,
+        }
+    }
+
+    /// The first suite offered by the initiator that we also accept
+    pub fn negotiate(offered: &[Self], accepted: &[Self]) -> Option<Self>
+    {
+        offered.iter().find(|suite| accepted.contains(suite)).copied()
+    }
+}
+
+impl Default for CipherSuite
+{
+    fn default() -> Self
+    {
+        Self::P521Aes256Gcm
+    }
+}
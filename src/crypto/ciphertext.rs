@@ -2,11 +2,11 @@ use std::error::Error;
 
 use serializable::Serializable;
 
-use crate::{config::defines, network::Packet};
+use crate::{config::{defines, PaddingPolicy}, network::Packet};
 
 use super::SymmetricKey;
 
-#[derive(Serializable, Clone)]
+#[derive(Serializable, Clone, Debug, PartialEq)]
 pub struct Ciphertext {
     pub ciphertext: Vec<u8>,
     pub iv: [u8; defines::SYMMETRIC_ALGORITHM_IV_LEN],
@@ -15,23 +15,21 @@ pub struct Ciphertext {
 
 impl Ciphertext 
 {
-    pub fn from_packet(packet: Packet, key: &SymmetricKey) -> Self
+    /// Also returns how many bytes of padding were added
+    pub fn from_packet(packet: Packet, key: &SymmetricKey, padding: PaddingPolicy) -> (Self, usize)
     {
         let plaintext = packet.serialize();
-        key.encrypt(&plaintext)
+        let len = plaintext.len();
+        let plaintext = padding.pad(plaintext);
+        let padding_len = plaintext.len() - len;
+        (key.encrypt(&plaintext), padding_len)
     }
 
     pub fn to_packet(self, key: &SymmetricKey) -> Result<Packet, Box<dyn Error>>
     {
         let plaintext = key.decrypt(&self)?;
         let (packet, len) = Packet::deserialize(&plaintext)?;
-        if len != plaintext.len()
-        {
-            Err(std::io::Error::new(std::io::ErrorKind::InvalidData,"Packet length mismatch").into())
-        }
-        else 
-        {
-            Ok(packet)
-        }
+        PaddingPolicy::check_padding(&plaintext[len..])?;
+        Ok(packet)
     }
 }
\ No newline at end of file
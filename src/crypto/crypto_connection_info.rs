@@ -2,28 +2,35 @@ use serializable::Serializable;
 
 use crate::config::Config;
 
-use super::{PublicKey, CryptoLastingInfo};
+use super::{PublicKey, CryptoLastingInfo, KeyRotation, KeyRotationStep, EcdheKey};
 
 #[derive(Serializable, Clone, Debug, PartialEq, Eq)]
 pub struct CryptoConnectionInfo
 {
-    pub ecdhe_public_key: PublicKey,
+    /// the initiator offers a key for every suite it accepts, the responder answers with the chosen one
+    pub ecdhe_keys: Vec<EcdheKey>,
     pub public_key: PublicKey,
+    pub prekey: PublicKey,
+    /// lets peers that know one of our old keys follow us to the current one, the oldest steps
+    /// are left out when the handshake would not fit in a packet
+    pub key_rotations: Vec<KeyRotationStep>,
 }
 
 impl CryptoConnectionInfo 
 {
-    pub fn from_config(config: &Config, ecdhe_public_key: PublicKey) -> Self
+    pub fn from_config(config: &Config, ecdhe_keys: Vec<EcdheKey>) -> Self
     {
         Self
         {
-            ecdhe_public_key,
+            ecdhe_keys,
             public_key: config.network.private_key.public_key(),
+            prekey: config.network.prekey.public_key(),
+            key_rotations: KeyRotation::steps(&config.network.key_rotations),
         }
     }
 
     pub fn into_lasting(&self) -> CryptoLastingInfo
     {
-        CryptoLastingInfo::new(&self.public_key)
+        CryptoLastingInfo::with_prekey(&self.public_key, &self.prekey)
     }
 }
\ No newline at end of file
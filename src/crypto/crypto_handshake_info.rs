@@ -1,24 +1,105 @@
 use std::error::Error;
 
-use super::{PrivateKey, PublicKey, SymmetricKey};
+use super::{PrivateKey, SymmetricKey, CipherSuite, EcdheKey};
 
 pub struct CryptoHandshakeInfo
 {
-    pub local_ecdhe_key: PrivateKey,
-    pub remote_ecdhe_key: Option<PublicKey>,
+    /// one key for each offered suite, only the negotiated one is kept by the responder
+    pub local_ecdhe_keys: Vec<(CipherSuite,PrivateKey)>,
+    pub remote_ecdhe_key: Option<EcdheKey>,
 }
 
 impl CryptoHandshakeInfo
 {
-    pub fn derive(&self) -> Result<SymmetricKey,Box<dyn Error>>
+    /// Handshake started by us, the suites are in order of preference
+    pub fn offer(cipher_suites: &[CipherSuite]) -> Self
+    {
+        Self
+        {
+            local_ecdhe_keys: cipher_suites.iter().map(|suite| (*suite, suite.generate_exchange_key())).collect(),
+            remote_ecdhe_key: None,
+        }
+    }
+
+    /// Handshake started by the peer, None if we don't accept any of the offered suites
+    pub fn accept(remote_ecdhe_keys: &[EcdheKey], accepted: &[CipherSuite]) -> Option<Self>
+    {
+        let offered = remote_ecdhe_keys.iter().map(|key| key.cipher_suite).collect::<Vec<_>>();
+        let cipher_suite = CipherSuite::negotiate(&offered, accepted)?;
+        Some(Self
+        {
+            local_ecdhe_keys: vec![(cipher_suite, cipher_suite.generate_exchange_key())],
+            remote_ecdhe_key: remote_ecdhe_keys.iter().find(|key| key.cipher_suite == cipher_suite).cloned(),
+        })
+    }
+
+    /// Picks the key the peer chose among the ones we offered
+    pub fn set_remote(&mut self, remote_ecdhe_keys: &[EcdheKey])
+    {
+        self.remote_ecdhe_key = remote_ecdhe_keys.iter()
+            .find(|key| self.local_ecdhe_keys.iter().any(|(suite,_key)| *suite == key.cipher_suite))
+            .cloned();
+    }
+
+    pub fn public_keys(&self) -> Vec<EcdheKey>
+    {
+        self.local_ecdhe_keys.iter().map(|(suite,key)| EcdheKey { cipher_suite: *suite, public_key: key.public_key() }).collect()
+    }
+
+    /// The negotiated suite, known once the remote key is set
+    pub fn cipher_suite(&self) -> Option<CipherSuite>
+    {
+        self.remote_ecdhe_key.as_ref().map(|key| key.cipher_suite)
+    }
+
+    /// The pre-shared key, if any, is mixed into the result so only peers that know it get the same key
+    pub fn derive(&self, psk: Option<&SymmetricKey>) -> Result<SymmetricKey,Box<dyn Error>>
     {
         if let Some(remote_ecdhe_key) = &self.remote_ecdhe_key
         {
-            Ok(self.local_ecdhe_key.derive(remote_ecdhe_key.clone())?)
+            let (_suite, local_ecdhe_key) = self.local_ecdhe_keys.iter()
+                .find(|(suite,_key)| *suite == remote_ecdhe_key.cipher_suite)
+                .ok_or("No common cipher suite")?;
+            let symmetric_key = local_ecdhe_key.derive(remote_ecdhe_key.public_key.clone())?;
+            let symmetric_key = match psk
+            {
+                Some(psk) => psk.derive_with(&symmetric_key.key),
+                None => symmetric_key,
+            };
+            Ok(symmetric_key.with_cipher_suite(remote_ecdhe_key.cipher_suite))
         }
         else
         {
             Err("Remote ECDHE key not set".into())
         }
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn negotiate_cipher_suite()
+    {
+        for cipher_suite in CipherSuite::all()
+        {
+            let mut initiator = CryptoHandshakeInfo::offer(&CipherSuite::all());
+            let responder = CryptoHandshakeInfo::accept(&initiator.public_keys(), &[cipher_suite]).unwrap();
+            initiator.set_remote(&responder.public_keys());
+            assert_eq!(initiator.cipher_suite(), Some(cipher_suite));
+            assert_eq!(responder.cipher_suite(), Some(cipher_suite));
+            let initiator_key = initiator.derive(None).unwrap();
+            let responder_key = responder.derive(None).unwrap();
+            assert_eq!(initiator_key, responder_key);
+            let psk = SymmetricKey::random();
+            assert_eq!(initiator.derive(Some(&psk)).unwrap(), responder.derive(Some(&psk)).unwrap());
+            assert_ne!(initiator.derive(Some(&psk)).unwrap(), responder.derive(Some(&SymmetricKey::random())).unwrap());
+            assert_eq!(responder_key.decrypt(&initiator_key.encrypt(b"hello")).unwrap(), b"hello");
+        }
+        let only_p521 = CryptoHandshakeInfo::offer(&[CipherSuite::P521Aes256Gcm]);
+        assert!(CryptoHandshakeInfo::accept(&only_p521.public_keys(), &[CipherSuite::X25519Ed25519ChaCha20Poly1305]).is_none());
+    }
+}
@@ -5,10 +5,11 @@ use crate::config;
 
 use super::{PublicKey, CryptoConnectionInfo};
 
-#[derive(Serializable, Clone, Debug, PartialEq, Eq)]
+#[derive(Serializable, Clone, Debug, PartialEq, Eq, Serialize)]
 pub struct CryptoLastingInfo
 {
-    pub public_key: PublicKey
+    pub public_key: PublicKey,
+    pub prekey: Option<PublicKey>,
 }
 
 impl CryptoLastingInfo
@@ -18,24 +19,40 @@ impl CryptoLastingInfo
         Self
         {
             public_key: public_key.clone(),
+            prekey: None,
+        }
+    }
+
+    pub fn with_prekey(public_key: &PublicKey, prekey: &PublicKey) -> Self
+    {
+        Self
+        {
+            public_key: public_key.clone(),
+            prekey: Some(prekey.clone()),
         }
     }
 
     pub fn from_config(config: &config::Config) -> Self
     {
-        Self::new(
+        Self::with_prekey(
             &config.network.private_key.public_key().into(),
+            &config.network.prekey.public_key(),
         )
     }
 }
 
-impl Serialize for CryptoLastingInfo
+/// Older configs stored only the public key as a plain string
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CryptoLastingInfoRepr
 {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where S: serde::Serializer
+    PublicKey(PublicKey),
+    Full
     {
-        Serialize::serialize(&self.public_key, serializer)
-    }
+        public_key: PublicKey,
+        #[serde(default)]
+        prekey: Option<PublicKey>,
+    },
 }
 
 impl<'de> Deserialize<'de> for CryptoLastingInfo
@@ -43,8 +60,11 @@ impl<'de> Deserialize<'de> for CryptoLastingInfo
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where D: serde::Deserializer<'de>
     {
-        let public_key = <PublicKey as Deserialize<'de>>::deserialize(deserializer)?;
-        Ok(Self::new(&public_key))
+        match CryptoLastingInfoRepr::deserialize(deserializer)?
+        {
+            CryptoLastingInfoRepr::PublicKey(public_key) => Ok(Self::new(&public_key)),
+            CryptoLastingInfoRepr::Full { public_key, prekey } => Ok(Self { public_key, prekey }),
+        }
     }
 }
 
@@ -52,6 +72,6 @@ impl From<CryptoConnectionInfo> for CryptoLastingInfo
 {
     fn from(info: CryptoConnectionInfo) -> Self
     {
-        Self::new(&info.public_key)
+        info.into_lasting()
     }
-}
\ No newline at end of file
+}
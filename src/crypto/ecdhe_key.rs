@@ -0,0 +1,11 @@
+use serializable::Serializable;
+
+use super::{CipherSuite, PublicKey};
+
+/// Ephemeral public key offered for a cipher suite during the handshake
+#[derive(Serializable, Clone, Debug, PartialEq, Eq)]
+pub struct EcdheKey
+{
+    pub cipher_suite: CipherSuite,
+    pub public_key: PublicKey,
+}
@@ -0,0 +1,75 @@
+use serde::{Serialize, Deserialize};
+use serializable::Serializable;
+
+use crate::config::defines;
+
+use super::{PublicKey, PrivateKey, KeyRotationStep};
+
+/// Statement signed with an old identity key that certifies the new one
+#[derive(Serializable, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyRotation
+{
+    pub old_key: PublicKey,
+    pub new_key: PublicKey,
+    signature: Vec<u8>,
+}
+
+impl KeyRotation
+{
+    pub fn new(old_key: &PrivateKey, new_key: &PublicKey) -> Self
+    {
+        let old_public_key = old_key.public_key();
+        let signature = old_key.sign(&Self::signed_data(&old_public_key, new_key));
+        Self
+        {
+            old_key: old_public_key,
+            new_key: new_key.clone(),
+            signature,
+        }
+    }
+
+    /// The chain as it's sent in the handshake, it must end with the current identity key
+    pub fn steps(rotations: &[KeyRotation]) -> Vec<KeyRotationStep>
+    {
+        rotations.iter().enumerate().map(|(index, rotation)| KeyRotationStep
+        {
+            new_key: (index + 1 < rotations.len()).then(|| rotation.new_key.clone()),
+            signature: rotation.signature.clone(),
+        }).collect()
+    }
+
+    pub(super) fn signed_data(old_key: &PublicKey, new_key: &PublicKey) -> Vec<u8>
+    {
+        let mut data = defines::KEY_ROTATION_CONTEXT.to_vec();
+        data.extend(Serializable::serialize(old_key));
+        data.extend(Serializable::serialize(new_key));
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn rotation_chain()
+    {
+        let first = PrivateKey::new();
+        let second = PrivateKey::new();
+        let third = PrivateKey::new();
+        let rotations = vec![
+            KeyRotation::new(&first, &second.public_key()),
+            KeyRotation::new(&second, &third.public_key()),
+        ];
+        let steps = KeyRotation::steps(&rotations);
+        assert!(KeyRotationStep::verify_chain(&steps, &first.public_key(), &third.public_key()));
+        assert!(KeyRotationStep::verify_chain(&steps, &second.public_key(), &third.public_key()));
+        // the oldest steps can be left out for the peers that know a newer key
+        assert!(KeyRotationStep::verify_chain(&steps[1..], &second.public_key(), &third.public_key()));
+        assert!(!KeyRotationStep::verify_chain(&steps[1..], &first.public_key(), &third.public_key()));
+        assert!(!KeyRotationStep::verify_chain(&steps, &third.public_key(), &first.public_key()));
+        let forged = KeyRotation::steps(&[KeyRotation::new(&third, &second.public_key())]);
+        assert!(!KeyRotationStep::verify_chain(&forged, &first.public_key(), &second.public_key()));
+    }
+}
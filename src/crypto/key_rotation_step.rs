@@ -0,0 +1,32 @@
+use serializable::Serializable;
+
+use crate::config::defines;
+
+use super::{PublicKey, KeyRotation};
+
+/// A key rotation as it's sent in the handshake. The old key is one the peer already trusts and the new key
+/// of the last step is the identity key presented with it, so they are left out to keep the handshake small
+#[derive(Serializable, Clone, Debug, PartialEq, Eq)]
+pub struct KeyRotationStep
+{
+    pub new_key: Option<PublicKey>,
+    pub signature: Vec<u8>,
+}
+
+impl KeyRotationStep
+{
+    /// Follows the steps starting from a key we trust, true if they lead to the presented key
+    pub fn verify_chain(steps: &[KeyRotationStep], trusted_key: &PublicKey, presented_key: &PublicKey) -> bool
+    {
+        let mut current_key = trusted_key.clone();
+        for step in steps.iter().take(defines::MAX_KEY_ROTATIONS)
+        {
+            let new_key = step.new_key.as_ref().unwrap_or(presented_key);
+            if current_key.verify(&KeyRotation::signed_data(&current_key, new_key), &step.signature)
+            {
+                current_key = new_key.clone();
+            }
+        }
+        current_key == *presented_key
+    }
+}
@@ -1,4 +1,7 @@
 pub mod signed_contact_info;
+pub mod signed_introduction;
+pub mod signed_group_info;
+pub mod signed_rejection;
 pub mod private_key;
 pub mod public_key;
 pub mod symmetric_key;
@@ -8,8 +11,18 @@ pub mod crypto_lasting_info;
 pub mod crypto_handshake_info;
 pub mod crypto_session_info;
 pub mod crypto_connection_info;
+pub mod ratchet_message;
+pub mod ratchet_session;
+pub mod safety_number;
+pub mod key_rotation;
+pub mod key_rotation_step;
+pub mod cipher_suite;
+pub mod ecdhe_key;
 
 pub use signed_contact_info::SignedContactInfo;
+pub use signed_introduction::SignedIntroduction;
+pub use signed_group_info::SignedGroupInfo;
+pub use signed_rejection::SignedRejection;
 pub use private_key::PrivateKey;
 pub use public_key::PublicKey;
 pub use symmetric_key::SymmetricKey;
@@ -18,4 +31,11 @@ pub use random::Random;
 pub use crypto_lasting_info::CryptoLastingInfo;
 pub use crypto_handshake_info::CryptoHandshakeInfo;
 pub use crypto_session_info::CryptoSessionInfo;
-pub use crypto_connection_info::CryptoConnectionInfo;
\ No newline at end of file
+pub use crypto_connection_info::CryptoConnectionInfo;
+pub use ratchet_message::{RatchetHeader, RatchetMessage};
+pub use ratchet_session::{RatchetSession, SkippedMessageKey};
+pub use safety_number::SafetyNumber;
+pub use key_rotation::KeyRotation;
+pub use key_rotation_step::KeyRotationStep;
+pub use cipher_suite::CipherSuite;
+pub use ecdhe_key::EcdheKey;
\ No newline at end of file
@@ -4,7 +4,7 @@ use serde::{Serialize, Deserialize};
 
 use crate::config::defines;
 
-use super::{PublicKey, SymmetricKey};
+use super::{PublicKey, SymmetricKey, CipherSuite};
 
 #[derive(Clone, Debug)]
 pub struct PrivateKey
@@ -19,8 +19,52 @@ impl PrivateKey
         PrivateKey{ key: defines::ASYMMETRIC_KEY_GENERATOR().unwrap() }
     }
 
+    pub(super) fn from_pkey(key: openssl::pkey::PKey<openssl::pkey::Private>) -> Self
+    {
+        PrivateKey{ key }
+    }
+
+    /// Ed25519 keys can only sign
+    pub fn can_agree(&self) -> bool
+    {
+        self.key.id() != openssl::pkey::Id::ED25519
+    }
+
+    /// The suite the algorithm of the key belongs to
+    pub fn cipher_suite(&self) -> CipherSuite
+    {
+        CipherSuite::of_key_id(self.key.id())
+    }
+
+    /// PKCS#8 PEM, encrypted with the passphrase if there is one
+    pub fn to_pem(&self, passphrase: Option<&str>) -> Result<String,Box<dyn Error>>
+    {
+        let pem = match passphrase
+        {
+            Some(passphrase) => self.key.private_key_to_pem_pkcs8_passphrase(openssl::symm::Cipher::aes_256_cbc(), passphrase.as_bytes())?,
+            None => self.key.private_key_to_pem_pkcs8()?,
+        };
+        Ok(String::from_utf8(pem)?)
+    }
+
+    pub fn from_pem(pem: &str, passphrase: Option<&str>) -> Result<Self,Box<dyn Error>>
+    {
+        let key = match passphrase
+        {
+            Some(passphrase) => openssl::pkey::PKey::private_key_from_pem_passphrase(pem.as_bytes(), passphrase.as_bytes())?,
+            None => openssl::pkey::PKey::private_key_from_pem(pem.as_bytes())?,
+        };
+        Ok(PrivateKey{ key })
+    }
+
     pub fn sign(&self, data: &[u8]) -> Vec<u8>
     {
+        if self.key.id() == openssl::pkey::Id::ED25519
+        {
+            // EdDSA hashes the message by itself
+            let mut signer = openssl::sign::Signer::new_without_digest(&self.key).unwrap();
+            return signer.sign_oneshot_to_vec(data).unwrap();
+        }
         let mut signer = openssl::sign::Signer::new(defines::MESSAGE_DIGEST(),&self.key).unwrap();
         signer.update(data).unwrap();
         let signature = signer.sign_to_vec().unwrap();
@@ -36,11 +80,17 @@ impl PrivateKey
 
     pub fn derive(&self, public_key: PublicKey) -> Result<SymmetricKey,Box<dyn Error>>
     {
-        let mut deriver = openssl::derive::Deriver::new(&self.key).unwrap();
-        deriver.set_peer(&public_key.key)?;
-        let shared_secret = deriver.derive_to_vec()?;
+        let shared_secret = self.diffie_hellman(&public_key)?;
         Ok(SymmetricKey::from_shared_secret(&shared_secret))
     }
+
+    /// Raw ECDH output, callers are expected to feed it to a key derivation function
+    pub fn diffie_hellman(&self, public_key: &PublicKey) -> Result<Vec<u8>,Box<dyn Error>>
+    {
+        let mut deriver = openssl::derive::Deriver::new(&self.key)?;
+        deriver.set_peer(&public_key.key)?;
+        Ok(deriver.derive_to_vec()?)
+    }
 }
 
 impl ToString for PrivateKey
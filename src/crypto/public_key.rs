@@ -5,6 +5,8 @@ use serializable::Serializable;
 
 use crate::config::defines;
 
+use super::CipherSuite;
+
 #[derive(Clone, Debug)]
 pub struct PublicKey
 {
@@ -13,9 +15,42 @@ pub struct PublicKey
 
 impl PublicKey 
 {
+    pub fn fingerprint(&self) -> Vec<u8>
+    {
+        let der = self.key.public_key_to_der().unwrap();
+        openssl::hash::hash(defines::MESSAGE_DIGEST(), &der).unwrap().to_vec()
+    }
+
+    /// Short hex form of the fingerprint, meant to be compared by humans
+    pub fn fingerprint_string(&self) -> String
+    {
+        self.fingerprint()[..defines::FINGERPRINT_DISPLAY_LEN]
+            .chunks(2)
+            .map(|chunk| chunk.iter().map(|b| format!("{:02x}", b)).collect::<String>())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Ed25519 keys can only verify
+    pub fn can_agree(&self) -> bool
+    {
+        self.key.id() != openssl::pkey::Id::ED25519
+    }
+
+    /// The suite the algorithm of the key belongs to
+    pub fn cipher_suite(&self) -> CipherSuite
+    {
+        CipherSuite::of_key_id(self.key.id())
+    }
 
     pub fn verify(&self, data: &[u8], signature: &[u8]) -> bool
     {
+        if self.key.id() == openssl::pkey::Id::ED25519
+        {
+            return openssl::sign::Verifier::new_without_digest(&self.key)
+                .and_then(|mut verifier| verifier.verify_oneshot(signature, data))
+                .unwrap_or(false);
+        }
         match openssl::sign::Verifier::new(defines::MESSAGE_DIGEST(),&self.key)
         {
             Ok(mut verifier) => 
@@ -26,9 +61,9 @@ impl PublicKey
                     {
                         match verifier.verify(signature)
                         {
-                            Ok(_) => 
+                            Ok(valid) => 
                             {
-                                true
+                                valid
                             },
                             Err(e) => 
                             {
@@ -112,4 +147,21 @@ impl<'de> Deserialize<'de> for PublicKey
         let s = <String as Deserialize>::deserialize(deserializer)?;
         PublicKey::from_str(&s).map_err(|e| serde::de::Error::custom(e.to_string()))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::crypto::PrivateKey;
+
+    #[test]
+    fn verify()
+    {
+        let private_key = PrivateKey::new();
+        let public_key = private_key.public_key();
+        let signature = private_key.sign(b"data");
+        assert!(public_key.verify(b"data", &signature));
+        assert!(!public_key.verify(b"other data", &signature));
+        assert!(!PrivateKey::new().public_key().verify(b"data", &signature));
+    }
+}
@@ -0,0 +1,20 @@
+use serializable::Serializable;
+
+use super::{PublicKey, Ciphertext};
+
+#[derive(Serializable, Clone, Debug, PartialEq)]
+pub struct RatchetHeader
+{
+    pub ratchet_key: PublicKey,
+    pub previous_chain_length: u32,
+    pub message_number: u32,
+    /// X3DH ephemeral key, present until the receiver has answered at least once
+    pub ephemeral_key: Option<PublicKey>,
+}
+
+#[derive(Serializable, Clone, Debug, PartialEq)]
+pub struct RatchetMessage
+{
+    pub header: RatchetHeader,
+    pub ciphertext: Ciphertext,
+}
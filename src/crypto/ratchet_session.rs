@@ -0,0 +1,338 @@
+use std::error::Error;
+
+use serde::{Serialize, Deserialize};
+use serializable::Serializable;
+
+use crate::config::defines;
+
+use super::{PrivateKey, PublicKey, SymmetricKey, RatchetHeader, RatchetMessage};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SkippedMessageKey
+{
+    pub ratchet_key: PublicKey,
+    pub message_number: u32,
+    pub message_key: SymmetricKey,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RatchetSession
+{
+    root_key: SymmetricKey,
+    sending_ratchet_key: PrivateKey,
+    receiving_ratchet_key: Option<PublicKey>,
+    sending_chain_key: Option<SymmetricKey>,
+    receiving_chain_key: Option<SymmetricKey>,
+    sent_count: u32,
+    received_count: u32,
+    previous_chain_length: u32,
+    #[serde(default)]
+    skipped_message_keys: Vec<SkippedMessageKey>,
+    pending_ephemeral_key: Option<PublicKey>,
+    /// the ephemeral key of the peer this session was answered with, if we are the responder
+    #[serde(default)]
+    remote_ephemeral_key: Option<PublicKey>,
+}
+
+impl RatchetSession
+{
+    /// Whether our keys and the ones of the peer belong to the same suite, a session cannot be set up otherwise
+    pub fn can_initiate(identity_key: &PrivateKey, prekey: &PrivateKey, remote_identity_key: &PublicKey, remote_prekey: &PublicKey) -> bool
+    {
+        let cipher_suite = remote_prekey.cipher_suite();
+        agreement_key(identity_key, prekey).cipher_suite() == cipher_suite &&
+            remote_agreement_key(remote_identity_key, Some(remote_prekey)).is_ok_and(|key| key.cipher_suite() == cipher_suite)
+    }
+
+    /// X3DH as the initiator, the remote prekey is also used as the first remote ratchet key
+    pub fn initiate(identity_key: &PrivateKey, prekey: &PrivateKey, remote_identity_key: &PublicKey, remote_prekey: &PublicKey) -> Result<Self,Box<dyn Error>>
+    {
+        let cipher_suite = remote_prekey.cipher_suite();
+        let ephemeral_key = cipher_suite.generate_exchange_key();
+        let mut shared_secret = agreement_key(identity_key, prekey).diffie_hellman(remote_prekey)?;
+        shared_secret.extend(ephemeral_key.diffie_hellman(remote_agreement_key(remote_identity_key, Some(remote_prekey))?)?);
+        shared_secret.extend(ephemeral_key.diffie_hellman(remote_prekey)?);
+        let sending_ratchet_key = cipher_suite.generate_exchange_key();
+        let (root_key, sending_chain_key) = kdf_root(
+            &SymmetricKey::from_shared_secret(&shared_secret),
+            &sending_ratchet_key.diffie_hellman(remote_prekey)?);
+        Ok(Self
+        {
+            root_key,
+            sending_ratchet_key,
+            receiving_ratchet_key: Some(remote_prekey.clone()),
+            sending_chain_key: Some(sending_chain_key),
+            receiving_chain_key: None,
+            sent_count: 0,
+            received_count: 0,
+            previous_chain_length: 0,
+            skipped_message_keys: Vec::new(),
+            pending_ephemeral_key: Some(ephemeral_key.public_key()),
+            remote_ephemeral_key: None,
+        })
+    }
+
+    /// X3DH as the responder, the session can send only after the first message was decrypted
+    pub fn respond(identity_key: &PrivateKey, prekey: &PrivateKey, remote_identity_key: &PublicKey, remote_prekey: Option<&PublicKey>, remote_ephemeral_key: &PublicKey) -> Result<Self,Box<dyn Error>>
+    {
+        let mut shared_secret = prekey.diffie_hellman(remote_agreement_key(remote_identity_key, remote_prekey)?)?;
+        shared_secret.extend(agreement_key(identity_key, prekey).diffie_hellman(remote_ephemeral_key)?);
+        shared_secret.extend(prekey.diffie_hellman(remote_ephemeral_key)?);
+        Ok(Self
+        {
+            root_key: SymmetricKey::from_shared_secret(&shared_secret),
+            sending_ratchet_key: prekey.clone(),
+            receiving_ratchet_key: None,
+            sending_chain_key: None,
+            receiving_chain_key: None,
+            sent_count: 0,
+            received_count: 0,
+            previous_chain_length: 0,
+            skipped_message_keys: Vec::new(),
+            pending_ephemeral_key: None,
+            remote_ephemeral_key: Some(remote_ephemeral_key.clone()),
+        })
+    }
+
+    /// True if we started this session and the peer has not answered yet
+    pub fn is_pending(&self) -> bool
+    {
+        self.pending_ephemeral_key.is_some()
+    }
+
+    /// True if this session was answered with this ephemeral key, so a message carrying it is not a new session
+    pub fn started_with(&self, ephemeral_key: &PublicKey) -> bool
+    {
+        self.remote_ephemeral_key.as_ref() == Some(ephemeral_key)
+    }
+
+    /// True if a message with this header was already decrypted by this session, so failing to decrypt it again
+    /// doesn't mean the session is out of sync
+    pub fn is_repeated(&self, header: &RatchetHeader) -> bool
+    {
+        header.ephemeral_key.as_ref().is_some_and(|ephemeral_key| self.started_with(ephemeral_key)) ||
+            (self.receiving_ratchet_key.as_ref() == Some(&header.ratchet_key) && header.message_number < self.received_count)
+    }
+
+    pub fn encrypt(&mut self, plaintext: &[u8]) -> Result<RatchetMessage,Box<dyn Error>>
+    {
+        let chain_key = self.sending_chain_key.ok_or("Ratchet session cannot send yet")?;
+        let (next_chain_key, message_key) = kdf_chain(&chain_key);
+        let header = RatchetHeader
+        {
+            ratchet_key: self.sending_ratchet_key.public_key(),
+            previous_chain_length: self.previous_chain_length,
+            message_number: self.sent_count,
+            ephemeral_key: self.pending_ephemeral_key.clone(),
+        };
+        let ciphertext = message_key.encrypt_with_associated_data(plaintext, &header.serialize());
+        self.sending_chain_key = Some(next_chain_key);
+        self.sent_count += 1;
+        Ok(RatchetMessage { header, ciphertext })
+    }
+
+    /// The session is left untouched if the message cannot be authenticated
+    pub fn decrypt(&mut self, message: &RatchetMessage) -> Result<Vec<u8>,Box<dyn Error>>
+    {
+        let mut next = self.clone();
+        let plaintext = next.decrypt_in_place(message)?;
+        next.pending_ephemeral_key = None;
+        *self = next;
+        Ok(plaintext)
+    }
+
+    fn decrypt_in_place(&mut self, message: &RatchetMessage) -> Result<Vec<u8>,Box<dyn Error>>
+    {
+        let header = &message.header;
+        let associated_data = header.serialize();
+        if let Some(index) = self.skipped_message_keys.iter().position(|skipped|
+            skipped.ratchet_key == header.ratchet_key && skipped.message_number == header.message_number)
+        {
+            let skipped = self.skipped_message_keys.remove(index);
+            return skipped.message_key.decrypt_with_associated_data(&message.ciphertext, &associated_data);
+        }
+        if self.receiving_ratchet_key.as_ref() != Some(&header.ratchet_key)
+        {
+            self.skip_message_keys(header.previous_chain_length)?;
+            self.dh_ratchet(&header.ratchet_key)?;
+        }
+        self.skip_message_keys(header.message_number)?;
+        let chain_key = self.receiving_chain_key.ok_or("Ratchet session has no receiving chain")?;
+        let (next_chain_key, message_key) = kdf_chain(&chain_key);
+        self.receiving_chain_key = Some(next_chain_key);
+        self.received_count += 1;
+        message_key.decrypt_with_associated_data(&message.ciphertext, &associated_data)
+    }
+
+    fn skip_message_keys(&mut self, until: u32) -> Result<(),Box<dyn Error>>
+    {
+        if self.received_count + defines::RATCHET_MAX_SKIPPED_MESSAGES < until
+        {
+            return Err("Too many skipped ratchet messages".into());
+        }
+        if let (Some(mut chain_key), Some(ratchet_key)) = (self.receiving_chain_key, self.receiving_ratchet_key.clone())
+        {
+            while self.received_count < until
+            {
+                let (next_chain_key, message_key) = kdf_chain(&chain_key);
+                self.skipped_message_keys.push(SkippedMessageKey { 
+                    ratchet_key: ratchet_key.clone(), 
+                    message_number: self.received_count, 
+                    message_key 
+                });
+                chain_key = next_chain_key;
+                self.received_count += 1;
+            }
+            self.receiving_chain_key = Some(chain_key);
+            let excess = self.skipped_message_keys.len().saturating_sub(defines::RATCHET_MAX_SKIPPED_MESSAGES as usize);
+            self.skipped_message_keys.drain(..excess);
+        }
+        Ok(())
+    }
+
+    fn dh_ratchet(&mut self, remote_ratchet_key: &PublicKey) -> Result<(),Box<dyn Error>>
+    {
+        self.previous_chain_length = self.sent_count;
+        self.sent_count = 0;
+        self.received_count = 0;
+        self.receiving_ratchet_key = Some(remote_ratchet_key.clone());
+        let (root_key, receiving_chain_key) = kdf_root(&self.root_key, &self.sending_ratchet_key.diffie_hellman(remote_ratchet_key)?);
+        self.sending_ratchet_key = remote_ratchet_key.cipher_suite().generate_exchange_key();
+        let (root_key, sending_chain_key) = kdf_root(&root_key, &self.sending_ratchet_key.diffie_hellman(remote_ratchet_key)?);
+        self.root_key = root_key;
+        self.receiving_chain_key = Some(receiving_chain_key);
+        self.sending_chain_key = Some(sending_chain_key);
+        Ok(())
+    }
+}
+
+/// Ed25519 identities cannot take part in a key agreement, their signed prekey stands in for them
+fn agreement_key<'a>(identity_key: &'a PrivateKey, prekey: &'a PrivateKey) -> &'a PrivateKey
+{
+    if identity_key.can_agree() {identity_key} else {prekey}
+}
+
+fn remote_agreement_key<'a>(remote_identity_key: &'a PublicKey, remote_prekey: Option<&'a PublicKey>) -> Result<&'a PublicKey,Box<dyn Error>>
+{
+    if remote_identity_key.can_agree()
+    {
+        Ok(remote_identity_key)
+    }
+    else
+    {
+        Ok(remote_prekey.ok_or("The prekey of the peer is unknown")?)
+    }
+}
+
+fn kdf_root(root_key: &SymmetricKey, dh_output: &[u8]) -> (SymmetricKey, SymmetricKey)
+{
+    let pseudo_random_key = root_key.derive_with(dh_output);
+    (pseudo_random_key.derive_with(&[1]), pseudo_random_key.derive_with(&[2]))
+}
+
+fn kdf_chain(chain_key: &SymmetricKey) -> (SymmetricKey, SymmetricKey)
+{
+    (chain_key.derive_with(&[2]), chain_key.derive_with(&[1]))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::crypto::CipherSuite;
+
+    use super::*;
+
+    fn sessions_with(alice_identity: PrivateKey, bob_identity: PrivateKey) -> (RatchetSession, RatchetSession)
+    {
+        let alice_prekey = alice_identity.cipher_suite().generate_exchange_key();
+        let bob_prekey = bob_identity.cipher_suite().generate_exchange_key();
+        assert!(RatchetSession::can_initiate(&alice_identity, &alice_prekey, &bob_identity.public_key(), &bob_prekey.public_key()));
+        let mut alice = RatchetSession::initiate(&alice_identity, &alice_prekey, &bob_identity.public_key(), &bob_prekey.public_key()).unwrap();
+        let first = alice.encrypt(b"hello").unwrap();
+        let ephemeral_key = first.header.ephemeral_key.clone().unwrap();
+        let mut bob = RatchetSession::respond(&bob_identity, &bob_prekey, &alice_identity.public_key(), Some(&alice_prekey.public_key()), &ephemeral_key).unwrap();
+        assert_eq!(bob.decrypt(&first).unwrap(), b"hello");
+        assert!(bob.started_with(&ephemeral_key));
+        assert!(!alice.started_with(&ephemeral_key));
+        (alice, bob)
+    }
+
+    fn sessions() -> (RatchetSession, RatchetSession)
+    {
+        sessions_with(PrivateKey::new(), PrivateKey::new())
+    }
+
+    #[test]
+    fn ratchet_signing_only_identity()
+    {
+        let cipher_suite = CipherSuite::X25519Ed25519ChaCha20Poly1305;
+        let (mut alice, mut bob) = sessions_with(cipher_suite.generate_identity_key(), cipher_suite.generate_identity_key());
+        let reply = bob.encrypt(b"hi").unwrap();
+        assert_eq!(alice.decrypt(&reply).unwrap(), b"hi");
+        let next = alice.encrypt(b"how are you?").unwrap();
+        // the ratchet keys follow the suite of the peers
+        assert_eq!(next.header.ratchet_key.cipher_suite(), cipher_suite);
+        assert_eq!(bob.decrypt(&next).unwrap(), b"how are you?");
+        let identity_key = cipher_suite.generate_identity_key();
+        let remote_identity_key = PrivateKey::new();
+        assert!(!RatchetSession::can_initiate(
+            &identity_key, &cipher_suite.generate_exchange_key(), 
+            &remote_identity_key.public_key(), &remote_identity_key.public_key()));
+    }
+
+    #[test]
+    fn ratchet_round_trip()
+    {
+        let (mut alice, mut bob) = sessions();
+        let reply = bob.encrypt(b"hi").unwrap();
+        assert!(reply.header.ephemeral_key.is_none());
+        assert_eq!(alice.decrypt(&reply).unwrap(), b"hi");
+        assert!(!alice.is_pending());
+        let next = alice.encrypt(b"how are you?").unwrap();
+        assert!(next.header.ephemeral_key.is_none());
+        assert_eq!(bob.decrypt(&next).unwrap(), b"how are you?");
+    }
+
+    #[test]
+    fn ratchet_out_of_order()
+    {
+        let (mut alice, mut bob) = sessions();
+        let first = alice.encrypt(b"first").unwrap();
+        let second = alice.encrypt(b"second").unwrap();
+        assert_eq!(bob.decrypt(&second).unwrap(), b"second");
+        assert_eq!(bob.decrypt(&first).unwrap(), b"first");
+        assert!(bob.decrypt(&first).is_err());
+        assert!(bob.is_repeated(&first.header));
+    }
+
+    #[test]
+    fn ratchet_lost_sync()
+    {
+        let (mut alice, mut bob) = sessions();
+        let reply = bob.encrypt(b"hi").unwrap();
+        assert_eq!(alice.decrypt(&reply).unwrap(), b"hi");
+        let next = alice.encrypt(b"how are you?").unwrap();
+        assert_eq!(bob.decrypt(&next).unwrap(), b"how are you?");
+        // alice goes back to this state, the ratchet moves on without her
+        let old_alice = alice.clone();
+        let reply = bob.encrypt(b"fine").unwrap();
+        assert_eq!(alice.decrypt(&reply).unwrap(), b"fine");
+        let next = alice.encrypt(b"good").unwrap();
+        assert_eq!(bob.decrypt(&next).unwrap(), b"good");
+        let mut alice = old_alice;
+        let message = alice.encrypt(b"still there?").unwrap();
+        assert!(bob.decrypt(&message).is_err());
+        assert!(!bob.is_repeated(&message.header));
+    }
+
+    #[test]
+    fn ratchet_rejects_tampering()
+    {
+        let (mut alice, mut bob) = sessions();
+        let mut message = alice.encrypt(b"secret").unwrap();
+        message.header.message_number += 1;
+        assert!(bob.decrypt(&message).is_err());
+        message.header.message_number -= 1;
+        assert_eq!(bob.decrypt(&message).unwrap(), b"secret");
+    }
+}
@@ -0,0 +1,76 @@
+use crate::config::defines;
+
+use super::PublicKey;
+
+/// Digits that both peers can compare to check that they see the same pair of identity keys
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SafetyNumber
+{
+    digits: String,
+}
+
+impl SafetyNumber
+{
+    pub fn new(local_key: &PublicKey, remote_key: &PublicKey) -> Self
+    {
+        let mut halves = [Self::digits_for(local_key), Self::digits_for(remote_key)];
+        // sorted so that both peers compute the same number
+        halves.sort();
+        Self { digits: halves.concat() }
+    }
+
+    fn digits_for(key: &PublicKey) -> String
+    {
+        key.fingerprint()
+            .chunks(5)
+            .take(defines::SAFETY_NUMBER_CHUNKS)
+            .map(|chunk| {
+                let value = chunk.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+                format!("{:05}", value % 100000)
+            })
+            .collect()
+    }
+
+    pub fn digits(&self) -> &str
+    {
+        &self.digits
+    }
+
+    /// Groups of five digits, four groups per line
+    pub fn to_lines(&self) -> Vec<String>
+    {
+        let groups = self.digits.as_bytes()
+            .chunks(5)
+            .map(|group| String::from_utf8_lossy(group).to_string())
+            .collect::<Vec<_>>();
+        groups.chunks(4).map(|line| line.join(" ")).collect()
+    }
+}
+
+impl ToString for SafetyNumber
+{
+    fn to_string(&self) -> String {
+        self.to_lines().join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::crypto::PrivateKey;
+
+    use super::*;
+
+    #[test]
+    fn safety_number_is_symmetric()
+    {
+        let alice = PrivateKey::new().public_key();
+        let bob = PrivateKey::new().public_key();
+        let carol = PrivateKey::new().public_key();
+        let alice_view = SafetyNumber::new(&alice, &bob);
+        assert_eq!(alice_view, SafetyNumber::new(&bob, &alice));
+        assert_ne!(alice_view, SafetyNumber::new(&alice, &carol));
+        assert_eq!(alice_view.digits().len(), 2 * 5 * defines::SAFETY_NUMBER_CHUNKS);
+        assert!(alice_view.digits().chars().all(|c| c.is_ascii_digit()));
+    }
+}
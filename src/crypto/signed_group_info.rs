@@ -0,0 +1,52 @@
+use serializable::Serializable;
+
+use crate::{text::GroupInfo, config::defines};
+
+use super::PrivateKey;
+
+/// A membership list signed by the owner of the group
+#[derive(Serializable, Clone, Debug, PartialEq)]
+pub struct SignedGroupInfo
+{
+    info: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl SignedGroupInfo
+{
+    pub fn from_group_info(group_info: &GroupInfo, private_key: &PrivateKey) -> Self
+    {
+        let info = group_info.serialize();
+        let signature = private_key.sign(&Self::signed_data(&info));
+        Self
+        {
+            info,
+            signature,
+        }
+    }
+
+    /// The list must be signed by the owner it names, callers check the owner didn't change
+    pub fn into_group_info(&self) -> std::io::Result<GroupInfo>
+    {
+        let (group_info, len) = GroupInfo::deserialize(&self.info)?;
+        if len != self.info.len()
+        {
+            Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid data length"))
+        }
+        else if group_info.owner.verify(&Self::signed_data(&self.info), &self.signature)
+        {
+            Ok(group_info)
+        }
+        else
+        {
+            Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid signature"))
+        }
+    }
+
+    fn signed_data(info: &[u8]) -> Vec<u8>
+    {
+        let mut data = defines::GROUP_CONTEXT.to_vec();
+        data.extend(info);
+        data
+    }
+}
\ No newline at end of file
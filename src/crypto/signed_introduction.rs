@@ -0,0 +1,77 @@
+use serializable::Serializable;
+
+use crate::{network::LastingContactInfo, config::defines};
+
+use super::{PrivateKey, PublicKey};
+
+/// A contact's identity vouched for by the peer that signed it
+#[derive(Serializable, Clone, Debug, PartialEq)]
+pub struct SignedIntroduction
+{
+    info: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl SignedIntroduction
+{
+    pub fn from_lasting_contact_info(contact_info: &LastingContactInfo, private_key: &PrivateKey) -> Self
+    {
+        // only the name and the keys are vouched for, not our own trust flags
+        let info = LastingContactInfo::new(contact_info.name(), contact_info.crypto_info()).serialize();
+        let signature = private_key.sign(&Self::signed_data(&info));
+        Self
+        {
+            info,
+            signature,
+        }
+    }
+
+    pub fn into_lasting_contact_info(&self, public_key: &PublicKey) -> std::io::Result<LastingContactInfo>
+    {
+        if public_key.verify(&Self::signed_data(&self.info), &self.signature)
+        {
+            let (contact_info, len) = LastingContactInfo::deserialize(&self.info)?;
+            if len == self.info.len()
+            {
+                Ok(LastingContactInfo::new(contact_info.name(), contact_info.crypto_info()))
+            }
+            else
+            {
+                Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid data length"))
+            }
+        }
+        else
+        {
+            Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid signature"))
+        }
+    }
+
+    fn signed_data(info: &[u8]) -> Vec<u8>
+    {
+        let mut data = defines::INTRODUCTION_CONTEXT.to_vec();
+        data.extend(info);
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::crypto::CryptoLastingInfo;
+
+    use super::*;
+
+    #[test]
+    fn introduction_signature()
+    {
+        let introducer = PrivateKey::new();
+        let mut introduced = LastingContactInfo::new("Introduced", &CryptoLastingInfo::new(&PrivateKey::new().public_key()));
+        introduced.set_verified(true);
+        let introduction = SignedIntroduction::from_lasting_contact_info(&introduced, &introducer);
+        let received = introduction.into_lasting_contact_info(&introducer.public_key()).unwrap();
+        assert_eq!(received.name(), introduced.name());
+        assert_eq!(received.crypto_info(), introduced.crypto_info());
+        assert!(!received.is_verified());
+        assert!(introduction.into_lasting_contact_info(&PrivateKey::new().public_key()).is_err());
+    }
+}
@@ -0,0 +1,64 @@
+use serializable::Serializable;
+
+use crate::config::defines;
+
+use super::{PrivateKey, PublicKey, EcdheKey};
+
+/// Refusal of a connection request, bound to the ephemeral keys of that request so it cannot be replayed
+#[derive(Serializable, Clone, Debug, PartialEq)]
+pub struct SignedRejection
+{
+    public_key: PublicKey,
+    signature: Vec<u8>,
+}
+
+impl SignedRejection
+{
+    pub fn new(rejected_ecdhe_keys: &[EcdheKey], private_key: &PrivateKey) -> Self
+    {
+        Self
+        {
+            public_key: private_key.public_key(),
+            signature: private_key.sign(&Self::signed_data(rejected_ecdhe_keys)),
+        }
+    }
+
+    /// The identity key of the peer that refused our request, None if the signature does not match it
+    pub fn verify(&self, ecdhe_keys: &[EcdheKey]) -> Option<&PublicKey>
+    {
+        if self.public_key.verify(&Self::signed_data(ecdhe_keys), &self.signature)
+        {
+            Some(&self.public_key)
+        }
+        else
+        {
+            None
+        }
+    }
+
+    fn signed_data(ecdhe_keys: &[EcdheKey]) -> Vec<u8>
+    {
+        let mut data = defines::REJECTION_CONTEXT.to_vec();
+        data.extend(ecdhe_keys.to_vec().serialize());
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::crypto::{CryptoHandshakeInfo, CipherSuite};
+
+    use super::*;
+
+    #[test]
+    fn rejection_signature()
+    {
+        let rejecter = PrivateKey::new();
+        let request = CryptoHandshakeInfo::offer(&CipherSuite::all()).public_keys();
+        let rejection = SignedRejection::new(&request, &rejecter);
+        assert_eq!(rejection.verify(&request), Some(&rejecter.public_key()));
+        let other_request = CryptoHandshakeInfo::offer(&CipherSuite::all()).public_keys();
+        assert!(rejection.verify(&other_request).is_none());
+    }
+}
@@ -1,13 +1,16 @@
-use std::error::Error;
+use std::{error::Error, str::FromStr};
+
+use serde::{Serialize, Deserialize};
 
 use crate::config::defines;
 
-use super::Ciphertext;
+use super::{Ciphertext, CipherSuite};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct SymmetricKey
 {
-    key: [u8; defines::SYMMETRIC_ALGORITHM_KEY_LEN]
+    pub(super) key: [u8; defines::SYMMETRIC_ALGORITHM_KEY_LEN],
+    cipher_suite: CipherSuite,
 }
 
 impl SymmetricKey
@@ -16,21 +19,73 @@ impl SymmetricKey
     {
         let mut key = [0; defines::SYMMETRIC_ALGORITHM_KEY_LEN];
         openssl::rand::rand_bytes(&mut key).unwrap();
-        SymmetricKey { key }
+        SymmetricKey { key, cipher_suite: CipherSuite::default() }
+    }
+
+    /// All the suites use keys of the same length, only the cipher changes
+    pub fn with_cipher_suite(self, cipher_suite: CipherSuite) -> Self
+    {
+        SymmetricKey { cipher_suite, ..self }
+    }
+
+    pub fn cipher_suite(&self) -> CipherSuite
+    {
+        self.cipher_suite
     }
 
     pub fn from_shared_secret(shared_secret: &[u8]) -> Self
     {
         let hss = openssl::hash::hash(defines::KEY_DERIVATION_MD(), shared_secret).unwrap();
-        SymmetricKey { key: hss[0..defines::SYMMETRIC_ALGORITHM_KEY_LEN].try_into().unwrap()}
+        SymmetricKey { key: hss[0..defines::SYMMETRIC_ALGORITHM_KEY_LEN].try_into().unwrap(), cipher_suite: CipherSuite::default() }
+    }
+
+    /// Slow derivation for keys that come from a passphrase, the salt must be stored next to the encrypted data
+    pub fn from_passphrase(passphrase: &str, salt: &[u8]) -> Result<Self,Box<dyn Error>>
+    {
+        let mut key = [0; defines::SYMMETRIC_ALGORITHM_KEY_LEN];
+        openssl::pkcs5::scrypt(
+            passphrase.as_bytes(), 
+            salt, 
+            defines::SCRYPT_N, 
+            defines::SCRYPT_R, 
+            defines::SCRYPT_P, 
+            defines::SCRYPT_MAX_MEM, 
+            &mut key)?;
+        Ok(SymmetricKey { key, cipher_suite: CipherSuite::default() })
+    }
+
+    /// Keyed derivation used by the ratchet chains, the output is HMAC(self, data) truncated to the key length
+    pub fn derive_with(&self, data: &[u8]) -> Self
+    {
+        let mac = self.mac(data);
+        SymmetricKey { key: mac[0..defines::SYMMETRIC_ALGORITHM_KEY_LEN].try_into().unwrap(), cipher_suite: self.cipher_suite }
+    }
+
+    pub fn mac(&self, data: &[u8]) -> Vec<u8>
+    {
+        let hmac_key = openssl::pkey::PKey::hmac(&self.key).unwrap();
+        let mut signer = openssl::sign::Signer::new(defines::KEY_DERIVATION_MD(), &hmac_key).unwrap();
+        signer.update(data).unwrap();
+        signer.sign_to_vec().unwrap()
+    }
+
+    pub fn verify_mac(&self, data: &[u8], mac: &[u8]) -> bool
+    {
+        let expected = self.mac(data);
+        expected.len() == mac.len() && openssl::memcmp::eq(&expected, mac)
     }
 
     pub fn encrypt(&self, data: &[u8]) -> Ciphertext
+    {
+        self.encrypt_with_associated_data(data, &[])
+    }
+
+    pub fn encrypt_with_associated_data(&self, data: &[u8], associated_data: &[u8]) -> Ciphertext
     {
         let mut iv = [0; defines::SYMMETRIC_ALGORITHM_IV_LEN];
         let mut tag = [0;defines::SYMMETRIC_ALGORITHM_TAG_LEN];
         openssl::rand::rand_bytes(&mut iv).unwrap();
-        let ciphertext = openssl::symm::encrypt_aead(defines::SYMMETRIC_ALGORITHM(), &self.key, Some(&iv), &[], data, &mut tag).unwrap();
+        let ciphertext = openssl::symm::encrypt_aead(self.cipher_suite.symmetric_algorithm(), &self.key, Some(&iv), associated_data, data, &mut tag).unwrap();
         Ciphertext {
             ciphertext,
             iv,
@@ -40,7 +95,46 @@ impl SymmetricKey
 
     pub fn decrypt(&self, ciphertext: &Ciphertext) -> Result<Vec<u8>,Box<dyn Error>>
     {
-        let plaintext = openssl::symm::decrypt_aead(defines::SYMMETRIC_ALGORITHM(), &self.key, Some(&ciphertext.iv), &[], &ciphertext.ciphertext, &ciphertext.tag)?;
+        self.decrypt_with_associated_data(ciphertext, &[])
+    }
+
+    pub fn decrypt_with_associated_data(&self, ciphertext: &Ciphertext, associated_data: &[u8]) -> Result<Vec<u8>,Box<dyn Error>>
+    {
+        let plaintext = openssl::symm::decrypt_aead(self.cipher_suite.symmetric_algorithm(), &self.key, Some(&ciphertext.iv), associated_data, &ciphertext.ciphertext, &ciphertext.tag)?;
         Ok(plaintext)
     }
-}
\ No newline at end of file
+}
+
+/// Only the key is encoded, keys stored as text (like the ratchet ones) use the default suite
+impl ToString for SymmetricKey
+{
+    fn to_string(&self) -> String {
+        openssl::base64::encode_block(&self.key)
+    }
+}
+
+impl FromStr for SymmetricKey
+{
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = openssl::base64::decode_block(s)?;
+        let key = bytes.as_slice().try_into().map_err(|_| "Invalid symmetric key length")?;
+        Ok(SymmetricKey{ key, cipher_suite: CipherSuite::default() })
+    }
+}
+
+impl Serialize for SymmetricKey
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: serde::Serializer, {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for SymmetricKey
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: serde::Deserializer<'de>, {
+        let s = String::deserialize(deserializer)?;
+        SymmetricKey::from_str(&s).map_err(|e| serde::de::Error::custom(e.to_string()))
+    }
+}
@@ -0,0 +1,44 @@
+/// What a hook asks the app to do, one per line of its output
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HookAction
+{
+    /// destination (contact or group id) and text
+    Send(String,String),
+    Log(String),
+}
+
+impl HookAction
+{
+    /// Parses `send <contact> <text>` or `log <text>`, `\n` in the text is a new line
+    pub fn parse(line: &str) -> Result<Self,String>
+    {
+        let line = line.trim();
+        let (action, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim_start();
+        match action
+        {
+            "send" => match rest.split_once(' ')
+            {
+                Some((to, text)) if !text.trim().is_empty() => Ok(HookAction::Send(to.to_string(), text.replace("\\n", "\n"))),
+                _ => Err(format!("Missing contact or text in: {}", line)),
+            },
+            "log" if !rest.is_empty() => Ok(HookAction::Log(rest.to_string())),
+            _ => Err(format!("Unknown action: {}", line)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn parse()
+    {
+        assert_eq!(HookAction::parse("send Alice Deploy is green\\nall good"), Ok(HookAction::Send("Alice".to_string(), "Deploy is green\nall good".to_string())));
+        assert_eq!(HookAction::parse("log  auto-reply sent "), Ok(HookAction::Log("auto-reply sent".to_string())));
+        assert!(HookAction::parse("send Alice").is_err());
+        assert!(HookAction::parse("call Alice").is_err());
+    }
+}
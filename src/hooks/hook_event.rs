@@ -0,0 +1,45 @@
+use std::net::SocketAddr;
+
+use crate::text::chat_export::json_string;
+
+use super::HookEventKind;
+
+/// Something that happened in the text, connection or voice thread, the hooks get it as JSON
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HookEvent
+{
+    /// conversation (the contact or the group id), author and text of an incoming message
+    Message(String,String,String),
+    Connected(String,SocketAddr),
+    Disconnected(String),
+    /// a contact is calling
+    Call(String),
+}
+
+impl HookEvent
+{
+    pub fn kind(&self) -> HookEventKind
+    {
+        match self
+        {
+            HookEvent::Message(..) => HookEventKind::Message,
+            HookEvent::Connected(..) => HookEventKind::Connected,
+            HookEvent::Disconnected(..) => HookEventKind::Disconnected,
+            HookEvent::Call(..) => HookEventKind::Call,
+        }
+    }
+
+    pub fn to_json(&self) -> String
+    {
+        let fields = match self
+        {
+            HookEvent::Message(conversation, author, text) =>
+                format!("\"conversation\": {}, \"from\": {}, \"text\": {}", json_string(conversation), json_string(author), json_string(text)),
+            HookEvent::Connected(name, address) =>
+                format!("\"name\": {}, \"address\": {}", json_string(name), json_string(&address.to_string())),
+            HookEvent::Disconnected(name) | HookEvent::Call(name) =>
+                format!("\"name\": {}", json_string(name)),
+        };
+        format!("{{\"event\": \"{}\", {}}}", self.kind().name(), fields)
+    }
+}
@@ -0,0 +1,32 @@
+use serde::{Serialize, Deserialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEventKind
+{
+    Message,
+    Connected,
+    Disconnected,
+    Call,
+}
+
+impl HookEventKind
+{
+    pub const ALL: [HookEventKind; 4] = [HookEventKind::Message, HookEventKind::Connected, HookEventKind::Disconnected, HookEventKind::Call];
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            HookEventKind::Message => "message",
+            HookEventKind::Connected => "connected",
+            HookEventKind::Disconnected => "disconnected",
+            HookEventKind::Call => "call",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self>
+    {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+}
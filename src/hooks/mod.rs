@@ -0,0 +1,8 @@
+pub mod threads;
+pub mod hook_event_kind;
+pub mod hook_event;
+pub mod hook_action;
+
+pub use hook_event_kind::HookEventKind;
+pub use hook_event::HookEvent;
+pub use hook_action::HookAction;
@@ -0,0 +1,246 @@
+use std::{sync::{mpsc::{Receiver, Sender, SyncSender, TrySendError}, Arc, RwLock}, process::{Command, Stdio}, io::{Write, Read}, time::{Duration, Instant}};
+
+use crate::{config::{Config, defines, Hook}, hooks::{HookEvent, HookAction}, log::{Logger, MessageKind}, text::TextRequest};
+
+pub fn run(
+    running: Arc<RwLock<bool>>,
+    log: Logger,
+    hook_events: Receiver<HookEvent>,
+    plugin_events: Sender<HookEvent>,
+    text_requests: Sender<TextRequest>,
+    config: Arc<RwLock<Config>>)
+{
+    // one worker for each hook, it runs one event at a time and keeps a few waiting
+    let mut workers: Vec<(Hook, SyncSender<String>)> = Vec::new();
+    while *running.read().unwrap()
+    {
+        match hook_events.recv_timeout(defines::THREAD_QUEUE_TIMEOUT)
+        {
+            Ok(event) =>
+            {
+                // the plugins check their own subscriptions
+                plugin_events.send(event.clone()).unwrap();
+                let configured = config.read().unwrap().hooks.clone();
+                dispatch(&mut workers, &event, configured, defines::HOOK_TIMEOUT, &log, &text_requests);
+            },
+            Err(e) =>
+            {
+                match e
+                {
+                    std::sync::mpsc::RecvTimeoutError::Timeout => {},
+                    std::sync::mpsc::RecvTimeoutError::Disconnected =>
+                    {
+                        if !*running.read().unwrap()
+                        {return}
+                        else
+                        {panic!("Hooks channel broken")}
+                    }
+                }
+            },
+        }
+    }
+}
+
+/// Hands the event to the worker of each hook that wants it, the worker is started by the first one
+fn dispatch(
+    workers: &mut Vec<(Hook, SyncSender<String>)>,
+    event: &HookEvent,
+    configured: Vec<Hook>,
+    timeout: Duration,
+    log: &Logger,
+    text_requests: &Sender<TextRequest>)
+{
+    // the workers of removed hooks stop once their queue is empty
+    workers.retain(|(hook, _)| configured.contains(hook));
+    let event_kind = event.kind();
+    let event = event.to_json();
+    for hook in configured.into_iter().filter(|hook| hook.events.contains(&event_kind))
+    {
+        let index = match workers.iter().position(|(worker, _)| *worker == hook)
+        {
+            Some(index) => index,
+            None => match start_worker(&hook, timeout, log, text_requests)
+            {
+                Ok(sender) =>
+                {
+                    workers.push((hook.clone(), sender));
+                    workers.len() - 1
+                },
+                Err(e) =>
+                {
+                    log.log(MessageKind::Error, &format!("Error starting hook {}: {}", hook.command, e)).unwrap();
+                    continue;
+                },
+            },
+        };
+        match workers[index].1.try_send(event.clone())
+        {
+            Ok(()) => {},
+            Err(TrySendError::Full(_)) =>
+            {
+                log.log(MessageKind::Error, &format!("Hook {} is busy, event dropped", hook.command)).unwrap();
+            },
+            Err(TrySendError::Disconnected(_)) =>
+            {
+                log.log(MessageKind::Error, &format!("Hook {} stopped, event dropped", hook.command)).unwrap();
+                workers.remove(index);
+            },
+        }
+    }
+}
+
+/// Starts the thread that runs the hook on the events sent to it, one at a time
+fn start_worker(hook: &Hook, timeout: Duration, log: &Logger, text_requests: &Sender<TextRequest>) -> Result<SyncSender<String>,String>
+{
+    let (sender, events) = std::sync::mpsc::sync_channel::<String>(defines::HOOK_QUEUE_SIZE);
+    let (hook, log, text_requests) = (hook.clone(), log.clone(), text_requests.clone());
+    std::thread::Builder::new().name("Hook".to_string()).spawn(move || {
+        // ends when the hook is removed and the sender dropped
+        for event in events
+        {
+            if let Err(e) = run_hook(&hook, &event, timeout, &log, &text_requests)
+            {
+                log.log(MessageKind::Error, &format!("Hook {} failed: {}", hook.command, e)).unwrap();
+            }
+        }
+    }).map_err(|e| e.to_string())?;
+    Ok(sender)
+}
+
+/// Runs the command with the event on stdin and carries out the actions it prints
+fn run_hook(hook: &Hook, event: &str, timeout: Duration, log: &Logger, text_requests: &Sender<TextRequest>) -> Result<(),String>
+{
+    let mut child = Command::new(&hook.command)
+        .args(&hook.args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    if let Some(mut stdin) = child.stdin.take()
+    {
+        // the hook doesn't have to read the event
+        let _ = writeln!(stdin, "{}", event);
+    }
+    let mut stdout = child.stdout.take().ok_or("The output of the hook is not available".to_string())?;
+    let reader = std::thread::spawn(move || {
+        let mut output = String::new();
+        stdout.read_to_string(&mut output).map(|_| output)
+    });
+    let started = Instant::now();
+    let status = loop
+    {
+        if let Some(status) = child.try_wait().map_err(|e| e.to_string())?
+        {
+            break status;
+        }
+        if started.elapsed() >= timeout
+        {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("timed out after {:?}", timeout));
+        }
+        std::thread::sleep(defines::THREAD_QUEUE_TIMEOUT);
+    };
+    if !status.success()
+    {
+        return Err(format!("exited with {}", status));
+    }
+    let output = reader.join()
+        .map_err(|_| "The output of the hook could not be read".to_string())?
+        .map_err(|e| e.to_string())?;
+    for line in output.lines().filter(|line| !line.trim().is_empty())
+    {
+        match HookAction::parse(line)?
+        {
+            HookAction::Send(to, text) => text_requests.send(TextRequest::Send(to, text, None)).map_err(|e| e.to_string())?,
+            HookAction::Log(text) => log.log(MessageKind::Event, &format!("{}: {}", hook.command, text))?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::hooks::HookEventKind;
+
+    use super::*;
+
+    fn shell_hook(script: &str) -> Hook
+    {
+        Hook
+        {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            events: vec![HookEventKind::Message],
+        }
+    }
+
+    fn message() -> HookEvent
+    {
+        HookEvent::Message("Bob".to_string(), "Bob".to_string(), "hi".to_string())
+    }
+
+    fn logged(log: &Logger, text: &str) -> bool
+    {
+        log.get().unwrap().iter().any(|message| message.text.contains(text))
+    }
+
+    #[test]
+    fn actions()
+    {
+        let log = Logger::new();
+        let (text_requests, requests) = std::sync::mpsc::channel();
+        let hook = shell_hook("cat > /dev/null; printf '%s\\n' 'send Alice hi\\nthere' 'log replied'");
+        run_hook(&hook, &message().to_json(), defines::HOOK_TIMEOUT, &log, &text_requests).unwrap();
+        assert!(matches!(requests.try_recv(), Ok(TextRequest::Send(to, text, None)) if to == "Alice" && text == "hi\nthere"));
+        assert!(logged(&log, "sh: replied"));
+        assert!(run_hook(&shell_hook("echo 'call Alice'"), "", defines::HOOK_TIMEOUT, &log, &text_requests).is_err());
+        assert!(run_hook(&shell_hook("exit 1"), "", defines::HOOK_TIMEOUT, &log, &text_requests).is_err());
+    }
+
+    #[test]
+    fn timeout()
+    {
+        let log = Logger::new();
+        let (text_requests, _requests) = std::sync::mpsc::channel();
+        let hook = shell_hook("sleep 10; echo 'send Alice late'");
+        let started = Instant::now();
+        let error = run_hook(&hook, "", Duration::from_millis(200), &log, &text_requests).unwrap_err();
+        assert!(error.contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        // the worker logs it and goes on with the next event
+        let mut workers = Vec::new();
+        dispatch(&mut workers, &message(), vec![hook], Duration::from_millis(200), &log, &text_requests);
+        std::thread::sleep(Duration::from_secs(1));
+        assert!(logged(&log, "Hook sh failed: timed out"));
+    }
+
+    #[test]
+    fn workers()
+    {
+        let log = Logger::new();
+        let (text_requests, _requests) = std::sync::mpsc::channel();
+        let slow = shell_hook("sleep 1");
+        let mut workers = Vec::new();
+        dispatch(&mut workers, &message(), vec![slow.clone()], defines::HOOK_TIMEOUT, &log, &text_requests);
+        dispatch(&mut workers, &message(), vec![slow.clone()], defines::HOOK_TIMEOUT, &log, &text_requests);
+        // one worker for each hook, it's kept for the next events
+        assert_eq!(workers.len(), 1);
+        // the hooks only get the events they asked for
+        dispatch(&mut workers, &HookEvent::Disconnected("Bob".to_string()), vec![slow.clone()], defines::HOOK_TIMEOUT, &log, &text_requests);
+        assert!(!logged(&log, "busy"));
+        // once the queue is full the events are dropped
+        for _ in 0..defines::HOOK_QUEUE_SIZE
+        {
+            dispatch(&mut workers, &message(), vec![slow.clone()], defines::HOOK_TIMEOUT, &log, &text_requests);
+        }
+        assert!(logged(&log, "Hook sh is busy, event dropped"));
+        assert_eq!(workers.len(), 1);
+        // and the worker of a removed hook is stopped
+        dispatch(&mut workers, &message(), Vec::new(), defines::HOOK_TIMEOUT, &log, &text_requests);
+        assert!(workers.is_empty());
+    }
+}
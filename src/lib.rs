@@ -7,5 +7,8 @@ pub mod text;
 pub mod file;
 pub mod thread;
 pub mod crypto;
+pub mod cli;
+pub mod hooks;
+pub mod plugins;
 
 pub use ui::UI;
\ No newline at end of file
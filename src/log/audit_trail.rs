@@ -0,0 +1,27 @@
+use std::io::Write;
+
+use chrono::Local;
+
+/// Append-only record of security relevant decisions, kept outside of the in-memory log
+pub struct AuditTrail
+{
+    path: String,
+}
+
+impl AuditTrail
+{
+    pub fn new(path: &str) -> Self
+    {
+        Self { path: path.to_string() }
+    }
+
+    pub fn record(&self, message: &str) -> Result<(),String>
+    {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| e.to_string())?;
+        writeln!(file, "{} {}", Local::now().to_rfc3339(), message).map_err(|e| e.to_string())
+    }
+}
@@ -1,12 +1,13 @@
 use std::{sync::{Arc, Mutex}, collections::LinkedList};
 
-use super::{Log, MessageKind, LogMessage};
+use super::{Log, MessageKind, LogMessage, AuditTrail};
 
 #[derive(Clone)]
 pub struct Logger
 {
     log: Arc<Mutex<Log>>,
-    notification: Arc<Mutex<bool>>
+    notification: Arc<Mutex<bool>>,
+    audit_trail: Option<Arc<AuditTrail>>,
 }
 
 impl Logger
@@ -15,7 +16,16 @@ impl Logger
     {
         Self { 
             log: Arc::new(Mutex::new(Log::new())),
-            notification: Arc::new(Mutex::new(false))
+            notification: Arc::new(Mutex::new(false)),
+            audit_trail: None,
+        }
+    }
+
+    pub fn with_audit_trail(path: &str) -> Self
+    {
+        Self {
+            audit_trail: Some(Arc::new(AuditTrail::new(path))),
+            ..Self::new()
         }
     }
 
@@ -28,6 +38,20 @@ impl Logger
         Ok(())
     }
 
+    /// Logs a security event and appends it to the audit trail if there is one,
+    /// a trail that cannot be written is reported in the log
+    pub fn audit(&self, message: &str)
+    {
+        self.log(MessageKind::Security, message).unwrap();
+        if let Some(audit_trail) = &self.audit_trail
+        {
+            if let Err(e) = audit_trail.record(message)
+            {
+                self.log(MessageKind::Error, &format!("Cannot write the audit trail: {}", e)).unwrap();
+            }
+        }
+    }
+
     pub fn get(&self) -> Result<LinkedList<LogMessage>,String>
     {
         let log = self.log.lock().map_err(|e|e.to_string())?;
@@ -42,4 +66,4 @@ impl Logger
         let notification = self.notification.lock().map_err(|e|e.to_string())?;
         Ok(*notification)
     }
-}
\ No newline at end of file
+}
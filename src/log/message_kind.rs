@@ -4,4 +4,5 @@ pub enum MessageKind
     Command,
     Event,
     Error,
+    Security,
 }
\ No newline at end of file
@@ -2,8 +2,10 @@ pub mod log_message;
 pub mod log;
 pub mod logger;
 pub mod message_kind;
+pub mod audit_trail;
 
 pub use log_message::LogMessage;
 pub use log::Log;
 pub use logger::Logger;
-pub use message_kind::MessageKind;
\ No newline at end of file
+pub use message_kind::MessageKind;
+pub use audit_trail::AuditTrail;
\ No newline at end of file
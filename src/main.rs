@@ -2,22 +2,49 @@
 use std::{any::Any, sync::{Arc, Mutex}};
 
 // hide console window on Windows in release
-use mokaccino::{ui, thread, config::defines};
+use mokaccino::{ui, thread, config::defines, cli};
 
 
 fn main() -> Result<(),Box<dyn Any + Send>>
 {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match cli::Command::from_args(&args)
+    {
+        Ok(Some(command)) =>
+        {
+            if let Err(e) = command.run(defines::CONFIG_PATH)
+            {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            return Ok(());
+        },
+        Ok(None) => {},
+        Err(usage) =>
+        {
+            eprintln!("{}", usage);
+            std::process::exit(1);
+        },
+    }
     let is_still_loading = Arc::new(Mutex::new(true));
     let is_still_loading_clone = is_still_loading.clone();
     let context = thread::Context::new(Some(defines::CONFIG_PATH));       
 
     let context_movable_connection_list_clone = context.movable.connection_list.clone();
     let context_movable_text_list_clone = context.movable.text_list.clone();
+    let context_movable_bandwidth_stats_clone = context.movable.bandwidth_stats.clone();
     let context_movable_log_clone = context.movable.log.clone();
     let context_movable_voice_interlocutor_clone = context.movable.voice_interlocutor.clone();
+    let context_movable_text_requests_tx_clone = context.movable.text_requests_tx.clone();
+    let context_movable_plugin_list_clone = context.movable.plugin_list.clone();
     let context_umovable_clone = context.unmovable.clone();
 
     let load_backend = std::thread::Builder::new().name("Loader".to_string()).spawn(move ||{
+        // wait for the user to unlock the config
+        while context_umovable_clone.locked_config.lock().unwrap().is_some() && *context_umovable_clone.running.read().unwrap()
+        {
+            std::thread::sleep(defines::UPDATE_UI_INTERVAL);
+        }
         let mut threads: Vec<std::thread::JoinHandle<()>> = vec![];
         
         threads.extend(thread::network::start(
@@ -29,6 +56,7 @@ fn main() -> Result<(),Box<dyn Any + Send>>
             context.movable.connection_queue_tx, 
             context.movable.voice_queue_tx, 
             context.movable.sender_queue_rx, 
+            context_movable_bandwidth_stats_clone,
             context_umovable_clone.config.clone()
         ));
 
@@ -37,8 +65,10 @@ fn main() -> Result<(),Box<dyn Any + Send>>
             context_movable_connection_list_clone.clone(),
             context_movable_log_clone.clone(),
             context.movable.connection_requests_rx,
+            context.movable.ui_notifications_tx.clone(),
             context.movable.connection_queue_rx, 
             context.movable.sender_queue_tx.clone(), 
+            context.movable.hook_events_tx.clone(),
             context_umovable_clone.config.clone()
         ));
 
@@ -50,6 +80,7 @@ fn main() -> Result<(),Box<dyn Any + Send>>
             context.movable.text_requests_rx,
             context.movable.text_queue_rx,
             context.movable.sender_queue_tx.clone(),
+            context.movable.hook_events_tx.clone(),
             context_umovable_clone.config.clone()
         ));
 
@@ -62,6 +93,7 @@ fn main() -> Result<(),Box<dyn Any + Send>>
             context.movable.ui_notifications_tx.clone(),
             context.movable.voice_queue_rx, 
             context.movable.sender_queue_tx.clone(), 
+            context.movable.hook_events_tx,
             context_umovable_clone.config.clone()
         ));
 
@@ -75,6 +107,25 @@ fn main() -> Result<(),Box<dyn Any + Send>>
             context_umovable_clone.config.clone()
         ));
 
+        threads.extend(thread::hooks::start(
+            context_umovable_clone.running.clone(),
+            context_movable_log_clone.clone(),
+            context.movable.hook_events_rx,
+            context.movable.plugin_events_tx,
+            context_movable_text_requests_tx_clone.clone(),
+            context_umovable_clone.config.clone()
+        ));
+
+        threads.extend(thread::plugins::start(
+            context_umovable_clone.running.clone(),
+            context_movable_log_clone.clone(),
+            context.movable.plugin_requests_rx,
+            context.movable.plugin_events_rx,
+            context_movable_text_requests_tx_clone,
+            context_movable_plugin_list_clone,
+            context_umovable_clone.config.clone()
+        ));
+
         let supervisor = thread::supervisor::start(
             context_umovable_clone.running.clone(),
             threads,
@@ -92,8 +143,11 @@ fn main() -> Result<(),Box<dyn Any + Send>>
         context.movable.connection_requests_tx,
         context.movable.text_requests_tx,
         context.movable.voice_requests_tx,
+        context.movable.plugin_requests_tx,
         context.movable.voice_interlocutor,
         context.movable.ui_notifications_rx,
+        context.movable.bandwidth_stats,
+        context.movable.plugin_list,
         
         context.unmovable,
         is_still_loading,
@@ -0,0 +1,51 @@
+/// Bytes that went through the socket, padding and cover traffic included
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BandwidthStats
+{
+    pub sent_bytes: u64,
+    pub received_bytes: u64,
+    pub sent_packets: u64,
+    pub received_packets: u64,
+    /// bytes added to the sent packets to hide their length
+    pub padding_bytes: u64,
+    /// bytes of the packets sent only to hide when we are idle
+    pub cover_bytes: u64,
+}
+
+impl BandwidthStats
+{
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    pub fn record_sent(&mut self, bytes: usize, padding: usize, cover: bool)
+    {
+        self.sent_bytes += bytes as u64;
+        self.sent_packets += 1;
+        self.padding_bytes += padding as u64;
+        if cover
+        {
+            self.cover_bytes += bytes as u64;
+        }
+    }
+
+    pub fn record_received(&mut self, bytes: usize)
+    {
+        self.received_bytes += bytes as u64;
+        self.received_packets += 1;
+    }
+
+    /// Fraction of the sent bytes spent on padding and cover traffic
+    pub fn overhead(&self) -> f64
+    {
+        if self.sent_bytes == 0
+        {
+            0.0
+        }
+        else
+        {
+            (self.padding_bytes + self.cover_bytes) as f64 / self.sent_bytes as f64
+        }
+    }
+}
@@ -1,14 +1,16 @@
 use std::time::Instant;
 
-use crate::crypto::{CryptoSessionInfo, SymmetricKey};
+use crate::crypto::{CryptoSessionInfo, SymmetricKey, CryptoLastingInfo};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct ConnectionInfo
 {
     pub last_seen: Instant,
     pub strikes: u16,
     pub packet_loss: u32,
-    pub crypto_session_info: CryptoSessionInfo
+    pub crypto_session_info: CryptoSessionInfo,
+    /// Identity the peer authenticated with, may differ from the known host if the user accepted a new key once
+    pub identity: Option<CryptoLastingInfo>,
 }
 
 impl ConnectionInfo
@@ -19,7 +21,16 @@ impl ConnectionInfo
             last_seen: Instant::now(),
             strikes: 0,
             packet_loss: 0,
-            crypto_session_info: CryptoSessionInfo{ symmetric_key }
+            crypto_session_info: CryptoSessionInfo{ symmetric_key },
+            identity: None,
+        }
+    }
+
+    pub fn with_identity(symmetric_key: SymmetricKey, identity: CryptoLastingInfo) -> Self
+    {
+        Self {
+            identity: Some(identity),
+            ..Self::new(symmetric_key)
         }
     }
 
@@ -2,7 +2,7 @@ use std::{net::SocketAddr, collections::HashMap};
 
 use symmetric_key::SymmetricKey;
 
-use crate::crypto::symmetric_key;
+use crate::crypto::{symmetric_key, CryptoLastingInfo};
 
 use super::ConnectionInfo;
 
@@ -31,6 +31,13 @@ impl ConnectionList
         self.address_to_info.insert(address,ConnectionInfo::new(symmetric_key));
     }
 
+    pub fn add_with_identity(&mut self, name: &str, address: SocketAddr, symmetric_key: SymmetricKey, identity: CryptoLastingInfo)
+    {
+        self.names_to_addresses.insert(name.to_string(),address);
+        self.addresses_to_names.insert(address,name.to_string());
+        self.address_to_info.insert(address,ConnectionInfo::with_identity(symmetric_key, identity));
+    }
+
     pub fn remove_with_name(&mut self, name: &str)
     {
         if let Some(address) = self.names_to_addresses.remove(name)
@@ -116,6 +123,6 @@ impl ConnectionList
 
     pub fn get_infos(&self) -> Vec<(SocketAddr,ConnectionInfo)>
     {
-        self.address_to_info.iter().map(|(address,info)|(*address,*info)).collect()
+        self.address_to_info.iter().map(|(address,info)|(*address,info.clone())).collect()
     }
 }
\ No newline at end of file
@@ -1,9 +1,18 @@
 use std::net::SocketAddr;
 
+use super::{KeyChangeDecision, Invite};
+
 #[derive(Debug, Clone)]
 pub enum ConnectionRequest
 {
     Connect(SocketAddr),
+    /// pins the key from the invite and connects to one of its addresses
+    AcceptInvite(Invite),
     Find(String),
     Disconnect(String),
+    ResolveKeyChange(String,KeyChangeDecision),
+    /// sends the identity of the second contact to the first one
+    Introduce(String,String),
+    /// the user's answer to a request the acl asked about
+    AnswerIncoming(SocketAddr,bool),
 }
\ No newline at end of file
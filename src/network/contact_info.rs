@@ -1,4 +1,4 @@
-use crate::{config::Config, crypto::{CryptoConnectionInfo, PublicKey}};
+use crate::{config::Config, crypto::{CryptoConnectionInfo, EcdheKey}};
 use serializable::Serializable;
 
 
@@ -7,6 +7,8 @@ pub struct ContactInfo
 {
     name: String,
     crypto_info: CryptoConnectionInfo,
+    /// token of the invite we are answering, if any
+    invite_token: Option<String>,
 }
 
 
@@ -14,15 +16,16 @@ impl ContactInfo
 {
     pub fn new(name: &str, info: &CryptoConnectionInfo) -> Self
     {
-        Self { name: name.to_string() , crypto_info: info.clone() }
+        Self { name: name.to_string() , crypto_info: info.clone(), invite_token: None }
     }
 
-    pub fn from_config(config: &Config, ecdhe_public_key: PublicKey) -> Self
+    pub fn from_config(config: &Config, ecdhe_keys: Vec<EcdheKey>, invite_token: Option<String>) -> Self
     {
         Self
         {
             name: config.network.name.to_string(),
-            crypto_info: CryptoConnectionInfo::from_config(config, ecdhe_public_key),
+            crypto_info: CryptoConnectionInfo::from_config(config, ecdhe_keys),
+            invite_token,
         }
     }
 
@@ -35,4 +38,21 @@ impl ContactInfo
     {
         &self.crypto_info
     }
+
+    pub fn invite_token(&self) -> Option<&str>
+    {
+        self.invite_token.as_deref()
+    }
+
+    /// Leaves out the oldest key rotation, returns false if there were none
+    pub fn drop_oldest_rotation(&mut self) -> bool
+    {
+        let rotations = &mut self.crypto_info.key_rotations;
+        if rotations.is_empty()
+        {
+            return false;
+        }
+        rotations.remove(0);
+        true
+    }
 }
\ No newline at end of file
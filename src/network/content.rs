@@ -1,20 +1,52 @@
+use std::time::SystemTime;
+
 use serializable::Serializable;
 
-use crate::{config::Config, crypto::{SignedContactInfo, PublicKey}};
+use crate::{config::{Config, defines}, crypto::{SignedContactInfo, RatchetMessage, EcdheKey, SignedIntroduction, SignedGroupInfo, SignedRejection, Ciphertext, SymmetricKey}, text::DisappearingTimer};
 
-use super::{ContactInfo, UserInfo};
+use super::{ContactInfo, UserInfo, Packet, SecurePacket};
 
 #[derive(Serializable, Clone, Debug, PartialEq)]
 pub enum Content
 {
-    Text(String,u64),
-    AcknowledgeText(String,u64),
+    /// text, nonce and lamport clock of the conversation
+    Text(String,u64,u64),
+    AcknowledgeText(u64),
+    /// nonce of a message whose edit or deletion was received
+    AcknowledgeChange(u64),
+    /// text, nonce, lamport clock and the nonce of the message it replies to
+    ReplyText(String,u64,u64,u64),
+    /// nonce of one of the sender's messages and its new text
+    EditText(u64,String),
+    /// nonce of one of the sender's messages, deleted for everyone
+    DeleteText(u64),
+    /// nonce of a message of the conversation, the emoji and whether it's added or removed
+    Reaction(u64,String,bool),
+    /// the sender is writing, it's not acknowledged
+    Typing,
+    /// messages of the conversation are removed after this time on both sides, None turns it off.
+    /// It comes with the time it was changed, it's sent on every connection and the latest change wins
+    DisappearingTimer(Option<DisappearingTimer>,SystemTime),
+    /// nonces of the messages the peer has seen
+    ReadReceipt(Vec<u64>),
+    Ratchet(RatchetMessage),
+    /// the sender cannot decrypt our ratchet messages anymore, the session is started again
+    ResetRatchet,
+    /// membership list and, for the members, the group key
+    GroupUpdate(SignedGroupInfo,Option<String>),
+    /// group id and a text sealed with the group key
+    GroupText(String,Ciphertext),
     Ping,
     Pong,
+    /// dummy packet sent while idle, dropped when received
+    Cover,
     RequestConnection(SignedContactInfo),
     AcknowledgeConnection,
+    /// signed by the identity key of the peer that refuses the request
+    RejectConnection(SignedRejection),
     RequestUserInfo(String,u8),
     UserInfo(UserInfo),
+    Introduction(SignedIntroduction),
     Voice(Vec<u8>),
     EndVoice,
     FileInfo(String,Vec<u8>,u64),
@@ -22,7 +54,64 @@ pub enum Content
     AcknowledgeFileData(u64)
 }
 impl Content {
-    pub fn request_connection_from_config(config: &Config, ecdhe_public_key: PublicKey) -> Self {
-        Content::RequestConnection(SignedContactInfo::from_contact_info(ContactInfo::from_config(config, ecdhe_public_key), &config.network.private_key))
+    /// The oldest key rotations are left out until the request fits in a packet,
+    /// the peers that only know one of those keys have to accept the new one by hand
+    pub fn request_connection_from_config(config: &Config, ecdhe_keys: Vec<EcdheKey>, invite_token: Option<String>) -> Self {
+        let psk = config.network.psk_key();
+        let mut contact_info = ContactInfo::from_config(config, ecdhe_keys, invite_token);
+        loop {
+            let content = Content::RequestConnection(SignedContactInfo::from_contact_info(contact_info.clone(), &config.network.private_key));
+            if content.plaintext_len(psk.as_ref()) <= defines::MAX_PACKET_SIZE || !contact_info.drop_oldest_rotation() {
+                return content;
+            }
+        }
+    }
+
+    /// Size of the packet when it's sent without encryption, like the handshake
+    pub fn plaintext_len(&self, psk: Option<&SymmetricKey>) -> usize {
+        let packet = Packet::from_content_now(self.clone());
+        match psk {
+            Some(psk) => SecurePacket::authenticate(packet, psk),
+            None => SecurePacket::Plaintext(packet),
+        }.serialize().len()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::crypto::{CipherSuite, CryptoHandshakeInfo, KeyRotationStep};
+
+    use super::*;
+
+    #[test]
+    fn request_connection_fits_in_a_packet()
+    {
+        for cipher_suite in CipherSuite::all()
+        {
+            let mut config = Config::default();
+            config.network.cipher_suites = vec![cipher_suite];
+            config.network.private_key = cipher_suite.generate_identity_key();
+            config.network.psk = Some("secret".to_string());
+            let first_key = config.network.private_key.public_key();
+            for _ in 0..defines::MAX_KEY_ROTATIONS
+            {
+                config.network.rotate_private_key();
+            }
+            let last_trusted_key = config.network.key_rotations.last().unwrap().old_key.clone();
+            let ecdhe_keys = CryptoHandshakeInfo::offer(&CipherSuite::all()).public_keys();
+            let content = Content::request_connection_from_config(&config, ecdhe_keys, Some("a".repeat(2 * defines::INVITE_TOKEN_LEN)));
+            assert!(content.plaintext_len(config.network.psk_key().as_ref()) <= defines::MAX_PACKET_SIZE);
+            let public_key = config.network.private_key.public_key();
+            let contact_info = match content
+            {
+                Content::RequestConnection(signed_contact_info) => signed_contact_info.into_contact_info(&public_key).unwrap(),
+                _ => unreachable!(),
+            };
+            // the last rotation always fits, the first ones are left out
+            let rotations = &contact_info.crypto_info().key_rotations;
+            assert!(KeyRotationStep::verify_chain(rotations, &last_trusted_key, &public_key));
+            assert!(!KeyRotationStep::verify_chain(rotations, &first_key, &public_key));
+        }
     }
-}
\ No newline at end of file
+}
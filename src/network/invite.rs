@@ -0,0 +1,184 @@
+use std::{net::{SocketAddr, UdpSocket}, str::FromStr};
+
+use crate::{config::defines, crypto::PublicKey};
+
+use super::UserInfo;
+
+/// `mokaccino://name@address,address?fingerprint=hex&token=hex`, shared out of band to pin the identity of the first contact
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Invite
+{
+    name: String,
+    addresses: Vec<SocketAddr>,
+    fingerprint: String,
+    token: Option<String>,
+}
+
+impl Invite
+{
+    pub fn from_user_info(user_info: &UserInfo, addresses: Vec<SocketAddr>, token: Option<String>) -> Option<Self>
+    {
+        let public_key = user_info.public_key().as_ref()?;
+        Some(Self
+        {
+            name: user_info.name().to_string(),
+            addresses,
+            fingerprint: Self::fingerprint_of(public_key),
+            token,
+        })
+    }
+
+    /// Hex of the first bytes of the fingerprint, enough to pin a key without making the link too long
+    pub fn fingerprint_of(public_key: &PublicKey) -> String
+    {
+        public_key.fingerprint()[..defines::INVITE_FINGERPRINT_LEN].iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// The address other hosts on the local network would see, the user can replace it with a public one
+    pub fn local_address(port: u16) -> Option<SocketAddr>
+    {
+        // connecting an udp socket sends nothing, it only selects the outgoing interface
+        let socket = UdpSocket::bind((defines::HOST, 0)).ok()?;
+        socket.connect(defines::INVITE_ROUTE_PROBE).ok()?;
+        let mut address = socket.local_addr().ok()?;
+        address.set_port(port);
+        Some(address)
+    }
+
+    pub fn generate_token() -> String
+    {
+        let mut token = [0; defines::INVITE_TOKEN_LEN];
+        openssl::rand::rand_bytes(&mut token).unwrap();
+        token.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    pub fn name(&self) -> &str
+    {
+        &self.name
+    }
+
+    pub fn addresses(&self) -> &[SocketAddr]
+    {
+        &self.addresses
+    }
+
+    pub fn fingerprint(&self) -> &str
+    {
+        &self.fingerprint
+    }
+
+    pub fn token(&self) -> Option<&str>
+    {
+        self.token.as_deref()
+    }
+
+    pub fn matches(&self, public_key: &PublicKey) -> bool
+    {
+        Self::fingerprint_of(public_key) == self.fingerprint
+    }
+}
+
+impl ToString for Invite
+{
+    fn to_string(&self) -> String
+    {
+        let addresses = self.addresses.iter().map(|address| address.to_string()).collect::<Vec<_>>().join(",");
+        let mut uri = format!("{}{}@{}?fingerprint={}", defines::INVITE_SCHEME, percent_encode(&self.name), addresses, self.fingerprint);
+        if let Some(token) = &self.token
+        {
+            uri.push_str(&format!("&token={}", token));
+        }
+        uri
+    }
+}
+
+impl FromStr for Invite
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        let rest = s.trim().strip_prefix(defines::INVITE_SCHEME).ok_or("Not a mokaccino invite")?;
+        let (path, query) = rest.split_once('?').ok_or("Missing fingerprint")?;
+        let (name, addresses) = path.rsplit_once('@').ok_or("Missing address")?;
+        let name = percent_decode(name)?;
+        let addresses = addresses.split(',')
+            .map(|address| address.parse::<SocketAddr>().map_err(|e| e.to_string()))
+            .collect::<Result<Vec<_>,_>>()?;
+        let mut fingerprint = None;
+        let mut token = None;
+        for parameter in query.split('&')
+        {
+            match parameter.split_once('=')
+            {
+                Some(("fingerprint", value)) => fingerprint = Some(value.to_lowercase()),
+                Some(("token", value)) => token = Some(value.to_lowercase()),
+                _ => {},
+            }
+        }
+        let fingerprint = fingerprint.ok_or("Missing fingerprint")?;
+        if fingerprint.len() != 2 * defines::INVITE_FINGERPRINT_LEN || !fingerprint.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            return Err("Invalid fingerprint".to_string());
+        }
+        if name.is_empty() || addresses.is_empty()
+        {
+            return Err("Missing name or address".to_string());
+        }
+        Ok(Self { name, addresses, fingerprint, token })
+    }
+}
+
+fn percent_encode(s: &str) -> String
+{
+    s.bytes().map(|b| 
+        if b.is_ascii_alphanumeric() || b"-_.~".contains(&b) {(b as char).to_string()} 
+        else {format!("%{:02X}", b)})
+    .collect()
+}
+
+fn percent_decode(s: &str) -> Result<String,String>
+{
+    let mut bytes = Vec::new();
+    let mut iter = s.bytes();
+    while let Some(b) = iter.next()
+    {
+        if b == b'%'
+        {
+            let hex = [iter.next().ok_or("Invalid escape")?, iter.next().ok_or("Invalid escape")?];
+            let hex = std::str::from_utf8(&hex).map_err(|e| e.to_string())?;
+            bytes.push(u8::from_str_radix(hex, 16).map_err(|e| e.to_string())?);
+        }
+        else
+        {
+            bytes.push(b);
+        }
+    }
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::crypto::PrivateKey;
+
+    use super::*;
+
+    #[test]
+    fn invite_round_trip()
+    {
+        let public_key = PrivateKey::new().public_key();
+        let invite = Invite
+        {
+            name: "Anon#1f ü".to_string(),
+            addresses: vec!["192.168.1.2:4848".parse().unwrap(), "[::1]:4848".parse().unwrap()],
+            fingerprint: Invite::fingerprint_of(&public_key),
+            token: Some(Invite::generate_token()),
+        };
+        let parsed = Invite::from_str(&invite.to_string()).unwrap();
+        assert_eq!(parsed, invite);
+        assert!(parsed.matches(&public_key));
+        assert!(!parsed.matches(&PrivateKey::new().public_key()));
+        assert!(Invite::from_str("mokaccino://name@127.0.0.1:4848").is_err());
+    }
+}
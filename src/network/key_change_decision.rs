@@ -0,0 +1,10 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyChangeDecision
+{
+    /// Keep the stored key and drop the request
+    Reject,
+    /// Trust the new key until restart without touching the known hosts
+    AcceptOnce,
+    /// Replace the stored key, the contact is no longer verified
+    Replace,
+}
@@ -8,13 +8,18 @@ pub struct LastingContactInfo
 {
     name: String,
     crypto_info: CryptoLastingInfo,
+    #[serde(default)]
+    verified: bool,
+    /// the verified contact that introduced this one
+    #[serde(default)]
+    vouched_by: Option<String>,
 }
 
 impl LastingContactInfo
 {
     pub fn new(name: &str, info: &CryptoLastingInfo) -> Self
     {
-        Self { name: name.to_string() , crypto_info: info.clone() }
+        Self { name: name.to_string() , crypto_info: info.clone(), verified: false, vouched_by: None }
     }
 
     pub fn name(&self) -> &str
@@ -26,4 +31,40 @@ impl LastingContactInfo
     {
         &self.crypto_info
     }
-}
\ No newline at end of file
+
+    pub fn crypto_info_mut(&mut self) -> &mut CryptoLastingInfo
+    {
+        &mut self.crypto_info
+    }
+
+    pub fn is_verified(&self) -> bool
+    {
+        self.verified
+    }
+
+    pub fn set_verified(&mut self, verified: bool)
+    {
+        self.verified = verified;
+    }
+
+    pub fn vouched_by(&self) -> Option<&str>
+    {
+        self.vouched_by.as_deref()
+    }
+
+    pub fn set_vouched_by(&mut self, vouched_by: Option<String>)
+    {
+        self.vouched_by = vouched_by;
+    }
+
+    /// How much the identity key is trusted, shown when connecting
+    pub fn trust_description(&self) -> String
+    {
+        match (self.verified, &self.vouched_by)
+        {
+            (true, _) => "verified".to_string(),
+            (false, Some(introducer)) => format!("trusted via introduction by {}", introducer),
+            (false, None) => "trusted on first use".to_string(),
+        }
+    }
+}
@@ -9,6 +9,9 @@ pub mod content;
 pub mod secure_packet;
 pub mod lasting_contact_info;
 pub mod user_info;
+pub mod key_change_decision;
+pub mod invite;
+pub mod bandwidth_stats;
 
 pub use contact_info::ContactInfo;
 pub use connection_list::ConnectionList;
@@ -18,4 +21,7 @@ pub use packet::Packet;
 pub use content::Content;
 pub use secure_packet::SecurePacket;
 pub use lasting_contact_info::LastingContactInfo;
-pub use user_info::UserInfo;
\ No newline at end of file
+pub use user_info::UserInfo;
+pub use key_change_decision::KeyChangeDecision;
+pub use invite::Invite;
+pub use bandwidth_stats::BandwidthStats;
\ No newline at end of file
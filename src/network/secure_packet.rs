@@ -1,6 +1,6 @@
 use serializable::Serializable;
 
-use crate::crypto::Ciphertext;
+use crate::crypto::{Ciphertext, SymmetricKey};
 
 use super::Packet;
 
@@ -9,4 +9,15 @@ pub enum SecurePacket
 {
     Ciphertext(Ciphertext),
     Plaintext(Packet),
+    /// plaintext packet with the mac of the pre-shared key
+    Authenticated(Packet,Vec<u8>),
+}
+
+impl SecurePacket
+{
+    pub fn authenticate(packet: Packet, psk: &SymmetricKey) -> Self
+    {
+        let mac = psk.mac(&packet.serialize());
+        SecurePacket::Authenticated(packet, mac)
+    }
 }
\ No newline at end of file
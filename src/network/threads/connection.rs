@@ -1,19 +1,32 @@
-use std::{sync::{mpsc::{Sender, Receiver}, Arc, RwLock}, net::SocketAddr, collections::HashMap, time::{Duration, Instant}};
+use std::{sync::{mpsc::{Sender, Receiver}, Arc, RwLock}, net::SocketAddr, collections::{HashMap, HashSet}, time::{Duration, Instant}};
 
-use crate::{config::{Config, defines}, network::{ConnectionList, Packet, Content, ContactInfo, ConnectionRequest, LastingContactInfo, UserInfo}, log::{Logger, MessageKind}, crypto::{CryptoHandshakeInfo, PrivateKey, CryptoLastingInfo}};
+use crate::{config::{Config, defines, AclAction}, network::{ConnectionList, Packet, Content, ContactInfo, ConnectionRequest, LastingContactInfo, UserInfo, KeyChangeDecision, Invite}, log::{Logger, MessageKind}, crypto::{CryptoHandshakeInfo, CryptoLastingInfo, PublicKey, KeyRotationStep, SignedIntroduction, SignedRejection}, ui::UiNotification, hooks::HookEvent, text::History};
 
 pub fn run(
     running: Arc<RwLock<bool>>,
     connection_list: Arc<RwLock<ConnectionList>>,
     log: Logger,
     requests: Receiver<ConnectionRequest>,
+    ui_notifications: Sender<UiNotification>,
     connection_queue: Receiver<(Packet,SocketAddr)>, 
     sender_queue: Sender<(Content,SocketAddr)>,
+    hook_events: Sender<HookEvent>,
     config: Arc<RwLock<Config>>
 )
 {
     let mut pending_requests = HashMap::<SocketAddr,(Option<ContactInfo>,CryptoHandshakeInfo,Instant,u16)>::new();
     let mut pending_user_info_requests = HashMap::<(String,SocketAddr),(Option<SocketAddr>,Instant)>::new();
+    // identity key changes waiting for a decision from the user, with the key presented, the address of the peer
+    // and when it was presented, only the latest one of each contact is kept until it expires
+    let mut pending_key_changes = HashMap::<String,(PublicKey,SocketAddr,Instant)>::new();
+    // keys the user accepted without replacing the known host, forgotten on restart
+    let mut accepted_once_keys = HashMap::<String,PublicKey>::new();
+    // tokens of the invites we are answering, sent again if the request is repeated
+    let mut invite_tokens = HashMap::<SocketAddr,String>::new();
+    // requests the acl told us to ask the user about
+    let mut pending_asks = HashSet::<SocketAddr>::new();
+    // when the latest new peers were accepted, for the rate limit
+    let mut stranger_requests = Vec::<Instant>::new();
     while *running.read().unwrap()
     {
         match connection_queue.recv_timeout(defines::THREAD_QUEUE_TIMEOUT)
@@ -53,27 +66,99 @@ pub fn run(
                     {
                         if let Ok(unsafe_info) = signed_contact_info.info()
                         {
-                            let (public_key, mut add_to_known_hosts) = {
+                            let (mut public_key, verified, mut add_to_known_hosts) = {
                                 let config = config.read().unwrap();
                                 if let Some(known_host) = config.network.known_hosts.get(unsafe_info.name())
                                 {
-                                    (known_host.crypto_info().public_key.clone(),false)
+                                    (known_host.crypto_info().public_key.clone(),known_host.is_verified(),false)
+                                }
+                                else if let Some(fingerprint) = config.network.pinned_fingerprints.get(unsafe_info.name())
+                                {
+                                    if Invite::fingerprint_of(&unsafe_info.crypto_info().public_key) != *fingerprint
+                                    {
+                                        log.audit(&format!("{} presented a key that does not match the invite (received {})",
+                                            unsafe_info.name(),
+                                            unsafe_info.crypto_info().public_key.fingerprint_string()));
+                                        continue;
+                                    }
+                                    log.audit(&format!("New peer {} authenticated by the invite (fingerprint {})",
+                                        unsafe_info.name(),
+                                        unsafe_info.crypto_info().public_key.fingerprint_string()));
+                                    (unsafe_info.crypto_info().public_key.clone(),false,true)
                                 }
                                 else
                                 {
-                                    log.log(MessageKind::Event, &format!("New peer {} added",unsafe_info.name())).unwrap();
-                                    (unsafe_info.crypto_info().public_key.clone(),true)
+                                    log.log(MessageKind::Event, &format!("New peer {} added (fingerprint {}), verify it from the contact info",
+                                        unsafe_info.name(), 
+                                        unsafe_info.crypto_info().public_key.fingerprint_string())).unwrap();
+                                    (unsafe_info.crypto_info().public_key.clone(),false,true)
                                 }
                             };
+                            let mut temporary_identity = false;
                             if public_key != unsafe_info.crypto_info().public_key
                             {
-                                log.log(MessageKind::Error, &format!("Public key mismatch for {}",unsafe_info.name())).unwrap();
-                                continue;
+                                let presented_key = unsafe_info.crypto_info().public_key.clone();
+                                if KeyRotationStep::verify_chain(&unsafe_info.crypto_info().key_rotations, &public_key, &presented_key) && 
+                                    signed_contact_info.into_contact_info(&presented_key).is_ok()
+                                {
+                                    // the stored key certified the new one, the peer keeps its verification
+                                    {
+                                        let mut config = config.write().unwrap();
+                                        if let Some(known_host) = config.network.known_hosts.get_mut(unsafe_info.name())
+                                        {
+                                            known_host.crypto_info_mut().public_key = presented_key.clone();
+                                        }
+                                        // the session was agreed with the old key, a new one is started with the new key
+                                        config.text.ratchet_sessions.remove(unsafe_info.name());
+                                        // the history is kept in a file named after the identity key
+                                        if let Err(e) = History::from_config(&config).rename(&History::identity(&public_key), &History::identity(&presented_key))
+                                        {
+                                            log.log(MessageKind::Error, &format!("Error moving the history of {}: {}", unsafe_info.name(), e)).unwrap();
+                                        }
+                                    }
+                                    log.audit(&format!("{} rotated the identity key from {} to {}",
+                                        unsafe_info.name(),
+                                        public_key.fingerprint_string(),
+                                        presented_key.fingerprint_string()));
+                                    public_key = presented_key;
+                                }
+                                else if accepted_once_keys.get(unsafe_info.name()) == Some(&presented_key)
+                                {
+                                    public_key = presented_key;
+                                    temporary_identity = true;
+                                }
+                                else
+                                {
+                                    // only ask the user if the request is really signed with the new key
+                                    if signed_contact_info.into_contact_info(&presented_key).is_ok() && 
+                                        !pending_key_changes.get(unsafe_info.name()).is_some_and(|(pending_key, _, since)|
+                                            pending_key == &presented_key && since.elapsed() < defines::KEY_CHANGE_TIMEOUT)
+                                    {
+                                        if verified
+                                        {
+                                            log.audit(&format!("WARNING: the identity key of verified contact {} changed, someone may be impersonating them (expected {}, received {})",
+                                                unsafe_info.name(),
+                                                public_key.fingerprint_string(),
+                                                presented_key.fingerprint_string()));
+                                        }
+                                        else
+                                        {
+                                            log.audit(&format!("Identity key of {} changed (expected {}, received {})",
+                                                unsafe_info.name(),
+                                                public_key.fingerprint_string(),
+                                                presented_key.fingerprint_string()));
+                                        }
+                                        pending_key_changes.insert(unsafe_info.name().to_string(), (presented_key.clone(), from, Instant::now()));
+                                        ui_notifications.send(UiNotification::IdentityKeyChanged(unsafe_info.name().to_string(), public_key, presented_key)).unwrap();
+                                    }
+                                    continue;
+                                }
                             }
                             match &signed_contact_info.into_contact_info(&public_key)
                             {
                                 Ok(contact_info) =>
                                 {
+                                    let mut used_invite_token = None;
                                     {
                                         let connection_list = connection_list.read().unwrap();
                                         if let Some(name) = connection_list.get_name(&from)
@@ -97,19 +182,20 @@ pub fn run(
                                                 {
                                                     // no info has changed, send the same response
                                                     let config_reader = config.read().unwrap();
-                                                    sender_queue.send((Content::request_connection_from_config(&config_reader,crypto_handshake_info.local_ecdhe_key.public_key().clone()), from)).unwrap();
+                                                    sender_queue.send((Content::request_connection_from_config(&config_reader,crypto_handshake_info.public_keys(),None), from)).unwrap();
                                                 }
                                             },
                                             None =>
                                             {
                                                 // this peer sent the initial request and the other peer responded, finalize the connection
+                                                let psk = config.read().unwrap().network.psk_key();
                                                 let success = 
                                                 {
                                                     let mut connection_list = connection_list.write().unwrap();
-                                                    crypto_handshake_info.remote_ecdhe_key = Some(contact_info.crypto_info().ecdhe_public_key.clone());
-                                                    if let Ok(symmetric_key) = crypto_handshake_info.derive()
+                                                    crypto_handshake_info.set_remote(&contact_info.crypto_info().ecdhe_keys);
+                                                    if let Ok(symmetric_key) = crypto_handshake_info.derive(psk.as_ref())
                                                     {
-                                                        connection_list.add(contact_info.name(), from, symmetric_key);
+                                                        connection_list.add_with_identity(contact_info.name(), from, symmetric_key, contact_info.crypto_info().into_lasting());
                                                         true
                                                     }
                                                     else 
@@ -117,15 +203,17 @@ pub fn run(
                                                         false
                                                     }
                                                 };
-                                                if success
+                                                if let (true, Some(cipher_suite)) = (success, crypto_handshake_info.cipher_suite())
                                                 {
-                                                    log.log(MessageKind::Event, &format!("Connection to {} established", contact_info.name())).unwrap();
+                                                    log.log(MessageKind::Event, &format!("Connection to {} established ({}, {})", contact_info.name(), cipher_suite.name(), trust_description(&config, contact_info))).unwrap();
                                                     sender_queue.send((Content::AcknowledgeConnection, from)).unwrap();
+                                                    hook_events.send(HookEvent::Connected(contact_info.name().to_string(), from)).unwrap();
                                                 }
                                                 else {
                                                     log.log(MessageKind::Error, &format!("Key exchange with {} failed", contact_info.name())).unwrap();
                                                 }
                                                 pending_requests.remove(&from);
+                                                invite_tokens.remove(&from);
                                                 
                                             }
                                         }
@@ -134,35 +222,100 @@ pub fn run(
                                     {
                                         // the other peer is requesting the connection
                                         let config_reader = config.read().unwrap();
-                                        let mut accept_connection = true;
-                                        if let Some(whitelist) = &config_reader.network.whitelist
+                                        let acl = &config_reader.network.acl;
+                                        let valid_token = contact_info.invite_token()
+                                            .filter(|token| config_reader.network.invite_tokens.iter().any(|t| t == token));
+                                        let mut action = match (acl.matching_rule(contact_info.name(), &public_key, &from), valid_token)
+                                        {
+                                            (Some(rule), _) => rule.action,
+                                            // a valid token lets the peer in unless a rule says otherwise
+                                            (None, Some(token)) =>
+                                            {
+                                                used_invite_token = Some(token.to_string());
+                                                AclAction::Accept
+                                            },
+                                            (None, None) => acl.default_action,
+                                        };
+                                        if add_to_known_hosts && used_invite_token.is_none() && action != AclAction::Drop
+                                        {
+                                            if let Some(limit) = acl.max_strangers_per_minute
+                                            {
+                                                stranger_requests.retain(|time| time.elapsed() < Duration::from_secs(60));
+                                                if stranger_requests.len() >= limit as usize
+                                                {
+                                                    action = AclAction::Drop;
+                                                }
+                                                else
+                                                {
+                                                    stranger_requests.push(Instant::now());
+                                                }
+                                            }
+                                        }
+                                        match action
                                         {
-                                            accept_connection = whitelist.iter().any(|name| name == contact_info.name())
+                                            AclAction::Accept => {},
+                                            AclAction::Reject =>
+                                            {
+                                                let rejection = SignedRejection::new(&contact_info.crypto_info().ecdhe_keys, &config_reader.network.private_key);
+                                                sender_queue.send((Content::RejectConnection(rejection), from)).unwrap();
+                                                log.log(MessageKind::Event, &format!("Rejected the connection from {} ({})", contact_info.name(), from)).unwrap();
+                                            },
+                                            AclAction::Ask =>
+                                            {
+                                                if !pending_asks.contains(&from)
+                                                {
+                                                    pending_asks.insert(from);
+                                                    ui_notifications.send(UiNotification::IncomingConnection(contact_info.name().to_string(), public_key.clone(), from)).unwrap();
+                                                }
+                                            },
+                                            AclAction::Drop => {},
+                                        }
+                                        let crypto_handshake_info = if action == AclAction::Accept
+                                        {
+                                            let crypto_handshake_info = CryptoHandshakeInfo::accept(&contact_info.crypto_info().ecdhe_keys, &config_reader.network.cipher_suites);
+                                            if crypto_handshake_info.is_none()
+                                            {
+                                                log.log(MessageKind::Error, &format!("No common cipher suite with {}", contact_info.name())).unwrap();
+                                            }
+                                            crypto_handshake_info
                                         }
-                                        if accept_connection
+                                        else
+                                        {
+                                            None
+                                        };
+                                        if let Some(crypto_handshake_info) = crypto_handshake_info
                                         {
                                             // accept the connection
-                                            let private_ecdhe_key = PrivateKey::new();
-                                            let public_ecdhe_key = private_ecdhe_key.public_key();
-                                            let crypto_handshake_info = CryptoHandshakeInfo {
-                                                local_ecdhe_key: private_ecdhe_key,
-                                                remote_ecdhe_key: Some(contact_info.crypto_info().ecdhe_public_key.clone()),
-                                            };
-                                            sender_queue.send((Content::request_connection_from_config(&config_reader, public_ecdhe_key), from)).unwrap();
+                                            sender_queue.send((Content::request_connection_from_config(&config_reader, crypto_handshake_info.public_keys(), None), from)).unwrap();
                                             pending_requests.insert(from, (Some(contact_info.clone()),crypto_handshake_info,Instant::now(),0));
                                         }
                                         else {
                                             add_to_known_hosts = false;
                                         }
                                     }
+                                    if let Some(token) = used_invite_token
+                                    {
+                                        config.write().unwrap().network.invite_tokens.retain(|t| *t != token);
+                                        log.audit(&format!("{} used a one-time invite token", contact_info.name()));
+                                    }
                                     if add_to_known_hosts
                                     {
                                         let mut config = config.write().unwrap();
+                                        config.network.pinned_fingerprints.remove(unsafe_info.name());
                                         config.network.known_hosts.insert(unsafe_info.name().to_string(),LastingContactInfo::new(
                                             unsafe_info.name(),
                                             &unsafe_info.crypto_info().into_lasting(),
                                         ));
                                     }
+                                    else if !temporary_identity
+                                    {
+                                        // the prekey is signed with the identity key, keep the latest one for the ratchet
+                                        let mut config = config.write().unwrap();
+                                        if let Some(known_host) = config.network.known_hosts.get_mut(contact_info.name())
+                                        {
+                                            known_host.crypto_info_mut().prekey = Some(contact_info.crypto_info().prekey.clone());
+                                        }
+                                    }
                                 }
                                 Err(e) => 
                                 {
@@ -171,6 +324,29 @@ pub fn run(
                             };
                         }
                     },
+                    Content::RejectConnection(rejection) =>
+                    {
+                        // only the peer we are connecting to can refuse, with a known identity and for this very request
+                        if let Some((None, crypto_handshake_info, _last_seen, _strikes)) = pending_requests.get(&from)
+                        {
+                            let known = {
+                                let config = config.read().unwrap();
+                                rejection.verify(&crypto_handshake_info.public_keys()).is_some_and(|public_key| 
+                                    config.network.known_hosts.values().any(|known_host| known_host.crypto_info().public_key == *public_key) ||
+                                    config.network.pinned_fingerprints.values().any(|fingerprint| Invite::fingerprint_of(public_key) == *fingerprint))
+                            };
+                            if known
+                            {
+                                pending_requests.remove(&from);
+                                invite_tokens.remove(&from);
+                                log.log(MessageKind::Error, &format!("Connection to {} was rejected", from)).unwrap();
+                            }
+                            else
+                            {
+                                log.log(MessageKind::Error, &format!("Ignored a rejection from {} that is not signed by a known peer", from)).unwrap();
+                            }
+                        }
+                    },
                     Content::AcknowledgeConnection => 
                     {
                         if let Some((option_info, crypto_handshake_info, _last_seen, _strikes)) = pending_requests.get_mut(&from)
@@ -178,24 +354,27 @@ pub fn run(
                             if let Some(contact_info) = option_info
                             {
                                 let mut connection_ok = false;
+                                let psk = config.read().unwrap().network.psk_key();
                                 // the other peer started the connection and has acknowledged our response
                                 {
                                     let mut connection_list = connection_list.write().unwrap();
-                                    if let Ok(symmetric_key) = crypto_handshake_info.derive()
+                                    if let Ok(symmetric_key) = crypto_handshake_info.derive(psk.as_ref())
                                     {
-                                        connection_list.add(contact_info.name(), from, symmetric_key);
+                                        connection_list.add_with_identity(contact_info.name(), from, symmetric_key, contact_info.crypto_info().into_lasting());
                                         connection_ok = true;
                                     }
                                 }
-                                if connection_ok
+                                if let (true, Some(cipher_suite)) = (connection_ok, crypto_handshake_info.cipher_suite())
                                 {
-                                    log.log(MessageKind::Event, &format!("Connection to {} established", contact_info.name())).unwrap();
+                                    log.log(MessageKind::Event, &format!("Connection to {} established ({}, {})", contact_info.name(), cipher_suite.name(), trust_description(&config, contact_info))).unwrap();
+                                    hook_events.send(HookEvent::Connected(contact_info.name().to_string(), from)).unwrap();
                                 }
                                 else
                                 {
                                     log.log(MessageKind::Error, &format!("Connection to {} failed", contact_info.name())).unwrap();
                                 }
                                 pending_requests.remove(&from);
+                                invite_tokens.remove(&from);
                             }
                             else {
                                 // this peer started the connection and was not expecting an ack from the other peer
@@ -257,7 +436,7 @@ pub fn run(
                                     if prev.is_none()
                                     {
                                         // we are the source of the request
-                                        request_connection(address, &mut pending_requests, &sender_queue, config.clone()).unwrap();
+                                        request_connection(address, None, &mut pending_requests, &sender_queue, config.clone()).unwrap();
                                     }
                                 }
                                 if let Some(addr) = prev
@@ -272,7 +451,65 @@ pub fn run(
                         {
                             pending_user_info_requests.remove_entry(&(info.name().to_string(),from));
                         }
-                    }
+                    },
+                    Content::Introduction(introduction) =>
+                    {
+                        let introducer = connection_list.read().unwrap().get_name(&from).map(str::to_string);
+                        let introducer_info = introducer.as_ref().and_then(|name| config.read().unwrap().network.known_hosts.get(name).cloned());
+                        match (introducer, introducer_info)
+                        {
+                            (Some(introducer), Some(introducer_info)) if introducer_info.is_verified() =>
+                            {
+                                match introduction.into_lasting_contact_info(&introducer_info.crypto_info().public_key)
+                                {
+                                    Ok(mut introduced) =>
+                                    {
+                                        let mut config = config.write().unwrap();
+                                        let own_name = config.network.name.clone();
+                                        if introduced.name() == own_name || introduced.name() == introducer
+                                        {
+                                            continue;
+                                        }
+                                        let fingerprint = introduced.crypto_info().public_key.fingerprint_string();
+                                        match config.network.known_hosts.get_mut(introduced.name())
+                                        {
+                                            Some(known_host) if known_host.crypto_info().public_key != introduced.crypto_info().public_key =>
+                                            {
+                                                log.audit(&format!("{} introduced {} with a different key than the stored one (expected {}, received {}), ignoring it",
+                                                    introducer,
+                                                    introduced.name(),
+                                                    known_host.crypto_info().public_key.fingerprint_string(),
+                                                    fingerprint));
+                                            },
+                                            Some(known_host) =>
+                                            {
+                                                if !known_host.is_verified() && known_host.vouched_by().is_none()
+                                                {
+                                                    known_host.set_vouched_by(Some(introducer.clone()));
+                                                    log.audit(&format!("{} vouched for {} ({})", introducer, introduced.name(), fingerprint));
+                                                }
+                                            },
+                                            None =>
+                                            {
+                                                introduced.set_vouched_by(Some(introducer.clone()));
+                                                log.audit(&format!("{} introduced {} ({})", introducer, introduced.name(), fingerprint));
+                                                config.network.known_hosts.insert(introduced.name().to_string(), introduced);
+                                            },
+                                        }
+                                    },
+                                    Err(e) =>
+                                    {
+                                        log.log(MessageKind::Error, &format!("Invalid introduction from {}: {}", introducer, e)).unwrap();
+                                    }
+                                }
+                            },
+                            (Some(introducer), _) =>
+                            {
+                                log.log(MessageKind::Event, &format!("Ignored an introduction from {}, only verified contacts can introduce others", introducer)).unwrap();
+                            },
+                            (None, _) => {},
+                        }
+                    },
                     _ => unreachable!("Connection thread received non-connection packet: {:?}",packet)
                 }
             },
@@ -283,6 +520,7 @@ pub fn run(
                     std::sync::mpsc::RecvTimeoutError::Timeout => {
                         //check for timed out pending requests
                         let config = config.read().unwrap().clone();
+                        pending_key_changes.retain(|_, (_, _, since)| since.elapsed() < defines::KEY_CHANGE_TIMEOUT);
 
                         let mut timed_out_pending_requests = Vec::new();
                         {
@@ -298,7 +536,7 @@ pub fn run(
                                     else
                                     {
                                         // send another request
-                                        sender_queue.send((Content::request_connection_from_config(&config, crypto_handshake_info.local_ecdhe_key.public_key()), address.clone())).unwrap();
+                                        sender_queue.send((Content::request_connection_from_config(&config, crypto_handshake_info.public_keys(), invite_tokens.get(address).cloned()), address.clone())).unwrap();
                                         *strikes += 1;
                                         *last_seen = Instant::now();
                                     }
@@ -308,6 +546,7 @@ pub fn run(
                         for address in timed_out_pending_requests
                         {
                             pending_requests.remove(&address);
+                            invite_tokens.remove(&address);
                             log.log(MessageKind::Error, &format!("Connection to {} timed out", address)).unwrap();
                         }
 
@@ -360,6 +599,10 @@ pub fn run(
                             }
                             for address in timed_out_connections
                             {
+                                if let Some(name) = connection_list.get_name(&address)
+                                {
+                                    hook_events.send(HookEvent::Disconnected(name.to_string())).unwrap();
+                                }
                                 connection_list.remove_with_address(&address);
                                 log.log(MessageKind::Event, &format!("Connection to {} timed out", address)).unwrap();
                             }
@@ -384,7 +627,45 @@ pub fn run(
                 {
                     ConnectionRequest::Connect(to) => 
                     {
-                        request_connection(to, &mut pending_requests, &sender_queue, config.clone()).unwrap();
+                        request_connection(to, None, &mut pending_requests, &sender_queue, config.clone()).unwrap();
+                    },
+                    ConnectionRequest::AcceptInvite(invite) =>
+                    {
+                        let pinned = {
+                            let mut config = config.write().unwrap();
+                            match config.network.known_hosts.get(invite.name())
+                            {
+                                Some(known_host) if !invite.matches(&known_host.crypto_info().public_key) =>
+                                {
+                                    log.audit(&format!("The invite of {} does not match the stored key {}, ignoring it",
+                                        invite.name(),
+                                        known_host.crypto_info().public_key.fingerprint_string()));
+                                    false
+                                },
+                                Some(_) => true,
+                                None =>
+                                {
+                                    config.network.pinned_fingerprints.insert(invite.name().to_string(), invite.fingerprint().to_string());
+                                    true
+                                },
+                            }
+                        };
+                        let address = invite.addresses().iter()
+                            .find(|address| defines::HOST.is_ipv4() == address.is_ipv4())
+                            .copied();
+                        match (pinned, address)
+                        {
+                            (true, Some(address)) =>
+                            {
+                                if let Some(token) = invite.token()
+                                {
+                                    invite_tokens.insert(address, token.to_string());
+                                }
+                                request_connection(address, invite.token().map(|token| token.to_string()), &mut pending_requests, &sender_queue, config.clone()).unwrap();
+                            },
+                            (true, None) => log.log(MessageKind::Error, &format!("No usable address in the invite of {}", invite.name())).unwrap(),
+                            (false, _) => {},
+                        }
                     },
                     ConnectionRequest::Find(name) =>
                     {
@@ -393,8 +674,80 @@ pub fn run(
                     ConnectionRequest::Disconnect(from) => 
                     {
                         let mut connection_list = connection_list.write().unwrap();
+                        if connection_list.get_address(&from).is_some()
+                        {
+                            hook_events.send(HookEvent::Disconnected(from.clone())).unwrap();
+                        }
                         connection_list.remove_with_name(&from);
                     },
+                    ConnectionRequest::AnswerIncoming(address, accept) =>
+                    {
+                        if pending_asks.remove(&address) && accept
+                        {
+                            // the peer gave up waiting, connect back to it
+                            request_connection(address, None, &mut pending_requests, &sender_queue, config.clone()).unwrap();
+                        }
+                    },
+                    ConnectionRequest::Introduce(to, name) =>
+                    {
+                        let address = connection_list.read().unwrap().get_address(&to).copied();
+                        let introduction = {
+                            let config = config.read().unwrap();
+                            config.network.known_hosts.get(&name)
+                                .map(|known_host| SignedIntroduction::from_lasting_contact_info(known_host, &config.network.private_key))
+                        };
+                        match (address, introduction)
+                        {
+                            (Some(address), Some(introduction)) =>
+                            {
+                                sender_queue.send((Content::Introduction(introduction), address)).unwrap();
+                                log.log(MessageKind::Event, &format!("Introduced {} to {}", name, to)).unwrap();
+                            },
+                            (None, _) => log.log(MessageKind::Error, &format!("{} is not connected", to)).unwrap(),
+                            (_, None) => log.log(MessageKind::Error, &format!("No identity key is known for {}", name)).unwrap(),
+                        }
+                    },
+                    ConnectionRequest::ResolveKeyChange(name, decision) =>
+                    {
+                        let pending = pending_key_changes.remove(&name)
+                            .filter(|(_, _, since)| since.elapsed() < defines::KEY_CHANGE_TIMEOUT);
+                        if pending.is_none()
+                        {
+                            log.log(MessageKind::Error, &format!("The key change of {} expired, it will be asked again when {} connects", name, name)).unwrap();
+                        }
+                        if let Some((presented_key, address, _since)) = pending
+                        {
+                            match decision
+                            {
+                                KeyChangeDecision::Reject =>
+                                {
+                                    log.audit(&format!("Rejected the new identity key of {} ({})", name, presented_key.fingerprint_string()));
+                                },
+                                KeyChangeDecision::AcceptOnce =>
+                                {
+                                    {
+                                        // the ratchet session is bound to the old identity, a new one will be started
+                                        let mut config = config.write().unwrap();
+                                        config.text.ratchet_sessions.remove(&name);
+                                    }
+                                    log.audit(&format!("Accepted the new identity key of {} ({}) until restart", name, presented_key.fingerprint_string()));
+                                    accepted_once_keys.insert(name, presented_key);
+                                    request_connection(address, None, &mut pending_requests, &sender_queue, config.clone()).unwrap();
+                                },
+                                KeyChangeDecision::Replace =>
+                                {
+                                    {
+                                        let mut config = config.write().unwrap();
+                                        // the new key was never verified and the old ratchet session is useless
+                                        config.network.known_hosts.insert(name.clone(), LastingContactInfo::new(&name, &CryptoLastingInfo::new(&presented_key)));
+                                        config.text.ratchet_sessions.remove(&name);
+                                    }
+                                    log.audit(&format!("Replaced the identity key of {} with {}", name, presented_key.fingerprint_string()));
+                                    request_connection(address, None, &mut pending_requests, &sender_queue, config.clone()).unwrap();
+                                },
+                            }
+                        }
+                    },
                 }
             },
             Err(e) => 
@@ -415,6 +768,18 @@ pub fn run(
     }
 }
 
+fn trust_description(config: &Arc<RwLock<Config>>, contact_info: &ContactInfo) -> String
+{
+    let config = config.read().unwrap();
+    match config.network.known_hosts.get(contact_info.name())
+    {
+        Some(known_host) if known_host.crypto_info().public_key == contact_info.crypto_info().public_key => known_host.trust_description(),
+        Some(_) => "accepted until restart".to_string(),
+        // new peers are added to the known hosts after the handshake
+        None => "trusted on first use".to_string(),
+    }
+}
+
 fn find_user(
     from: Option<SocketAddr>,
     name: &str, 
@@ -454,20 +819,15 @@ fn find_user(
 
 fn request_connection(
     to: SocketAddr,
+    invite_token: Option<String>,
     pending_requests: &mut HashMap::<SocketAddr,(Option<ContactInfo>,CryptoHandshakeInfo,Instant,u16)>,
     sender_queue: &Sender<(Content,SocketAddr)>,
     config: Arc<RwLock<Config>>
 ) -> Result<(),String>
 {
     let config = config.read().unwrap().clone();
-    let private_ecdhe_key = PrivateKey::new();
-    let public_ecdhe_key = private_ecdhe_key.public_key();
-    let crypto_handshake_info = CryptoHandshakeInfo
-    {
-        local_ecdhe_key: private_ecdhe_key,
-        remote_ecdhe_key: None,
-    };
-    sender_queue.send((Content::request_connection_from_config(&config, public_ecdhe_key), to)).unwrap();
+    let crypto_handshake_info = CryptoHandshakeInfo::offer(&config.network.cipher_suites);
+    sender_queue.send((Content::request_connection_from_config(&config, crypto_handshake_info.public_keys(), invite_token), to)).unwrap();
     pending_requests.insert(to, (None,crypto_handshake_info,Instant::now(),0));
     Ok(())
 }
\ No newline at end of file
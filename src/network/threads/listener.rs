@@ -2,7 +2,7 @@ use std::{net::{UdpSocket, SocketAddr}, sync::{Arc, mpsc::Sender, RwLock}};
 
 use serializable::Serializable;
 
-use crate::{network::{Packet, Content, ConnectionList, SecurePacket}, config::{Config, defines}, log::{Logger, MessageKind}};
+use crate::{network::{Packet, Content, ConnectionList, SecurePacket, BandwidthStats}, config::{Config, defines}, log::{Logger, MessageKind}};
 
 pub fn run(
     running: Arc<RwLock<bool>>,
@@ -13,7 +13,8 @@ pub fn run(
     file_queue: Sender<(Packet,SocketAddr)>,
     connection_queue: Sender<(Packet,SocketAddr)>,
     voice_queue: Sender<(Packet,SocketAddr)>,
-    _config: Arc<RwLock<Config>>
+    bandwidth_stats: Arc<RwLock<BandwidthStats>>,
+    config: Arc<RwLock<Config>>
 )
 {
     let mut buffer = [0u8; defines::MAX_PACKET_SIZE];
@@ -23,6 +24,7 @@ pub fn run(
         {
             Ok((len,from)) => 
             {
+                bandwidth_stats.write().unwrap().record_received(len);
                 let (secure_packet,packet_size) = match SecurePacket::deserialize(&buffer[..len])
                 {
                     Ok(sp) => sp,
@@ -37,20 +39,19 @@ pub fn run(
                     log.log(MessageKind::Error, &format!("Packet size mismatch: {} != {}",packet_size,len)).unwrap();
                     continue;
                 }
+                let psk = config.read().unwrap().network.psk_key();
                 let packet = match secure_packet
                 {
+                    // on a closed network only the packets with the right mac are looked at, the others are dropped silently
+                    SecurePacket::Plaintext(_) if psk.is_some() => continue,
+                    SecurePacket::Authenticated(p, mac) if psk.as_ref().map(|psk| psk.verify_mac(&p.serialize(), &mac)).unwrap_or(false) => 
+                    {
+                        check_plaintext(p, from, &log)
+                    },
+                    SecurePacket::Authenticated(_, _) => continue,
                     SecurePacket::Plaintext(p) => 
                     {
-
-                        match p.content 
-                        {
-                            Content::RequestConnection(_) |
-                            Content::AcknowledgeConnection => {},
-                            _  => {
-                                log.log(MessageKind::Error, &format!("Received unexpected plaintext packet from {}",from)).unwrap();
-                            }
-                        };
-                        p
+                        check_plaintext(p, from, &log)
                     },
                     SecurePacket::Ciphertext(c) => 
                     {
@@ -76,16 +77,31 @@ pub fn run(
                 };
                 //log.log(MessageKind::Event, &format!("Received {:.unwrap()} from {}", packet, from)).unwrap();
                 let queue = match &packet.content {
-                    Content::Text(_,_) |
-                    Content::AcknowledgeText(_,_) => {
+                    Content::Cover => continue,
+                    Content::Text(_,_,_) |
+                    Content::ReplyText(_,_,_,_) |
+                    Content::EditText(_,_) |
+                    Content::DeleteText(_) |
+                    Content::Reaction(_,_,_) |
+                    Content::Typing |
+                    Content::DisappearingTimer(_,_) |
+                    Content::AcknowledgeText(_) |
+                    Content::AcknowledgeChange(_) |
+                    Content::ReadReceipt(_) |
+                    Content::Ratchet(_) |
+                    Content::ResetRatchet |
+                    Content::GroupUpdate(_,_) |
+                    Content::GroupText(_,_) => {
                         &text_queue
                     },
                     Content::Ping |
                     Content::Pong |
                     Content::RequestConnection(_) |
                     Content::AcknowledgeConnection |
+                    Content::RejectConnection(_) |
                     Content::RequestUserInfo(_,_) |
-                    Content::UserInfo(_) => 
+                    Content::UserInfo(_) |
+                    Content::Introduction(_) => 
                     {
                         &connection_queue
                     },
@@ -114,4 +130,62 @@ pub fn run(
             }
         }
     }
-}
\ No newline at end of file
+}
+
+fn check_plaintext(packet: Packet, from: SocketAddr, log: &Logger) -> Packet
+{
+    match packet.content 
+    {
+        Content::RequestConnection(_) |
+        Content::AcknowledgeConnection |
+        Content::RejectConnection(_) => {},
+        _  => {
+            log.log(MessageKind::Error, &format!("Received unexpected plaintext packet from {}",from)).unwrap();
+        }
+    };
+    packet
+}
+#[cfg(test)]
+mod tests
+{
+    use std::sync::mpsc::channel;
+
+    use crate::{crypto::SymmetricKey, network::socket};
+
+    use super::*;
+
+    #[test]
+    fn closed_network()
+    {
+        let running = Arc::new(RwLock::new(true));
+        let socket = Arc::new(socket::create(0).unwrap());
+        let address: SocketAddr = ([127, 0, 0, 1], socket.local_addr().unwrap().port()).into();
+        let mut config = Config::default();
+        config.network.psk = Some("secret".to_string());
+        let psk = config.network.psk_key().unwrap();
+        let (text_queue, _text_packets) = channel();
+        let (file_queue, _file_packets) = channel();
+        let (connection_queue, connection_packets) = channel();
+        let (voice_queue, _voice_packets) = channel();
+        let listener = {
+            let (running, socket) = (running.clone(), socket.clone());
+            let config = Arc::new(RwLock::new(config));
+            std::thread::spawn(move || run(running, socket, Arc::new(RwLock::new(ConnectionList::new())), Logger::new(),
+                text_queue, file_queue, connection_queue, voice_queue, Arc::new(RwLock::new(BandwidthStats::new())), config))
+        };
+
+        let peer = socket::create(0).unwrap();
+        let packet = || Packet::from_content_now(Content::AcknowledgeConnection);
+        // a peer without the key, or with another one, is dropped
+        peer.send_to(&SecurePacket::Plaintext(packet()).serialize(), address).unwrap();
+        peer.send_to(&SecurePacket::authenticate(packet(), &SymmetricKey::random()).serialize(), address).unwrap();
+        assert!(connection_packets.recv_timeout(4*defines::THREAD_QUEUE_TIMEOUT).is_err());
+        // one with the key is let in
+        peer.send_to(&SecurePacket::authenticate(packet(), &psk).serialize(), address).unwrap();
+        let (received, _from) = connection_packets.recv_timeout(4*defines::THREAD_QUEUE_TIMEOUT).unwrap();
+        assert_eq!(received.content, Content::AcknowledgeConnection);
+
+        *running.write().unwrap() = false;
+        listener.join().unwrap();
+    }
+}
@@ -1,8 +1,8 @@
-use std::{net::{UdpSocket, SocketAddr}, sync::{Arc, mpsc::Receiver, RwLock}};
+use std::{net::{UdpSocket, SocketAddr}, sync::{Arc, mpsc::Receiver, RwLock}, time::{Instant, Duration}};
 
 use serializable::Serializable;
 
-use crate::{network::{Packet, Content, ConnectionList, SecurePacket}, config::{Config, defines}, log::{Logger, MessageKind}, crypto::Ciphertext};
+use crate::{network::{Packet, Content, ConnectionList, SecurePacket, BandwidthStats}, config::{Config, defines}, log::{Logger, MessageKind}, crypto::Ciphertext};
 
 pub fn run(
     running: Arc<RwLock<bool>>,
@@ -10,9 +10,12 @@ pub fn run(
     connection_list: Arc<RwLock<ConnectionList>>,
     log: Logger,
     queue: Receiver<(Content,SocketAddr)>, 
-    _config: Arc<RwLock<Config>>
+    bandwidth_stats: Arc<RwLock<BandwidthStats>>,
+    config: Arc<RwLock<Config>>
 )
 {
+    let mut last_sent = Instant::now();
+    let mut cover_delay = None;
     while *running.read().unwrap()
     {
         match queue.recv_timeout(defines::THREAD_QUEUE_TIMEOUT)
@@ -20,48 +23,32 @@ pub fn run(
             Ok((content, dst)) =>
             {
                 //log.log(MessageKind::Event, &format!("Sending {:.unwrap()} to {}",content, dst)).unwrap();
-                let needs_encryption = match content 
-                {
-                    Content::RequestConnection(_) |
-                    Content::AcknowledgeConnection => false,
-                    _ => true
-                };
-                let packet = Packet::from_content_now(content);
-                let secure_packet = 
-                {
-                    let connection_list = connection_list.read().unwrap();
-                    if let Some(info) = connection_list.get_info_from_addr(&dst)
-                    {
-                        if needs_encryption
-                        {
-                            SecurePacket::Ciphertext(Ciphertext::from_packet(packet, &info.crypto_session_info.symmetric_key))
-                        }
-                        else
-                        {
-                            SecurePacket::Plaintext(packet)    
-                        }
-                    }
-                    else
-                    {
-                        SecurePacket::Plaintext(packet)
-                    }
-                };
-                
-                let bytes = secure_packet.serialize();
-                if bytes.len() > defines::MAX_PACKET_SIZE
-                {
-                    log.log(MessageKind::Error, &format!("Cannot send a packet over {}B, the packet was {}B", defines::MAX_PACKET_SIZE, bytes.len())).unwrap();
-                }
-                else 
-                {
-                    socket.send_to(&bytes, dst).unwrap();    
-                }
+                send(content, dst, &socket, &connection_list, &log, &bandwidth_stats, &config);
+                last_sent = Instant::now();
             }
             Err(e) =>
             {
                 match e
                 {
-                    std::sync::mpsc::RecvTimeoutError::Timeout => {},
+                    std::sync::mpsc::RecvTimeoutError::Timeout => 
+                    {
+                        let cover_traffic_ms = config.read().unwrap().network.cover_traffic_ms;
+                        if let Some(cover_traffic_ms) = cover_traffic_ms
+                        {
+                            // the delay is random so the cover packets don't follow a pattern
+                            let delay = *cover_delay.get_or_insert_with(|| Duration::from_millis(cover_traffic_ms / 2 + rand::random::<u64>() % (cover_traffic_ms + 1)));
+                            if last_sent.elapsed() >= delay
+                            {
+                                let addresses = connection_list.read().unwrap().get_addresses();
+                                for address in addresses
+                                {
+                                    send(Content::Cover, address, &socket, &connection_list, &log, &bandwidth_stats, &config);
+                                }
+                                last_sent = Instant::now();
+                                cover_delay = None;
+                            }
+                        }
+                    },
                     std::sync::mpsc::RecvTimeoutError::Disconnected => 
                     {
                         if !*running.read().unwrap()
@@ -73,4 +60,65 @@ pub fn run(
             },
         }
     }
+}
+
+fn send(
+    content: Content,
+    dst: SocketAddr,
+    socket: &UdpSocket,
+    connection_list: &Arc<RwLock<ConnectionList>>,
+    log: &Logger,
+    bandwidth_stats: &Arc<RwLock<BandwidthStats>>,
+    config: &Arc<RwLock<Config>>
+)
+{
+    let needs_encryption = match content 
+    {
+        Content::RequestConnection(_) |
+        Content::AcknowledgeConnection |
+        Content::RejectConnection(_) => false,
+        _ => true
+    };
+    let is_cover = content == Content::Cover;
+    let (padding, psk) = {
+        let config = config.read().unwrap();
+        (config.network.padding, config.network.psk_key())
+    };
+    let packet = Packet::from_content_now(content);
+    let (secure_packet, padding_len) = 
+    {
+        let connection_list = connection_list.read().unwrap();
+        if let Some(info) = connection_list.get_info_from_addr(&dst)
+        {
+            if needs_encryption
+            {
+                let (ciphertext, padding_len) = Ciphertext::from_packet(packet, &info.crypto_session_info.symmetric_key, padding);
+                (SecurePacket::Ciphertext(ciphertext), padding_len)
+            }
+            else
+            {
+                (SecurePacket::Plaintext(packet), 0)
+            }
+        }
+        else
+        {
+            (SecurePacket::Plaintext(packet), 0)
+        }
+    };
+    
+    let secure_packet = match (secure_packet, psk)
+    {
+        (SecurePacket::Plaintext(packet), Some(psk)) => SecurePacket::authenticate(packet, &psk),
+        (secure_packet, _) => secure_packet,
+    };
+    let bytes = secure_packet.serialize();
+    if bytes.len() > defines::MAX_PACKET_SIZE
+    {
+        log.log(MessageKind::Error, &format!("Cannot send a packet over {}B, the packet was {}B", defines::MAX_PACKET_SIZE, bytes.len())).unwrap();
+    }
+    else 
+    {
+        socket.send_to(&bytes, dst).unwrap();    
+        bandwidth_stats.write().unwrap().record_sent(bytes.len(), padding_len, is_cover);
+    }
 }
\ No newline at end of file
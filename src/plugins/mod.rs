@@ -0,0 +1,12 @@
+pub mod threads;
+pub mod plugin;
+pub mod plugin_state;
+pub mod plugin_info;
+pub mod plugin_list;
+pub mod plugin_request;
+
+pub use plugin::Plugin;
+pub use plugin_state::PluginState;
+pub use plugin_info::PluginInfo;
+pub use plugin_list::PluginList;
+pub use plugin_request::PluginRequest;
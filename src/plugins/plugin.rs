@@ -0,0 +1,250 @@
+use wasmi::{Engine, Module, Store, Linker, Instance, Caller, Extern, core::Trap};
+
+use crate::{config::defines, hooks::{HookEvent, HookEventKind, HookAction}};
+
+use super::PluginState;
+
+/// A WebAssembly module loaded from the plugins folder, it can only reach the app through the host functions
+pub struct Plugin
+{
+    name: String,
+    store: Store<PluginState>,
+    instance: Instance,
+    fuel_added: u64,
+}
+
+impl Plugin
+{
+    /// Instantiates the module and runs its `mokaccino_init` export if it has one
+    pub fn load(name: &str, wasm: &[u8]) -> Result<Self,String>
+    {
+        let mut engine_config = wasmi::Config::default();
+        engine_config.consume_fuel(true);
+        let engine = Engine::new(&engine_config);
+        let module = Module::new(&engine, wasm).map_err(|e| e.to_string())?;
+        let mut store = Store::new(&engine, PluginState::new());
+        store.limiter(|state| &mut state.limits);
+        let mut linker = <Linker<PluginState>>::new(&engine);
+        Self::link(&mut linker).map_err(|e| e.to_string())?;
+        let instance = linker.instantiate(&mut store, &module).map_err(|e| e.to_string())?;
+        // the start function runs on fuel as well
+        store.add_fuel(defines::PLUGIN_FUEL).map_err(|e| e.to_string())?;
+        let instance = instance.start(&mut store).map_err(|e| e.to_string())?;
+        let mut plugin = Self { name: name.to_string(), store, instance, fuel_added: defines::PLUGIN_FUEL };
+        if let Ok(init) = plugin.instance.get_typed_func::<(),()>(&plugin.store, "mokaccino_init")
+        {
+            plugin.refuel()?;
+            init.call(&mut plugin.store, ()).map_err(|e| e.to_string())?;
+        }
+        Ok(plugin)
+    }
+
+    /// The imports of the `mokaccino` module, strings are passed as pointer and length in the plugin memory
+    fn link(linker: &mut Linker<PluginState>) -> Result<(),wasmi::errors::LinkerError>
+    {
+        linker.func_wrap("mokaccino", "subscribe", |caller: Caller<'_,PluginState>, ptr: i32, len: i32| -> Result<(),Trap> {
+            let name = read_text(&caller, ptr, len)?;
+            let kind = HookEventKind::from_name(&name).ok_or_else(|| Trap::new(format!("Unknown event: {}", name)))?;
+            let mut caller = caller;
+            let subscriptions = &mut caller.data_mut().subscriptions;
+            if !subscriptions.contains(&kind)
+            {
+                subscriptions.push(kind);
+            }
+            Ok(())
+        })?;
+        linker.func_wrap("mokaccino", "register_command", |caller: Caller<'_,PluginState>, ptr: i32, len: i32| -> Result<(),Trap> {
+            let command = read_text(&caller, ptr, len)?;
+            if command.is_empty() || command.contains(char::is_whitespace)
+            {
+                return Err(Trap::new(format!("Invalid command name: {:?}", command)));
+            }
+            let mut caller = caller;
+            let commands = &mut caller.data_mut().commands;
+            if !commands.contains(&command)
+            {
+                commands.push(command);
+            }
+            Ok(())
+        })?;
+        linker.func_wrap("mokaccino", "send", |caller: Caller<'_,PluginState>, to_ptr: i32, to_len: i32, text_ptr: i32, text_len: i32| -> Result<(),Trap> {
+            let to = read_text(&caller, to_ptr, to_len)?;
+            let text = read_text(&caller, text_ptr, text_len)?;
+            let mut caller = caller;
+            push_action(caller.data_mut(), HookAction::Send(to, text))
+        })?;
+        linker.func_wrap("mokaccino", "log", |caller: Caller<'_,PluginState>, ptr: i32, len: i32| -> Result<(),Trap> {
+            let text = read_text(&caller, ptr, len)?;
+            let mut caller = caller;
+            push_action(caller.data_mut(), HookAction::Log(text))
+        })?;
+        linker.func_wrap("mokaccino", "set_panel", |caller: Caller<'_,PluginState>, title_ptr: i32, title_len: i32, text_ptr: i32, text_len: i32| -> Result<(),Trap> {
+            let title = read_text(&caller, title_ptr, title_len)?;
+            let text = read_text(&caller, text_ptr, text_len)?;
+            let mut caller = caller;
+            // an empty text removes the panel
+            if text.is_empty()
+            {
+                caller.data_mut().panels.remove(&title);
+            }
+            else
+            {
+                let panels = &mut caller.data_mut().panels;
+                if !panels.contains_key(&title) && panels.len() >= defines::PLUGIN_MAX_PANELS
+                {
+                    return Err(Trap::new(format!("More than {} panels", defines::PLUGIN_MAX_PANELS)));
+                }
+                panels.insert(title, text);
+            }
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    pub fn name(&self) -> &str
+    {
+        &self.name
+    }
+
+    pub fn state(&self) -> &PluginState
+    {
+        self.store.data()
+    }
+
+    /// Passes the event as JSON to `mokaccino_on_event` if the plugin subscribed to it
+    pub fn on_event(&mut self, event: &HookEvent) -> Result<(),String>
+    {
+        if !self.state().subscriptions.contains(&event.kind())
+        {
+            return Ok(());
+        }
+        self.call_with_text("mokaccino_on_event", &event.to_json())
+    }
+
+    /// Passes the whole command line to `mokaccino_on_command`
+    pub fn on_command(&mut self, command: &str) -> Result<(),String>
+    {
+        self.call_with_text("mokaccino_on_command", command)
+    }
+
+    pub fn take_actions(&mut self) -> Vec<HookAction>
+    {
+        std::mem::take(&mut self.store.data_mut().actions)
+    }
+
+    fn call_with_text(&mut self, export: &str, text: &str) -> Result<(),String>
+    {
+        let alloc = self.instance.get_typed_func::<i32,i32>(&self.store, "mokaccino_alloc").map_err(|e| e.to_string())?;
+        let function = self.instance.get_typed_func::<(i32,i32),()>(&self.store, export).map_err(|e| e.to_string())?;
+        let memory = self.instance.get_memory(&self.store, "memory").ok_or("The plugin doesn't export its memory".to_string())?;
+        let len = i32::try_from(text.len()).map_err(|e| e.to_string())?;
+        self.refuel()?;
+        let ptr = alloc.call(&mut self.store, len).map_err(|e| e.to_string())?;
+        memory.write(&mut self.store, ptr as u32 as usize, text.as_bytes()).map_err(|e| e.to_string())?;
+        function.call(&mut self.store, (ptr, len)).map_err(|e| e.to_string())
+    }
+
+    /// Every call gets the same amount of fuel, whatever was left by the previous ones
+    fn refuel(&mut self) -> Result<(),String>
+    {
+        let consumed = self.store.fuel_consumed().unwrap_or_default();
+        let remaining = self.fuel_added.saturating_sub(consumed);
+        let delta = defines::PLUGIN_FUEL.saturating_sub(remaining);
+        self.store.add_fuel(delta).map_err(|e| e.to_string())?;
+        self.fuel_added += delta;
+        Ok(())
+    }
+}
+
+/// The actions are carried out after the call, so each call can only leave a few of them
+fn push_action(state: &mut PluginState, action: HookAction) -> Result<(),Trap>
+{
+    if state.actions.len() >= defines::PLUGIN_MAX_ACTIONS
+    {
+        return Err(Trap::new(format!("More than {} actions in one call", defines::PLUGIN_MAX_ACTIONS)));
+    }
+    state.actions.push(action);
+    Ok(())
+}
+
+fn read_text(caller: &Caller<'_,PluginState>, ptr: i32, len: i32) -> Result<String,Trap>
+{
+    if len as u32 as usize > defines::PLUGIN_MAX_STRING_LEN
+    {
+        return Err(Trap::new(format!("String longer than {} bytes", defines::PLUGIN_MAX_STRING_LEN)));
+    }
+    let memory = caller.get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| Trap::new("The plugin doesn't export its memory"))?;
+    let start = ptr as u32 as usize;
+    let end = start.checked_add(len as u32 as usize).ok_or_else(|| Trap::new("String out of bounds"))?;
+    let bytes = memory.data(caller).get(start..end).ok_or_else(|| Trap::new("String out of bounds"))?;
+    String::from_utf8(bytes.to_vec()).map_err(|e| Trap::new(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    const ECHO: &str = r#"
+        (module
+            (import "mokaccino" "subscribe" (func $subscribe (param i32 i32)))
+            (import "mokaccino" "register_command" (func $register_command (param i32 i32)))
+            (import "mokaccino" "send" (func $send (param i32 i32 i32 i32)))
+            (import "mokaccino" "log" (func $log (param i32 i32)))
+            (import "mokaccino" "set_panel" (func $set_panel (param i32 i32 i32 i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 0) "message")
+            (data (i32.const 16) "echo")
+            (data (i32.const 32) "Alice")
+            (data (i32.const 48) "Status")
+            (data (i32.const 64) "**ready**")
+            (global $next (mut i32) (i32.const 1024))
+            (func (export "mokaccino_alloc") (param $len i32) (result i32)
+                (local $ptr i32)
+                (local.set $ptr (global.get $next))
+                (global.set $next (i32.add (global.get $next) (local.get $len)))
+                (local.get $ptr))
+            (func (export "mokaccino_init")
+                (call $subscribe (i32.const 0) (i32.const 7))
+                (call $register_command (i32.const 16) (i32.const 4))
+                (call $set_panel (i32.const 48) (i32.const 6) (i32.const 64) (i32.const 9)))
+            (func (export "mokaccino_on_event") (param i32 i32)
+                (call $send (i32.const 32) (i32.const 5) (local.get 0) (local.get 1)))
+            (func (export "mokaccino_on_command") (param i32 i32)
+                (call $log (local.get 0) (local.get 1))))
+    "#;
+
+    #[test]
+    fn run()
+    {
+        let mut plugin = Plugin::load("echo", &wat::parse_str(ECHO).unwrap()).unwrap();
+        assert_eq!(plugin.state().subscriptions, vec![HookEventKind::Message]);
+        assert_eq!(plugin.state().commands, vec!["echo".to_string()]);
+        assert_eq!(plugin.state().panels.get("Status").map(String::as_str), Some("**ready**"));
+
+        plugin.on_event(&HookEvent::Disconnected("Bob".to_string())).unwrap();
+        assert!(plugin.take_actions().is_empty());
+        let event = HookEvent::Message("Bob".to_string(), "Bob".to_string(), "hi".to_string());
+        plugin.on_event(&event).unwrap();
+        assert_eq!(plugin.take_actions(), vec![HookAction::Send("Alice".to_string(), event.to_json())]);
+        plugin.on_command("echo hello").unwrap();
+        assert_eq!(plugin.take_actions(), vec![HookAction::Log("echo hello".to_string())]);
+
+        // a plugin that never returns is stopped
+        let endless = wat::parse_str(r#"(module (func (export "mokaccino_init") (loop (br 0))))"#).unwrap();
+        assert!(Plugin::load("endless", &endless).is_err());
+        let endless_start = wat::parse_str(r#"(module (func $start (loop (br 0))) (start $start))"#).unwrap();
+        assert!(Plugin::load("endless_start", &endless_start).is_err());
+
+        // and so is one that floods the app with actions
+        let flood = wat::parse_str(r#"
+            (module
+                (import "mokaccino" "log" (func $log (param i32 i32)))
+                (memory (export "memory") 1)
+                (func (export "mokaccino_init") (loop (call $log (i32.const 0) (i32.const 1)) (br 0))))
+        "#).unwrap();
+        assert!(Plugin::load("flood", &flood).err().is_some_and(|e| e.contains("actions")));
+    }
+}
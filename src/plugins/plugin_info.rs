@@ -0,0 +1,12 @@
+/// What the UI knows about a plugin found in the plugins folder
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginInfo
+{
+    pub name: String,
+    pub enabled: bool,
+    pub commands: Vec<String>,
+    /// title and Markdown text of the panels added by the plugin
+    pub panels: Vec<(String,String)>,
+    /// why the plugin was stopped, if it was
+    pub error: Option<String>,
+}
@@ -0,0 +1,40 @@
+use super::PluginInfo;
+
+pub struct PluginList
+{
+    plugins: Vec<PluginInfo>,
+}
+
+impl PluginList
+{
+    pub fn new() -> Self
+    {
+        Self { plugins: Vec::new() }
+    }
+
+    pub fn set(&mut self, plugins: Vec<PluginInfo>)
+    {
+        self.plugins = plugins;
+    }
+
+    pub fn get_infos(&self) -> &[PluginInfo]
+    {
+        &self.plugins
+    }
+
+    /// Name of the running plugin that registered the command
+    pub fn command_owner(&self, command: &str) -> Option<&str>
+    {
+        self.plugins.iter()
+            .find(|plugin| plugin.error.is_none() && plugin.commands.iter().any(|c| c == command))
+            .map(|plugin| plugin.name.as_str())
+    }
+
+    pub fn commands(&self) -> Vec<String>
+    {
+        self.plugins.iter()
+            .filter(|plugin| plugin.error.is_none())
+            .flat_map(|plugin| plugin.commands.iter().cloned())
+            .collect()
+    }
+}
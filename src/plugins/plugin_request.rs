@@ -0,0 +1,8 @@
+#[derive(Debug, Clone)]
+pub enum PluginRequest
+{
+    /// a line of the System chat starting with a command registered by a plugin
+    Command(String),
+    /// looks for plugins again and loads the enabled ones
+    Reload,
+}
@@ -0,0 +1,34 @@
+use std::collections::BTreeMap;
+
+use wasmi::{StoreLimits, StoreLimitsBuilder};
+
+use crate::{config::defines, hooks::{HookEventKind, HookAction}};
+
+/// What a plugin asked for through the host functions
+pub struct PluginState
+{
+    pub subscriptions: Vec<HookEventKind>,
+    pub commands: Vec<String>,
+    /// Markdown text of each panel by title
+    pub panels: BTreeMap<String,String>,
+    /// actions not carried out yet
+    pub actions: Vec<HookAction>,
+    pub limits: StoreLimits,
+}
+
+impl PluginState
+{
+    pub fn new() -> Self
+    {
+        Self {
+            subscriptions: Vec::new(),
+            commands: Vec::new(),
+            panels: BTreeMap::new(),
+            actions: Vec::new(),
+            limits: StoreLimitsBuilder::new()
+                .memory_size(defines::PLUGIN_MAX_MEMORY)
+                .instances(1)
+                .build(),
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use std::{sync::{mpsc::{Receiver, Sender}, Arc, RwLock}, collections::HashMap};
+
+use crate::{config::{Config, defines}, hooks::{HookEvent, HookAction}, log::{Logger, MessageKind}, plugins::{Plugin, PluginInfo, PluginList, PluginRequest}, text::TextRequest};
+
+pub fn run(
+    running: Arc<RwLock<bool>>,
+    log: Logger,
+    requests: Receiver<PluginRequest>,
+    plugin_events: Receiver<HookEvent>,
+    text_requests: Sender<TextRequest>,
+    plugin_list: Arc<RwLock<PluginList>>,
+    config: Arc<RwLock<Config>>)
+{
+    // why each plugin was stopped, cleared when the plugins are reloaded
+    let mut errors = HashMap::<String,String>::new();
+    let (mut names, mut plugins) = load_plugins(&mut errors, &log, &text_requests, &config);
+    publish(&names, &plugins, &errors, &plugin_list, &config);
+    while *running.read().unwrap()
+    {
+        match plugin_events.recv_timeout(defines::THREAD_QUEUE_TIMEOUT)
+        {
+            Ok(event) =>
+            {
+                plugins.retain_mut(|plugin| {
+                    let result = plugin.on_event(&event);
+                    finish(plugin, result, &mut errors, &log, &text_requests)
+                });
+                publish(&names, &plugins, &errors, &plugin_list, &config);
+            },
+            Err(e) =>
+            {
+                match e
+                {
+                    std::sync::mpsc::RecvTimeoutError::Timeout => {},
+                    std::sync::mpsc::RecvTimeoutError::Disconnected =>
+                    {
+                        if !*running.read().unwrap()
+                        {return}
+                        else
+                        {panic!("Plugin events channel broken")}
+                    }
+                }
+            },
+        }
+        match requests.try_recv()
+        {
+            Ok(PluginRequest::Command(command)) =>
+            {
+                let name = command.split_whitespace().next().unwrap_or_default().to_string();
+                // the first plugin that registered the command gets it
+                if let Some(index) = plugins.iter().position(|plugin| plugin.state().commands.contains(&name))
+                {
+                    let result = plugins[index].on_command(&command);
+                    if !finish(&mut plugins[index], result, &mut errors, &log, &text_requests)
+                    {
+                        plugins.remove(index);
+                    }
+                }
+                else
+                {
+                    log.log(MessageKind::Error, &format!("Unknown command: {}", name)).unwrap();
+                }
+                publish(&names, &plugins, &errors, &plugin_list, &config);
+            },
+            Ok(PluginRequest::Reload) =>
+            {
+                errors.clear();
+                (names, plugins) = load_plugins(&mut errors, &log, &text_requests, &config);
+                publish(&names, &plugins, &errors, &plugin_list, &config);
+            },
+            Err(e) =>
+            {
+                match e
+                {
+                    std::sync::mpsc::TryRecvError::Empty => {},
+                    std::sync::mpsc::TryRecvError::Disconnected =>
+                    {
+                        if !*running.read().unwrap()
+                        {return}
+                        else
+                        {panic!("Plugin requests channel broken")}
+                    },
+                }
+            },
+        }
+    }
+}
+
+/// Names of the plugins in the plugins folder and the enabled ones, loaded
+fn load_plugins(
+    errors: &mut HashMap<String,String>,
+    log: &Logger,
+    text_requests: &Sender<TextRequest>,
+    config: &Arc<RwLock<Config>>) -> (Vec<String>, Vec<Plugin>)
+{
+    // the plugins folder is next to the config, like the other data files
+    let plugins_path = config.read().unwrap().data_path(defines::PLUGINS_PATH);
+    let mut names: Vec<String> = match std::fs::read_dir(&plugins_path)
+    {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "wasm"))
+            .filter_map(|path| path.file_stem().and_then(|stem| stem.to_str()).map(str::to_string))
+            .collect(),
+        // no folder, no plugins
+        Err(_) => Vec::new(),
+    };
+    names.sort();
+    let enabled = config.read().unwrap().plugins.clone();
+    let mut plugins = Vec::new();
+    for name in names.iter().filter(|name| enabled.contains(*name))
+    {
+        let path = std::path::Path::new(&plugins_path).join(format!("{}.wasm", name));
+        match std::fs::read(path).map_err(|e| e.to_string()).and_then(|wasm| Plugin::load(name, &wasm))
+        {
+            Ok(mut plugin) =>
+            {
+                // the actions of the initialization
+                if finish(&mut plugin, Ok(()), errors, log, text_requests)
+                {
+                    log.log(MessageKind::Event, &format!("Plugin {} loaded", name)).unwrap();
+                    plugins.push(plugin);
+                }
+            },
+            Err(e) =>
+            {
+                log.log(MessageKind::Error, &format!("Error loading plugin {}: {}", name, e)).unwrap();
+                errors.insert(name.clone(), e);
+            },
+        }
+    }
+    (names, plugins)
+}
+
+/// Carries out the actions of the plugin, returns false if the plugin failed and must be stopped
+fn finish(
+    plugin: &mut Plugin,
+    result: Result<(),String>,
+    errors: &mut HashMap<String,String>,
+    log: &Logger,
+    text_requests: &Sender<TextRequest>) -> bool
+{
+    // what a failing plugin did before the error is still carried out
+    for action in plugin.take_actions()
+    {
+        match action
+        {
+            HookAction::Send(to, text) => text_requests.send(TextRequest::Send(to, text, None)).unwrap(),
+            HookAction::Log(text) => log.log(MessageKind::Event, &format!("{}: {}", plugin.name(), text)).unwrap(),
+        }
+    }
+    match result
+    {
+        Ok(()) => true,
+        Err(e) =>
+        {
+            log.log(MessageKind::Error, &format!("Plugin {} stopped: {}", plugin.name(), e)).unwrap();
+            errors.insert(plugin.name().to_string(), e);
+            false
+        },
+    }
+}
+
+/// Shows the state of the plugins to the UI
+fn publish(
+    names: &[String],
+    plugins: &[Plugin],
+    errors: &HashMap<String,String>,
+    plugin_list: &Arc<RwLock<PluginList>>,
+    config: &Arc<RwLock<Config>>)
+{
+    let enabled = config.read().unwrap().plugins.clone();
+    let infos = names.iter().map(|name| {
+        let plugin = plugins.iter().find(|plugin| plugin.name() == name);
+        PluginInfo {
+            name: name.clone(),
+            enabled: enabled.contains(name),
+            commands: plugin.map(|plugin| plugin.state().commands.clone()).unwrap_or_default(),
+            panels: plugin.map(|plugin| plugin.state().panels.iter().map(|(title, text)| (title.clone(), text.clone())).collect()).unwrap_or_default(),
+            error: errors.get(name).cloned(),
+        }
+    }).collect();
+    plugin_list.write().unwrap().set(infos);
+}
@@ -0,0 +1,157 @@
+use std::time::SystemTime;
+
+use chrono::{DateTime, Local, SecondsFormat};
+
+use super::{ExportEntry, ExportFormat};
+
+/// A conversation, or the System log, ready to be written to a file
+pub struct ChatExport
+{
+    pub title: String,
+    pub entries: Vec<ExportEntry>,
+}
+
+impl ChatExport
+{
+    pub fn new(title: &str, entries: Vec<ExportEntry>) -> Self
+    {
+        Self { title: title.to_string(), entries }
+    }
+
+    pub fn save(&self, path: &str, format: ExportFormat) -> Result<(),String>
+    {
+        std::fs::write(path, self.render(format)).map_err(|e| e.to_string())
+    }
+
+    pub fn render(&self, format: ExportFormat) -> String
+    {
+        match format
+        {
+            ExportFormat::Json => self.render_json(),
+            ExportFormat::Html => self.render_html(),
+            ExportFormat::Text => self.render_text(),
+        }
+    }
+
+    fn render_json(&self) -> String
+    {
+        let entries: Vec<String> = self.entries.iter().map(|entry| format!(
+            "    {{\"sender\": {}, \"direction\": \"{}\", \"timestamp\": \"{}\", \"text\": {}}}",
+            json_string(&entry.sender),
+            entry.direction_name(),
+            timestamp(entry.timestamp),
+            json_string(&entry.text)))
+            .collect();
+        format!("{{\n  \"title\": {},\n  \"messages\": [\n{}\n  ]\n}}\n", json_string(&self.title), entries.join(",\n"))
+    }
+
+    fn render_html(&self) -> String
+    {
+        let entries: String = self.entries.iter().map(|entry| format!(
+            "<div class=\"{}\"><span class=\"time\">{}</span> <span class=\"sender\">{}</span><pre>{}</pre></div>\n",
+            entry.direction_name(),
+            timestamp(entry.timestamp),
+            html_escape(&entry.sender),
+            html_escape(&entry.text)))
+            .collect();
+        format!(concat!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n",
+            "<style>\n",
+            "body {{ font-family: sans-serif; margin: 2em; }}\n",
+            "div {{ margin: 0.5em 0; padding: 0.4em 0.6em; border-radius: 5px; background: #eee; }}\n",
+            "div.outgoing {{ background: #e6dcf5; }}\n",
+            ".time {{ color: #777; font-size: 0.8em; }}\n",
+            ".sender {{ font-weight: bold; }}\n",
+            "pre {{ margin: 0.2em 0 0 0; white-space: pre-wrap; font-family: inherit; }}\n",
+            "</style>\n</head>\n<body>\n<h1>{}</h1>\n{}</body>\n</html>\n"),
+            html_escape(&self.title), html_escape(&self.title), entries)
+    }
+
+    fn render_text(&self) -> String
+    {
+        let mut text = format!("{}\n\n", self.title);
+        for entry in &self.entries
+        {
+            let arrow = match entry.direction_name()
+            {
+                "incoming" => "<-",
+                "outgoing" => "->",
+                _ => "--",
+            };
+            // continuation lines are indented so every entry starts with its header
+            text += &format!("[{}] {} {}: {}\n", timestamp(entry.timestamp), arrow, entry.sender, entry.text.replace('\n', "\n    "));
+        }
+        text
+    }
+}
+
+fn timestamp(time: SystemTime) -> String
+{
+    DateTime::<Local>::from(time).to_rfc3339_opts(SecondsFormat::Secs, false)
+}
+
+/// Quoted and escaped JSON string
+pub fn json_string(text: &str) -> String
+{
+    let mut escaped = String::from("\"");
+    for c in text.chars()
+    {
+        match c
+        {
+            '"' => escaped += "\\\"",
+            '\\' => escaped += "\\\\",
+            '\n' => escaped += "\\n",
+            '\r' => escaped += "\\r",
+            '\t' => escaped += "\\t",
+            c if (c as u32) < 0x20 => escaped += &format!("\\u{:04x}", c as u32),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn html_escape(text: &str) -> String
+{
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::text::TextDirection;
+
+    use super::*;
+
+    #[test]
+    fn render()
+    {
+        let export = ChatExport::new("Alice", vec![
+            ExportEntry {
+                sender: "Alice".to_string(),
+                direction: Some(TextDirection::Incoming),
+                timestamp: SystemTime::UNIX_EPOCH,
+                text: "say \"hi\"\n<b>".to_string(),
+            },
+            ExportEntry {
+                sender: "Bob".to_string(),
+                direction: Some(TextDirection::Outgoing),
+                timestamp: SystemTime::UNIX_EPOCH,
+                text: "hi".to_string(),
+            },
+        ]);
+        let json = export.render(ExportFormat::Json);
+        assert!(json.contains("\"text\": \"say \\\"hi\\\"\\n<b>\""));
+        assert!(json.contains("\"direction\": \"outgoing\""));
+        let html = export.render(ExportFormat::Html);
+        assert!(html.contains("<pre>say &quot;hi&quot;\n&lt;b&gt;</pre>"));
+        assert!(!html.contains("<b>"));
+        let text = export.render(ExportFormat::Text);
+        assert!(text.contains("<- Alice: say \"hi\"\n    <b>\n"));
+        assert!(text.contains("-> Bob: hi\n"));
+    }
+}
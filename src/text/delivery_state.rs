@@ -0,0 +1,51 @@
+use serializable::Serializable;
+
+#[derive(Serializable, Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum DeliveryState
+{
+    /// waiting to be sent
+    Pending,
+    /// the recipient is offline, it will be sent when it connects
+    Queued,
+    /// sent, waiting for the ack
+    Sent,
+    Delivered,
+    Read,
+    /// it could not be sent or queued, it can be resent
+    Failed,
+}
+
+impl DeliveryState
+{
+    /// The peer has it, so it belongs in the history
+    pub fn is_delivered(&self) -> bool
+    {
+        matches!(self, DeliveryState::Delivered | DeliveryState::Read)
+    }
+
+    pub fn symbol(&self) -> &'static str
+    {
+        match self
+        {
+            DeliveryState::Pending => "🕓",
+            DeliveryState::Queued => "📤",
+            DeliveryState::Sent => "✔",
+            DeliveryState::Delivered => "✔✔",
+            DeliveryState::Read => "👁",
+            DeliveryState::Failed => "⚠",
+        }
+    }
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            DeliveryState::Pending => "Pending",
+            DeliveryState::Queued => "Queued",
+            DeliveryState::Sent => "Sent",
+            DeliveryState::Delivered => "Delivered",
+            DeliveryState::Read => "Read",
+            DeliveryState::Failed => "Failed",
+        }
+    }
+}
\ No newline at end of file
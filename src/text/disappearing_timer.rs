@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use serde::{Serialize, Deserialize};
+use serializable::Serializable;
+
+/// How long the messages of a conversation are kept, agreed by both sides
+#[derive(Serializable, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct DisappearingTimer
+{
+    pub seconds: u64,
+}
+
+impl DisappearingTimer
+{
+    /// The timers offered in the UI
+    pub const OPTIONS: [DisappearingTimer; 4] = [
+        DisappearingTimer::from_secs(5 * 60),
+        DisappearingTimer::from_secs(60 * 60),
+        DisappearingTimer::from_secs(24 * 60 * 60),
+        DisappearingTimer::from_secs(7 * 24 * 60 * 60),
+    ];
+
+    pub const fn from_secs(seconds: u64) -> Self
+    {
+        Self { seconds }
+    }
+
+    pub fn max_age(&self) -> Duration
+    {
+        Duration::from_secs(self.seconds)
+    }
+
+    pub fn name(&self) -> String
+    {
+        let units = [(7 * 24 * 60 * 60, "week"), (24 * 60 * 60, "day"), (60 * 60, "hour"), (60, "minute"), (1, "second")];
+        let (size, unit) = units.into_iter()
+            .find(|(size, _)| self.seconds >= *size && self.seconds % size == 0)
+            .unwrap_or((1, "second"));
+        let count = self.seconds / size;
+        if count == 1 {format!("1 {}", unit)} else {format!("{} {}s", count, unit)}
+    }
+}
@@ -0,0 +1,63 @@
+use std::time::SystemTime;
+
+use crate::log::{LogMessage, MessageKind};
+
+use super::{TextInfo, TextDirection};
+
+/// A message as it is written in an export
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExportEntry
+{
+    pub sender: String,
+    /// None for the messages of the System log
+    pub direction: Option<TextDirection>,
+    pub timestamp: SystemTime,
+    pub text: String,
+}
+
+impl ExportEntry
+{
+    /// `contact` is the sender of incoming messages without an author, `local_name` of outgoing ones
+    pub fn from_text(text: &TextInfo, contact: &str, local_name: &str) -> Self
+    {
+        let sender = match (&text.direction, &text.author)
+        {
+            (TextDirection::Incoming, Some(author)) => author.as_str(),
+            (TextDirection::Incoming, None) => contact,
+            (TextDirection::Outgoing, _) => local_name,
+        };
+        Self {
+            sender: sender.to_string(),
+            direction: Some(text.direction.clone()),
+            timestamp: text.sent,
+            text: text.text.clone(),
+        }
+    }
+
+    pub fn from_log(message: &LogMessage) -> Self
+    {
+        let text = match message.kind
+        {
+            MessageKind::Event => message.text.clone(),
+            MessageKind::Command => format!("Command: {}", message.text),
+            MessageKind::Error => format!("Error: {}", message.text),
+            MessageKind::Security => format!("Security: {}", message.text),
+        };
+        Self {
+            sender: message.src.clone(),
+            direction: None,
+            timestamp: message.time,
+            text,
+        }
+    }
+
+    pub fn direction_name(&self) -> &'static str
+    {
+        match self.direction
+        {
+            Some(TextDirection::Incoming) => "incoming",
+            Some(TextDirection::Outgoing) => "outgoing",
+            None => "system",
+        }
+    }
+}
@@ -0,0 +1,47 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat
+{
+    Json,
+    /// a single page with the style inlined
+    Html,
+    Text,
+}
+
+impl ExportFormat
+{
+    pub const ALL: [ExportFormat; 3] = [ExportFormat::Json, ExportFormat::Html, ExportFormat::Text];
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            ExportFormat::Json => "JSON",
+            ExportFormat::Html => "HTML",
+            ExportFormat::Text => "Plain text",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str
+    {
+        match self
+        {
+            ExportFormat::Json => "json",
+            ExportFormat::Html => "html",
+            ExportFormat::Text => "txt",
+        }
+    }
+
+    /// Guesses the format from the extension of the file, plain text if it's unknown
+    pub fn from_path(path: &str) -> Self
+    {
+        let extension = std::path::Path::new(path).extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str()
+        {
+            "json" => ExportFormat::Json,
+            "html" | "htm" => ExportFormat::Html,
+            _ => ExportFormat::Text,
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use std::error::Error;
+
+use serde::{Serialize, Deserialize};
+use serializable::Serializable;
+
+use crate::{crypto::{SymmetricKey, Ciphertext, PublicKey}, network::Content};
+
+use super::{GroupInfo, GroupMember};
+
+/// A group we are a member of.
+/// Messages are sent by their author to every member, so the pairwise sessions authenticate the author
+/// and the group key makes sure only the current members can read them
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Group
+{
+    pub info: GroupInfo,
+    /// replaced whenever the members change
+    pub key: SymmetricKey,
+    /// members removed by the owner that were not told yet
+    #[serde(default)]
+    pub removed: Vec<GroupMember>,
+    /// we left the group, its lists are ignored from now on
+    #[serde(default)]
+    pub left: bool,
+}
+
+impl Group
+{
+    pub fn new(name: &str, owner: GroupMember) -> Self
+    {
+        Self { info: GroupInfo::new(name, owner), key: SymmetricKey::random(), removed: Vec::new(), left: false }
+    }
+
+    pub fn is_owned_by(&self, public_key: &PublicKey) -> bool
+    {
+        &self.info.owner == public_key
+    }
+
+    /// Only for the owner, the owner is always kept and the key is changed
+    pub fn set_members(&mut self, members: Vec<GroupMember>)
+    {
+        let owner = self.info.member(&self.info.owner).cloned();
+        let members: Vec<GroupMember> = owner.into_iter()
+            .chain(members.into_iter().filter(|member| member.public_key != self.info.owner))
+            .collect();
+        for member in &self.info.members
+        {
+            if !members.iter().any(|other| other.public_key == member.public_key)
+            {
+                self.removed.push(member.clone());
+            }
+        }
+        self.removed.retain(|removed| !members.iter().any(|member| member.public_key == removed.public_key));
+        self.info.members = members;
+        self.info.version += 1;
+        self.key = SymmetricKey::random();
+    }
+
+    pub fn seal(&self, content: &Content) -> Ciphertext
+    {
+        self.key.encrypt_with_associated_data(&content.serialize(), self.info.id.as_bytes())
+    }
+
+    pub fn open(&self, ciphertext: &Ciphertext) -> Result<Content,Box<dyn Error>>
+    {
+        let plaintext = self.key.decrypt_with_associated_data(ciphertext, self.info.id.as_bytes())?;
+        let (content, len) = Content::deserialize(&plaintext)?;
+        if len != plaintext.len()
+        {
+            Err("Group message length mismatch".into())
+        }
+        else
+        {
+            Ok(content)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::crypto::PrivateKey;
+
+    use super::*;
+
+    #[test]
+    fn membership_change()
+    {
+        let owner = GroupMember::new("Owner", &PrivateKey::new().public_key());
+        let member = GroupMember::new("Member", &PrivateKey::new().public_key());
+        let mut group = Group::new("Group", owner.clone());
+        group.set_members(vec![member.clone()]);
+        assert_eq!(group.info.members, vec![owner.clone(), member.clone()]);
+        let message = Content::Text("Hello".to_string(), 1, 1);
+        let ciphertext = group.seal(&message);
+        assert_eq!(group.open(&ciphertext).unwrap(), message);
+        let old = group.clone();
+        group.set_members(vec![]);
+        assert_eq!(group.info.members, vec![owner]);
+        assert_eq!(group.removed, vec![member]);
+        assert_eq!(group.info.version, 2);
+        // the removed member can't read the new messages
+        assert!(old.open(&group.seal(&message)).is_err());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,41 @@
+use serde::{Serialize, Deserialize};
+use serializable::Serializable;
+
+use crate::crypto::PublicKey;
+
+use super::GroupMember;
+
+/// Membership list of a group, only the owner can change it
+#[derive(Serializable, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GroupInfo
+{
+    /// random and stable, the name can be shared by many groups
+    pub id: String,
+    pub name: String,
+    pub owner: PublicKey,
+    /// increased at every change, older lists are ignored
+    pub version: u64,
+    pub members: Vec<GroupMember>,
+}
+
+impl GroupInfo
+{
+    pub fn new(name: &str, owner: GroupMember) -> Self
+    {
+        let mut id = [0; 16];
+        openssl::rand::rand_bytes(&mut id).unwrap();
+        Self
+        {
+            id: id.iter().map(|b| format!("{:02x}", b)).collect(),
+            name: name.to_string(),
+            owner: owner.public_key.clone(),
+            version: 0,
+            members: vec![owner],
+        }
+    }
+
+    pub fn member(&self, public_key: &PublicKey) -> Option<&GroupMember>
+    {
+        self.members.iter().find(|member| &member.public_key == public_key)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,19 @@
+use serde::{Serialize, Deserialize};
+use serializable::Serializable;
+
+use crate::crypto::PublicKey;
+
+#[derive(Serializable, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GroupMember
+{
+    pub name: String,
+    pub public_key: PublicKey,
+}
+
+impl GroupMember
+{
+    pub fn new(name: &str, public_key: &PublicKey) -> Self
+    {
+        Self { name: name.to_string(), public_key: public_key.clone() }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,257 @@
+use std::{path::PathBuf, io::Write, time::{SystemTime, Duration}};
+
+use serializable::Serializable;
+
+use crate::{crypto::{SymmetricKey, PublicKey, Ciphertext}, config::{Config, HistoryRetention, defines}};
+
+use super::HistoryEntry;
+
+/// Chat history on disk, one file for each contact identity and for each group with one encrypted entry per line.
+/// The file of a conversation is named by `History::identity` or `History::group_identity`
+pub struct History
+{
+    path: PathBuf,
+    key: SymmetricKey,
+}
+
+impl History
+{
+    pub fn new(path: &str, key: SymmetricKey) -> Self
+    {
+        Self { path: PathBuf::from(path), key }
+    }
+
+    pub fn from_config(config: &Config) -> Self
+    {
+        Self::new(&config.data_path(defines::HISTORY_PATH), config.text.history_key)
+    }
+
+    pub fn append(&self, identity: &str, entry: &HistoryEntry) -> Result<(),String>
+    {
+        std::fs::create_dir_all(&self.path).map_err(|e| e.to_string())?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.file(identity))
+            .map_err(|e| e.to_string())?;
+        writeln!(file, "{}", self.seal(identity, entry)).map_err(|e| e.to_string())
+    }
+
+    /// Reads the history of a contact and counts the lines that cannot be read, the expired entries are removed from the file
+    pub fn load(&self, identity: &str, retention: HistoryRetention) -> Result<(Vec<HistoryEntry>, usize),String>
+    {
+        let (mut entries, unreadable) = self.entries(identity)?;
+        let count = entries.len();
+        if let Some(max_age) = retention.max_age()
+        {
+            let now = SystemTime::now();
+            entries.retain(|entry| now.duration_since(entry.saved).map(|age| age <= max_age).unwrap_or(true));
+        }
+        if retention == HistoryRetention::Off
+        {
+            entries.clear();
+        }
+        if entries.len() != count
+        {
+            self.rewrite(identity, &entries, &unreadable)?;
+        }
+        Ok((entries, unreadable.len()))
+    }
+
+    /// Saves the entry removing the older copies of the message, so an edited or deleted text doesn't stay on disk
+    pub fn replace(&self, identity: &str, entry: &HistoryEntry) -> Result<(),String>
+    {
+        let (mut entries, unreadable) = self.entries(identity)?;
+        entries.retain(|old| old.info.nonce != entry.info.nonce || old.info.direction != entry.info.direction);
+        entries.push(entry.clone());
+        std::fs::create_dir_all(&self.path).map_err(|e| e.to_string())?;
+        self.rewrite(identity, &entries, &unreadable)
+    }
+
+    /// Removes the messages written or received more than `max_age` ago, for the contacts with disappearing messages
+    pub fn expire(&self, identity: &str, max_age: Duration) -> Result<(),String>
+    {
+        let (mut entries, unreadable) = self.entries(identity)?;
+        let count = entries.len();
+        let now = SystemTime::now();
+        entries.retain(|entry| now.duration_since(entry.info.local_time()).map(|age| age <= max_age).unwrap_or(true));
+        if entries.len() != count
+        {
+            self.rewrite(identity, &entries, &unreadable)?;
+        }
+        Ok(())
+    }
+
+    /// Moves the history to the new identity key of a contact, the lines that cannot be read stay where they are
+    pub fn rename(&self, identity: &str, new_identity: &str) -> Result<(),String>
+    {
+        let (entries, unreadable) = self.entries(identity)?;
+        if entries.is_empty()
+        {
+            return Ok(());
+        }
+        let (mut new_entries, new_unreadable) = self.entries(new_identity)?;
+        new_entries.extend(entries);
+        std::fs::create_dir_all(&self.path).map_err(|e| e.to_string())?;
+        self.rewrite(new_identity, &new_entries, &new_unreadable)?;
+        self.rewrite(identity, &[], &unreadable)
+    }
+
+    pub fn clear(&self, identity: &str) -> Result<(),String>
+    {
+        let path = self.file(identity);
+        if path.exists()
+        {
+            std::fs::remove_file(path).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// The entries that can be read and the lines that cannot, which are kept as they are when the file is rewritten
+    fn entries(&self, identity: &str) -> Result<(Vec<HistoryEntry>, Vec<String>),String>
+    {
+        let path = self.file(identity);
+        if !path.exists()
+        {
+            return Ok((Vec::new(), Vec::new()));
+        }
+        let file = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        let mut entries = Vec::new();
+        let mut unreadable = Vec::new();
+        for line in file.lines().filter(|line| !line.is_empty())
+        {
+            match self.open(identity, line)
+            {
+                Ok(entry) => entries.push(entry),
+                Err(_) => unreadable.push(line.to_string()),
+            }
+        }
+        Ok((entries, unreadable))
+    }
+
+    fn rewrite(&self, identity: &str, entries: &[HistoryEntry], unreadable: &[String]) -> Result<(),String>
+    {
+        if entries.is_empty() && unreadable.is_empty()
+        {
+            return self.clear(identity);
+        }
+        let data: String = unreadable.iter().cloned()
+            .chain(entries.iter().map(|entry| self.seal(identity, entry)))
+            .map(|line| format!("{}\n", line))
+            .collect();
+        std::fs::write(self.file(identity), data).map_err(|e| e.to_string())
+    }
+
+    fn file(&self, identity: &str) -> PathBuf
+    {
+        self.path.join(format!("{}.log", identity))
+    }
+
+    /// Name of the history of a contact
+    pub fn identity(public_key: &PublicKey) -> String
+    {
+        public_key.fingerprint().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Name of the history of a group, the id comes from the peers so it is hashed rather than used as a file name
+    pub fn group_identity(id: &str) -> String
+    {
+        let digest = openssl::hash::hash(defines::MESSAGE_DIGEST(), id.as_bytes()).unwrap();
+        format!("group_{}", digest.iter().map(|b| format!("{:02x}", b)).collect::<String>())
+    }
+
+    /// The entry is bound to the file it belongs to, so it can't be moved to another contact
+    fn seal(&self, identity: &str, entry: &HistoryEntry) -> String
+    {
+        let ciphertext = self.key.encrypt_with_associated_data(&entry.serialize(), identity.as_bytes());
+        openssl::base64::encode_block(&ciphertext.serialize())
+    }
+
+    fn open(&self, identity: &str, line: &str) -> Result<HistoryEntry,String>
+    {
+        let data = openssl::base64::decode_block(line).map_err(|e| e.to_string())?;
+        let (ciphertext, _len) = Ciphertext::deserialize(&data).map_err(|e| e.to_string())?;
+        let plaintext = self.key.decrypt_with_associated_data(&ciphertext, identity.as_bytes())
+            .map_err(|_| "Cannot decrypt the history, the key may have changed".to_string())?;
+        let (entry, len) = HistoryEntry::deserialize(&plaintext).map_err(|e| e.to_string())?;
+        if len != plaintext.len()
+        {
+            Err("History entry length mismatch".to_string())
+        }
+        else
+        {
+            Ok(entry)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::time::Duration;
+
+    use crate::{crypto::PrivateKey, text::{TextInfo, TextDirection, DeliveryState}};
+
+    use super::*;
+
+    #[test]
+    fn history_round_trip()
+    {
+        let path = std::env::temp_dir().join(format!("mokaccino_history_{:x}", rand::random::<u64>()));
+        let history = History::new(path.to_str().unwrap(), SymmetricKey::random());
+        let contact = &History::identity(&PrivateKey::new().public_key());
+        let message = |text: &str| TextInfo 
+        { 
+            text: text.to_string(), 
+            nonce: 0, 
+            direction: TextDirection::Incoming, 
+            author: None,
+            lamport: 0, 
+            sent: SystemTime::UNIX_EPOCH, 
+            received: SystemTime::UNIX_EPOCH,
+            state: DeliveryState::Delivered,
+            reply_to: None,
+            edited: false,
+            deleted: false,
+        };
+        let old = HistoryEntry { info: message("old"), saved: SystemTime::now() - Duration::from_secs(3 * 24 * 60 * 60) };
+        let new = HistoryEntry::now(message("new"));
+        history.append(contact, &old).unwrap();
+        history.append(contact, &new).unwrap();
+        assert_eq!(history.load(contact, HistoryRetention::Forever).unwrap(), (vec![old, new.clone()], 0));
+        assert_eq!(history.load(contact, HistoryRetention::Days(1)).unwrap(), (vec![new.clone()], 0));
+        // the expired entry was removed from the file
+        assert_eq!(history.load(contact, HistoryRetention::Forever).unwrap(), (vec![new.clone()], 0));
+        assert!(history.load(&History::identity(&PrivateKey::new().public_key()), HistoryRetention::Forever).unwrap().0.is_empty());
+        let other_key = History::new(path.to_str().unwrap(), SymmetricKey::random());
+        assert_eq!(other_key.load(contact, HistoryRetention::Forever).unwrap(), (Vec::new(), 1));
+        // a corrupt line is skipped and kept, the rest of the history is still read
+        let file = path.join(format!("{}.log", contact));
+        let mut data = std::fs::read_to_string(&file).unwrap();
+        data.push_str("corrupt\n");
+        std::fs::write(&file, data).unwrap();
+        history.replace(contact, &new).unwrap();
+        assert_eq!(history.load(contact, HistoryRetention::Forever).unwrap(), (vec![new.clone()], 1));
+        std::fs::write(&file, "").unwrap();
+        // disappearing messages go by the time they were received, a sender with a clock ahead can't keep them
+        let recent = HistoryEntry::now(TextInfo { received: SystemTime::now(), ..message("recent") });
+        let future = SystemTime::now() + Duration::from_secs(365 * 24 * 60 * 60);
+        history.append(contact, &HistoryEntry::now(TextInfo { sent: future, ..message("expired") })).unwrap();
+        history.append(contact, &recent).unwrap();
+        history.expire(contact, Duration::from_secs(60 * 60)).unwrap();
+        assert_eq!(history.load(contact, HistoryRetention::Forever).unwrap(), (vec![recent.clone()], 0));
+        // the history follows a key rotation
+        let rotated = &History::identity(&PrivateKey::new().public_key());
+        history.rename(contact, rotated).unwrap();
+        assert!(history.load(contact, HistoryRetention::Forever).unwrap().0.is_empty());
+        assert_eq!(history.load(rotated, HistoryRetention::Forever).unwrap(), (vec![recent], 0));
+        // groups have their own files
+        let group = &History::group_identity("../group");
+        assert!(!group.contains('/'));
+        history.append(group, &new).unwrap();
+        assert_eq!(history.load(group, HistoryRetention::Forever).unwrap(), (vec![new], 0));
+        history.clear(rotated).unwrap();
+        assert!(history.load(rotated, HistoryRetention::Forever).unwrap().0.is_empty());
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
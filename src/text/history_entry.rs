@@ -0,0 +1,20 @@
+use std::time::SystemTime;
+
+use serializable::Serializable;
+
+use super::TextInfo;
+
+#[derive(Serializable, Clone, Debug, PartialEq)]
+pub struct HistoryEntry
+{
+    pub info: TextInfo,
+    pub saved: SystemTime,
+}
+
+impl HistoryEntry
+{
+    pub fn now(info: TextInfo) -> Self
+    {
+        Self { info, saved: SystemTime::now() }
+    }
+}
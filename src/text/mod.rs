@@ -1,12 +1,40 @@
 pub mod threads;
 pub mod text_info;
 pub mod text_direction;
+pub mod delivery_state;
 pub mod text_request;
 pub mod text_fast_storage;
+pub mod reactions;
+pub mod search_index;
+pub mod search_result;
+pub mod disappearing_timer;
 pub mod text_list;
+pub mod history_entry;
+pub mod history;
+pub mod outbox;
+pub mod group_member;
+pub mod group_info;
+pub mod group;
+pub mod export_format;
+pub mod export_entry;
+pub mod chat_export;
 
 pub use text_info::TextInfo;
 pub use text_direction::TextDirection;
+pub use delivery_state::DeliveryState;
 pub use text_request::TextRequest;
 pub use text_fast_storage::TextFastStorage;
-pub use text_list::TextList;
\ No newline at end of file
+pub use reactions::Reactions;
+pub use search_index::SearchIndex;
+pub use search_result::SearchResult;
+pub use disappearing_timer::DisappearingTimer;
+pub use text_list::TextList;
+pub use history_entry::HistoryEntry;
+pub use history::History;
+pub use outbox::Outbox;
+pub use group_member::GroupMember;
+pub use group_info::GroupInfo;
+pub use group::Group;
+pub use export_format::ExportFormat;
+pub use export_entry::ExportEntry;
+pub use chat_export::ChatExport;
\ No newline at end of file
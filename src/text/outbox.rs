@@ -0,0 +1,110 @@
+use std::path::PathBuf;
+
+use serializable::Serializable;
+
+use crate::{crypto::{SymmetricKey, PublicKey, Ciphertext}, config::{Config, defines}};
+
+use super::{TextInfo, History};
+
+/// Messages waiting for their recipient to come online, one encrypted file for each contact identity
+pub struct Outbox
+{
+    path: PathBuf,
+    key: SymmetricKey,
+}
+
+impl Outbox
+{
+    pub fn new(path: &str, key: SymmetricKey) -> Self
+    {
+        Self { path: PathBuf::from(path), key }
+    }
+
+    pub fn from_config(config: &Config) -> Self
+    {
+        Self::new(&config.data_path(defines::OUTBOX_PATH), config.text.history_key)
+    }
+
+    pub fn load(&self, public_key: &PublicKey) -> Result<Vec<TextInfo>,String>
+    {
+        let path = self.file(public_key);
+        if !path.exists()
+        {
+            return Ok(Vec::new());
+        }
+        let file = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        let data = openssl::base64::decode_block(file.trim()).map_err(|e| e.to_string())?;
+        let (ciphertext, _len) = Ciphertext::deserialize(&data).map_err(|e| e.to_string())?;
+        let plaintext = self.key.decrypt_with_associated_data(&ciphertext, History::identity(public_key).as_bytes())
+            .map_err(|_| "Cannot decrypt the outbox, the key may have changed".to_string())?;
+        let (texts, len) = Vec::<TextInfo>::deserialize(&plaintext).map_err(|e| e.to_string())?;
+        if len != plaintext.len()
+        {
+            Err("Outbox length mismatch".to_string())
+        }
+        else
+        {
+            Ok(texts)
+        }
+    }
+
+    /// Replaces the queue of a contact, an empty queue removes the file
+    pub fn store(&self, public_key: &PublicKey, texts: &Vec<TextInfo>) -> Result<(),String>
+    {
+        let path = self.file(public_key);
+        if texts.is_empty()
+        {
+            if path.exists()
+            {
+                std::fs::remove_file(path).map_err(|e| e.to_string())?;
+            }
+            return Ok(());
+        }
+        std::fs::create_dir_all(&self.path).map_err(|e| e.to_string())?;
+        let ciphertext = self.key.encrypt_with_associated_data(&texts.serialize(), History::identity(public_key).as_bytes());
+        std::fs::write(path, openssl::base64::encode_block(&ciphertext.serialize())).map_err(|e| e.to_string())
+    }
+
+    fn file(&self, public_key: &PublicKey) -> PathBuf
+    {
+        self.path.join(format!("{}.queue", History::identity(public_key)))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::time::SystemTime;
+
+    use crate::{crypto::PrivateKey, text::{TextDirection, DeliveryState}};
+
+    use super::*;
+
+    #[test]
+    fn outbox_round_trip()
+    {
+        let path = std::env::temp_dir().join(format!("mokaccino_outbox_{:x}", rand::random::<u64>()));
+        let outbox = Outbox::new(path.to_str().unwrap(), SymmetricKey::random());
+        let contact = PrivateKey::new().public_key();
+        let message = TextInfo
+        {
+            text: "queued".to_string(),
+            nonce: 1,
+            direction: TextDirection::Outgoing,
+            author: None,
+            lamport: 1,
+            sent: SystemTime::UNIX_EPOCH,
+            received: SystemTime::UNIX_EPOCH,
+            state: DeliveryState::Queued,
+            reply_to: None,
+            edited: false,
+            deleted: false,
+        };
+        outbox.store(&contact, &vec![message.clone()]).unwrap();
+        assert_eq!(outbox.load(&contact).unwrap(), vec![message]);
+        assert!(outbox.load(&PrivateKey::new().public_key()).unwrap().is_empty());
+        outbox.store(&contact, &Vec::new()).unwrap();
+        assert!(outbox.load(&contact).unwrap().is_empty());
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
\ No newline at end of file
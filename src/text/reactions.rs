@@ -0,0 +1,60 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Who reacted to a message with each emoji, None is us
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Reactions
+{
+    emojis: BTreeMap<String,BTreeSet<Option<String>>>,
+}
+
+impl Reactions
+{
+    pub fn set(&mut self, who: Option<&str>, emoji: &str, added: bool)
+    {
+        let who = who.map(str::to_string);
+        if added
+        {
+            self.emojis.entry(emoji.to_string()).or_default().insert(who);
+        }
+        else if let Some(people) = self.emojis.get_mut(emoji)
+        {
+            people.remove(&who);
+            if people.is_empty()
+            {
+                self.emojis.remove(emoji);
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String,&BTreeSet<Option<String>>)>
+    {
+        self.emojis.iter()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.emojis.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn aggregate()
+    {
+        let mut reactions = Reactions::default();
+        reactions.set(None, "👍", true);
+        reactions.set(Some("Peer"), "👍", true);
+        reactions.set(Some("Peer"), "👍", true);
+        reactions.set(Some("Peer"), "❤", true);
+        let counts: Vec<(String,usize)> = reactions.iter().map(|(emoji, people)| (emoji.clone(), people.len())).collect();
+        assert_eq!(counts, vec![("❤".to_string(), 1), ("👍".to_string(), 2)]);
+        reactions.set(Some("Peer"), "❤", false);
+        reactions.set(None, "👍", false);
+        reactions.set(Some("Peer"), "👍", false);
+        assert!(reactions.is_empty());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,136 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use super::{TextInfo, TextDirection};
+
+/// Conversation, nonce and direction of a message
+pub type MessageKey = (String,u64,TextDirection);
+
+/// Inverted index of the words of every message in the text list
+#[derive(Default)]
+pub struct SearchIndex
+{
+    words: BTreeMap<String,HashSet<MessageKey>>,
+    /// words of each message, to remove them when it changes
+    messages: HashMap<MessageKey,Vec<String>>,
+}
+
+impl SearchIndex
+{
+    /// Lowercase words of the text without punctuation
+    pub fn tokenize(text: &str) -> Vec<String>
+    {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    }
+
+    /// Adds the message, replacing the words it had before
+    pub fn insert(&mut self, from: &str, text: &TextInfo)
+    {
+        let key = (from.to_string(), text.nonce, text.direction.clone());
+        self.remove(&key);
+        if text.deleted
+        {
+            return;
+        }
+        let mut words = Self::tokenize(&text.text);
+        words.sort();
+        words.dedup();
+        for word in &words
+        {
+            self.words.entry(word.clone()).or_default().insert(key.clone());
+        }
+        self.messages.insert(key, words);
+    }
+
+    pub fn remove(&mut self, key: &MessageKey)
+    {
+        for word in self.messages.remove(key).unwrap_or_default()
+        {
+            if let Some(keys) = self.words.get_mut(&word)
+            {
+                keys.remove(key);
+                if keys.is_empty()
+                {
+                    self.words.remove(&word);
+                }
+            }
+        }
+    }
+
+    pub fn remove_conversation(&mut self, from: &str)
+    {
+        let keys: Vec<MessageKey> = self.messages.keys().filter(|(conversation, _, _)| conversation == from).cloned().collect();
+        for key in keys
+        {
+            self.remove(&key);
+        }
+    }
+
+    /// Messages with a word starting with each word of the query
+    pub fn search(&self, query: &str) -> HashSet<MessageKey>
+    {
+        let mut found: Option<HashSet<MessageKey>> = None;
+        for word in Self::tokenize(query)
+        {
+            let matching: HashSet<MessageKey> = self.words.range(word.clone()..)
+                .take_while(|(indexed, _)| indexed.starts_with(&word))
+                .flat_map(|(_, keys)| keys.iter().cloned())
+                .collect();
+            found = Some(match found
+            {
+                Some(found) => found.intersection(&matching).cloned().collect(),
+                None => matching,
+            });
+        }
+        found.unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::time::SystemTime;
+
+    use crate::text::DeliveryState;
+
+    use super::*;
+
+    fn text(nonce: u64, text: &str) -> TextInfo
+    {
+        TextInfo {
+            text: text.to_string(),
+            nonce,
+            direction: TextDirection::Incoming,
+            author: None,
+            lamport: nonce,
+            sent: SystemTime::now(),
+            received: SystemTime::now(),
+            state: DeliveryState::Delivered,
+            reply_to: None,
+            edited: false,
+            deleted: false,
+        }
+    }
+
+    #[test]
+    fn search()
+    {
+        let mut index = SearchIndex::default();
+        index.insert("Alice", &text(1, "The build is broken again"));
+        index.insert("Bob", &text(2, "Who broke the BUILD?"));
+        index.insert("Bob", &text(3, "Lunch?"));
+        let key = |from: &str, nonce| (from.to_string(), nonce, TextDirection::Incoming);
+        assert_eq!(index.search("build bro"), HashSet::from([key("Alice", 1), key("Bob", 2)]));
+        assert_eq!(index.search("lunch"), HashSet::from([key("Bob", 3)]));
+
+        let mut edited = text(2, "Who fixed it?");
+        edited.edited = true;
+        index.insert("Bob", &edited);
+        assert_eq!(index.search("build"), HashSet::from([key("Alice", 1)]));
+        index.remove_conversation("Alice");
+        assert!(index.search("build").is_empty());
+        assert!(index.search("").is_empty());
+    }
+}
@@ -0,0 +1,55 @@
+use std::time::SystemTime;
+
+use crate::config::defines;
+
+use super::{TextInfo, TextDirection, SearchIndex};
+
+/// A message matching a search, with the words around the match
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchResult
+{
+    pub conversation: String,
+    pub nonce: u64,
+    pub direction: TextDirection,
+    pub author: Option<String>,
+    pub sent: SystemTime,
+    pub snippet: String,
+}
+
+impl SearchResult
+{
+    pub fn new(conversation: &str, text: &TextInfo, query: &str) -> Self
+    {
+        Self {
+            conversation: conversation.to_string(),
+            nonce: text.nonce,
+            direction: text.direction.clone(),
+            author: text.author.clone(),
+            sent: text.sent,
+            snippet: Self::snippet(&text.text, query),
+        }
+    }
+
+    /// The words around the first one matching the query
+    fn snippet(text: &str, query: &str) -> String
+    {
+        let query = SearchIndex::tokenize(query);
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let found = words.iter().position(|word| {
+            let word = SearchIndex::tokenize(word);
+            query.iter().any(|query| word.iter().any(|word| word.starts_with(query)))
+        }).unwrap_or(0);
+        let start = found.saturating_sub(defines::SEARCH_SNIPPET_WORDS);
+        let end = (found + defines::SEARCH_SNIPPET_WORDS + 1).min(words.len());
+        let mut snippet = words[start..end].join(" ");
+        if start > 0
+        {
+            snippet = format!("…{}", snippet);
+        }
+        if end < words.len()
+        {
+            snippet = format!("{}…", snippet);
+        }
+        snippet
+    }
+}
@@ -1,4 +1,6 @@
-#[derive(Clone, Eq, PartialEq, Hash)]
+use serializable::Serializable;
+
+#[derive(Serializable, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum TextDirection
 {
     Incoming,
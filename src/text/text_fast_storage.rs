@@ -1,10 +1,11 @@
 use std::collections::{HashSet, LinkedList};
 
-use super::TextInfo;
+use super::{TextInfo, TextDirection};
 
 pub struct TextFastStorage
 {
-    set: HashSet<TextInfo>,
+    /// a resent message has new timestamps, so duplicates are found by nonce
+    set: HashSet<(u64,TextDirection)>,
     list: LinkedList<TextInfo>,
 }
 
@@ -18,11 +19,53 @@ impl TextFastStorage
         }
     }
 
-    pub fn add(&mut self, text: TextInfo)
+    /// Returns false if the message was already there
+    pub fn add(&mut self, text: TextInfo) -> bool
     {
-        if self.set.insert(text.clone())
+        let new = self.set.insert((text.nonce, text.direction.clone()));
+        if new
         {
+            // messages usually arrive in order, so the position is searched from the back
+            let position = self.list.iter().rev().position(|other| other.order_key() <= text.order_key()).unwrap_or(self.list.len());
+            let mut tail = self.list.split_off(self.list.len() - position);
             self.list.push_back(text);
+            self.list.append(&mut tail);
+        }
+        new
+    }
+
+    pub fn get_by_nonce(&self, nonce: u64, direction: &TextDirection) -> Option<&TextInfo>
+    {
+        self.list.iter().rev().find(|text| text.nonce == nonce && &text.direction == direction)
+    }
+
+    pub fn get_mut(&mut self, nonce: u64, direction: &TextDirection) -> Option<&mut TextInfo>
+    {
+        self.list.iter_mut().rev().find(|text| text.nonce == nonce && &text.direction == direction)
+    }
+
+    /// Only the author of a message can change it, `author` is None for the peer of a direct chat
+    pub fn edit(&mut self, nonce: u64, direction: &TextDirection, author: Option<&str>, text: String) -> Option<&TextInfo>
+    {
+        let message = self.get_mut(nonce, direction).filter(|message| message.author.as_deref() == author && !message.deleted)?;
+        message.text = text;
+        message.edited = true;
+        Some(message)
+    }
+
+    pub fn delete(&mut self, nonce: u64, direction: &TextDirection, author: Option<&str>) -> Option<&TextInfo>
+    {
+        let message = self.get_mut(nonce, direction).filter(|message| message.author.as_deref() == author)?;
+        message.text.clear();
+        message.deleted = true;
+        Some(message)
+    }
+
+    pub fn remove(&mut self, nonce: u64, direction: &TextDirection)
+    {
+        if self.set.remove(&(nonce, direction.clone()))
+        {
+            self.list = std::mem::take(&mut self.list).into_iter().filter(|text| text.nonce != nonce || &text.direction != direction).collect();
         }
     }
 
@@ -33,7 +76,85 @@ impl TextFastStorage
 
     pub fn contains(&self, text: &TextInfo) -> bool
     {
-        self.set.contains(text)
+        self.set.contains(&(text.nonce, text.direction.clone()))
+    }
+
+    pub fn last_lamport(&self) -> u64
+    {
+        self.list.iter().map(|text| text.lamport).max().unwrap_or(0)
     }
 }
 
+#[cfg(test)]
+mod tests
+{
+    use std::time::{SystemTime, Duration};
+
+    use crate::text::DeliveryState;
+
+    use super::*;
+
+    #[test]
+    fn causal_order()
+    {
+        let now = SystemTime::now();
+        let message = |nonce: u64, lamport: u64, sent: SystemTime, direction: TextDirection| TextInfo
+        {
+            text: nonce.to_string(),
+            nonce,
+            direction,
+            author: None,
+            lamport,
+            sent,
+            received: now,
+            state: DeliveryState::Delivered,
+            reply_to: None,
+            edited: false,
+            deleted: false,
+        };
+        let mut storage = TextFastStorage::new();
+        storage.add(message(1, 1, now, TextDirection::Incoming));
+        storage.add(message(3, 3, now, TextDirection::Incoming));
+        // acknowledged after a later message arrived
+        storage.add(message(2, 2, now, TextDirection::Outgoing));
+        // sent at the same time as the other peer's message
+        storage.add(message(4, 3, now - Duration::from_secs(1), TextDirection::Outgoing));
+        // resent, it must not be shown twice
+        storage.add(message(1, 1, now + Duration::from_secs(1), TextDirection::Incoming));
+        let order: Vec<u64> = storage.get().iter().map(|text| text.nonce).collect();
+        assert_eq!(order, vec![1, 2, 4, 3]);
+        assert_eq!(storage.last_lamport(), 3);
+    }
+
+    #[test]
+    fn edit_and_delete()
+    {
+        let mut storage = TextFastStorage::new();
+        let now = SystemTime::now();
+        let mut message = TextInfo
+        {
+            text: "Helo".to_string(),
+            nonce: 1,
+            direction: TextDirection::Incoming,
+            author: Some("Author".to_string()),
+            lamport: 1,
+            sent: now,
+            received: now,
+            state: DeliveryState::Delivered,
+            reply_to: None,
+            edited: false,
+            deleted: false,
+        };
+        storage.add(message.clone());
+        // only the author can change it
+        assert!(storage.edit(1, &TextDirection::Incoming, Some("Other"), "Bye".to_string()).is_none());
+        assert!(storage.delete(1, &TextDirection::Incoming, None).is_none());
+        message.text = "Hello".to_string();
+        message.edited = true;
+        assert_eq!(storage.edit(1, &TextDirection::Incoming, Some("Author"), "Hello".to_string()), Some(&message));
+        message.text.clear();
+        message.deleted = true;
+        assert_eq!(storage.delete(1, &TextDirection::Incoming, Some("Author")), Some(&message));
+        assert!(storage.edit(1, &TextDirection::Incoming, Some("Author"), "Hello again".to_string()).is_none());
+    }
+}
\ No newline at end of file
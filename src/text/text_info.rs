@@ -1,9 +1,54 @@
-use super::TextDirection;
+use std::time::SystemTime;
 
-#[derive(Clone, Eq, PartialEq, Hash)]
+use serializable::Serializable;
+
+use crate::config::defines;
+
+use super::{TextDirection, DeliveryState};
+
+#[derive(Serializable, Clone, Debug, Eq, PartialEq, Hash)]
 pub struct TextInfo
 {
     pub text: String,
     pub nonce: u64,
     pub direction: TextDirection,
+    /// who wrote an incoming group message
+    pub author: Option<String>,
+    /// per conversation lamport clock, the messages are shown in this order
+    pub lamport: u64,
+    /// when the sender sent it
+    pub sent: SystemTime,
+    /// when it was received, or acknowledged for outgoing messages
+    pub received: SystemTime,
+    pub state: DeliveryState,
+    /// nonce of the message this one answers
+    pub reply_to: Option<u64>,
+    pub edited: bool,
+    /// deleted by its author, the text is gone
+    pub deleted: bool,
+}
+
+impl TextInfo
+{
+    /// Messages sent at the same lamport time are ordered by time and then by nonce
+    pub fn order_key(&self) -> (u64, SystemTime, u64)
+    {
+        (self.lamport, self.sent, self.nonce)
+    }
+
+    /// When the message was written or arrived here by our own clock, the clock of the sender may be wrong
+    pub fn local_time(&self) -> SystemTime
+    {
+        match self.direction
+        {
+            TextDirection::Incoming => self.received,
+            TextDirection::Outgoing => self.sent,
+        }
+    }
+
+    /// A longer text doesn't fit in a packet, it can't be sent
+    pub fn is_too_long(&self) -> bool
+    {
+        self.text.len() > defines::MAX_TEXT_LEN
+    }
 }
\ No newline at end of file
@@ -1,11 +1,22 @@
-use std::collections::{HashMap, LinkedList};
+use std::{collections::{HashMap, LinkedList}, time::{Instant, Duration, SystemTime}};
 
-use super::{TextInfo, TextFastStorage};
+use crate::config::defines;
+
+use super::{TextInfo, TextFastStorage, TextDirection, Reactions, SearchIndex, SearchResult};
 
 pub struct TextList
 {
     messages: HashMap<String,TextFastStorage>,
     notifications: HashMap<String,bool>,
+    /// incoming messages not shown yet
+    unread: HashMap<String,Vec<u64>>,
+    /// messages shown but not acknowledged with a read receipt yet
+    read: HashMap<String,Vec<u64>>,
+    /// reactions of each conversation by message nonce
+    reactions: HashMap<String,HashMap<u64,Reactions>>,
+    /// when each person of a conversation was last seen typing
+    typing: HashMap<String,HashMap<String,Instant>>,
+    search_index: SearchIndex,
 }
 
 impl TextList
@@ -15,14 +26,176 @@ impl TextList
         Self { 
             messages: HashMap::new(),
             notifications: HashMap::new(),
+            unread: HashMap::new(),
+            read: HashMap::new(),
+            reactions: HashMap::new(),
+            typing: HashMap::new(),
+            search_index: SearchIndex::default(),
         }
     }
 
     pub fn add(&mut self, from: &str, text: TextInfo)
     {
         let storage = self.messages.entry(from.to_string()).or_insert(TextFastStorage::new());
-        storage.add(text);
-        self.notifications.insert(from.to_string(), true);
+        let nonce = text.nonce;
+        let added = storage.add(text.clone());
+        if added
+        {
+            self.search_index.insert(from, &text);
+        }
+        if added && text.direction == TextDirection::Incoming
+        {
+            // the message they were typing arrived
+            if let Some(typing) = self.typing.get_mut(from)
+            {
+                typing.remove(text.author.as_deref().unwrap_or(from));
+            }
+            self.notifications.insert(from.to_string(), true);
+            self.unread.entry(from.to_string()).or_default().push(nonce);
+        }
+    }
+
+    /// Messages read from the history, they don't count as new.
+    /// A message saved again replaces the older copy
+    pub fn add_history(&mut self, from: &str, texts: Vec<TextInfo>)
+    {
+        let storage = self.messages.entry(from.to_string()).or_insert(TextFastStorage::new());
+        for text in texts
+        {
+            self.search_index.insert(from, &text);
+            let (nonce, direction) = (text.nonce, text.direction.clone());
+            if !storage.add(text.clone())
+            {
+                if let Some(old) = storage.get_mut(nonce, &direction)
+                {
+                    *old = text;
+                }
+            }
+        }
+    }
+
+    pub fn get_info(&self, from: &str, nonce: u64, direction: &TextDirection) -> Option<TextInfo>
+    {
+        self.messages.get(from)?.get_by_nonce(nonce, direction).cloned()
+    }
+
+    /// Changes a message in place, returns the updated message
+    pub fn update(&mut self, from: &str, nonce: u64, direction: &TextDirection, f: impl FnOnce(&mut TextInfo)) -> Option<TextInfo>
+    {
+        let text = self.messages.get_mut(from)?.get_mut(nonce, direction)?;
+        f(text);
+        self.search_index.insert(from, text);
+        Some(text.clone())
+    }
+
+    pub fn edit(&mut self, from: &str, nonce: u64, direction: &TextDirection, author: Option<&str>, text: String) -> Option<TextInfo>
+    {
+        let text = self.messages.get_mut(from)?.edit(nonce, direction, author, text)?;
+        self.search_index.insert(from, text);
+        Some(text.clone())
+    }
+
+    pub fn delete(&mut self, from: &str, nonce: u64, direction: &TextDirection, author: Option<&str>) -> Option<TextInfo>
+    {
+        let text = self.messages.get_mut(from)?.delete(nonce, direction, author)?;
+        self.search_index.insert(from, text);
+        Some(text.clone())
+    }
+
+    /// Returns false if the message doesn't exist, `who` is None for our own reactions
+    pub fn react(&mut self, from: &str, nonce: u64, who: Option<&str>, emoji: &str, added: bool) -> bool
+    {
+        let exists = self.messages.get(from).map(|storage| {
+            [TextDirection::Incoming, TextDirection::Outgoing].iter()
+                .any(|direction| storage.get_by_nonce(nonce, direction).map(|text| !text.deleted).unwrap_or(false))
+        }).unwrap_or(false);
+        if exists
+        {
+            self.reactions.entry(from.to_string()).or_default().entry(nonce).or_default().set(who, emoji, added);
+        }
+        exists
+    }
+
+    pub fn reactions(&self, from: &str) -> Option<&HashMap<u64,Reactions>>
+    {
+        self.reactions.get(from)
+    }
+
+    pub fn set_typing(&mut self, from: &str, who: &str)
+    {
+        self.typing.entry(from.to_string()).or_default().insert(who.to_string(), Instant::now());
+    }
+
+    /// Who is typing in the conversation, sorted by name
+    pub fn typing(&self, from: &str) -> Vec<String>
+    {
+        let mut typing: Vec<String> = self.typing.get(from).map(|typing| typing.iter()
+            .filter(|(_, last_seen)| last_seen.elapsed() < defines::TYPING_TIMEOUT)
+            .map(|(who, _)| who.clone())
+            .collect()).unwrap_or_default();
+        typing.sort();
+        typing
+    }
+
+    pub fn remove(&mut self, from: &str, nonce: u64, direction: &TextDirection)
+    {
+        if let Some(storage) = self.messages.get_mut(from)
+        {
+            storage.remove(nonce, direction);
+        }
+        self.search_index.remove(&(from.to_string(), nonce, direction.clone()));
+    }
+
+    /// Removes the messages sent more than `max_age` ago, returns how many were removed
+    pub fn expire(&mut self, from: &str, max_age: Duration) -> usize
+    {
+        let now = SystemTime::now();
+        let expired: Vec<(u64,TextDirection)> = match self.messages.get(from)
+        {
+            Some(storage) => storage.get().iter()
+                .filter(|text| now.duration_since(text.local_time()).map(|age| age > max_age).unwrap_or(false))
+                .map(|text| (text.nonce, text.direction.clone()))
+                .collect(),
+            None => return 0,
+        };
+        for (nonce, direction) in &expired
+        {
+            self.remove(from, *nonce, direction);
+            if let Some(reactions) = self.reactions.get_mut(from)
+            {
+                reactions.remove(nonce);
+            }
+            for nonces in [self.unread.get_mut(from), self.read.get_mut(from)].into_iter().flatten()
+            {
+                nonces.retain(|unread| unread != nonce);
+            }
+        }
+        expired.len()
+    }
+
+    pub fn clear(&mut self, from: &str)
+    {
+        self.messages.remove(from);
+        self.notifications.remove(from);
+        self.unread.remove(from);
+        self.read.remove(from);
+        self.reactions.remove(from);
+        self.typing.remove(from);
+        self.search_index.remove_conversation(from);
+    }
+
+    /// Messages of every conversation matching the query, the newest first
+    pub fn search(&self, query: &str) -> Vec<SearchResult>
+    {
+        let mut results: Vec<SearchResult> = self.search_index.search(query).into_iter()
+            .filter_map(|(from, nonce, direction)| {
+                let text = self.messages.get(&from)?.get_by_nonce(nonce, &direction)?;
+                Some(SearchResult::new(&from, text, query))
+            })
+            .collect();
+        results.sort_by(|r1, r2| r2.sent.cmp(&r1.sent).then(r1.nonce.cmp(&r2.nonce)));
+        results.truncate(defines::SEARCH_RESULTS_LIMIT);
+        results
     }
 
     pub fn get(&mut self, from: &str) -> Option<&LinkedList<TextInfo>>
@@ -31,15 +204,36 @@ impl TextList
         if ret.is_some()
         {
             self.notifications.insert(from.to_string(), false);
+            if let Some(unread) = self.unread.remove(from)
+            {
+                self.read.entry(from.to_string()).or_default().extend(unread);
+            }
         }
         ret
     }
 
+    /// The messages of a conversation without marking them as read
+    pub fn messages(&self, from: &str) -> Option<&LinkedList<TextInfo>>
+    {
+        self.messages.get(from).map(|storage| storage.get())
+    }
+
     pub fn contains(&self, from: &str, text: &TextInfo) -> bool
     {
         self.messages.get(from).map(|storage| storage.contains(text)).unwrap_or(false)
     }
 
+    pub fn last_lamport(&self, from: &str) -> u64
+    {
+        self.messages.get(from).map(|storage| storage.last_lamport()).unwrap_or(0)
+    }
+
+    /// The messages shown since the last call, for each contact
+    pub fn take_read(&mut self) -> HashMap<String,Vec<u64>>
+    {
+        std::mem::take(&mut self.read)
+    }
+
     pub fn has_new_messages(&self, from: &str) -> bool
     {
         self.notifications.get(from).cloned().unwrap_or(false)
@@ -1,6 +1,18 @@
+use super::DisappearingTimer;
+
 #[derive(Debug, Clone)]
-pub struct TextRequest
+pub enum TextRequest
 {
-    pub text: String,
-    pub dst: String,
+    /// destination, text and the nonce of the message it replies to
+    Send(String,String,Option<u64>),
+    /// changes the text of one of our messages
+    Edit(String,u64,String),
+    /// deletes one of our messages for everyone
+    Delete(String,u64),
+    /// adds or removes our reaction to a message
+    React(String,u64,String,bool),
+    /// tells the contact we are writing
+    Typing(String),
+    /// changes how long the messages of a contact are kept, for both of us
+    SetTimer(String,Option<DisappearingTimer>),
 }
\ No newline at end of file
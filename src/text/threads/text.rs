@@ -1,59 +1,172 @@
-use std::{sync::{mpsc::{Receiver, Sender}, Arc, RwLock}, net::SocketAddr, collections::HashMap, time::{Instant, Duration}};
+use std::{sync::{mpsc::{Receiver, Sender}, Arc, RwLock}, net::SocketAddr, collections::{HashMap, HashSet}, time::{Instant, Duration, SystemTime}, error::Error, str::FromStr};
 
 use rand::RngCore;
+use serializable::Serializable;
 
-use crate::{network::{Packet, ConnectionList, Content}, config::{Config, defines}, text::{TextList, TextInfo, TextDirection, TextRequest}, log::Logger};
+use crate::{network::{Packet, ConnectionList, Content}, config::{Config, defines, HistoryRetention}, text::{TextList, TextInfo, TextDirection, TextRequest, History, HistoryEntry, DeliveryState, Outbox, Group, DisappearingTimer}, log::{Logger, MessageKind}, hooks::HookEvent, crypto::{RatchetMessage, RatchetSession, CryptoLastingInfo, PublicKey, SignedGroupInfo, SymmetricKey, Ciphertext}};
 
 pub fn run(
     running: Arc<RwLock<bool>>,
     text_list: Arc<RwLock<TextList>>,
     connection_list: Arc<RwLock<ConnectionList>>,
-    _log: Logger,
+    log: Logger,
     requests: Receiver<TextRequest>,
     text_queue: Receiver<(Packet,SocketAddr)>, 
     sender_queue: Sender<(Content,SocketAddr)>,
+    hook_events: Sender<HookEvent>,
     config: Arc<RwLock<Config>>)
 {
-    let mut pending_messages = HashMap::<(String,String,u64),Instant>::new();
+    let context = TextContext { text_list, connection_list, log, sender_queue, hook_events, config };
+    let TextContext { text_list, connection_list, log, sender_queue, config, .. } = &context;
+    // last time the messages waiting for an ack were sent, the text is in the text list
+    let mut pending_messages = HashMap::<(String,u64),Instant>::new();
+    // same for the edits and the deletions of our messages
+    let mut pending_changes = HashMap::<(String,u64),Instant>::new();
+    let mut clocks = HashMap::<String,u64>::new();
+    load_history(text_list, log, config);
+    let mut outbox = load_outbox(text_list, log, config);
+    // group id, member name and version of the membership lists sent to the connected members
+    let mut announced = HashSet::<(String,String,u64)>::new();
+    // connected contacts that were told our disappearing timer
+    let mut announced_timers = HashSet::<String>::new();
+    let mut last_expiry = Instant::now();
+    let mut last_announce = Instant::now();
     while *running.read().unwrap()
     {
         match text_queue.recv_timeout(defines::THREAD_QUEUE_TIMEOUT) {
             Ok((packet,from)) => 
             {
                 match packet.content {
-                    Content::Text(text,nonce) => 
+                    content @ (Content::Text(..) | Content::ReplyText(..) | Content::EditText(..) | Content::DeleteText(..) | Content::Reaction(..) | Content::Typing | Content::DisappearingTimer(..)) => 
                     {
                         // check if the user is in the connection list
-                        let connection_list = connection_list.read().unwrap();
-                        if let Some(name) = connection_list.get_name(&from)
+                        let name = connection_list.read().unwrap().get_name(&from).map(|name| name.to_string());
+                        if let Some(name) = name.filter(|name| !is_downgrade(name, log, config))
                         {
-                            // check if the text was already received
-                            let mut text_list = text_list.write().unwrap();
-                            let info = TextInfo {
-                                text: text.clone(),
-                                nonce,
-                                direction: TextDirection::Incoming,
-                            };
-                            // add to the list if new
-                            text_list.add(name, info);
-                            // send ack
-                            let content = Content::AcknowledgeText(text,nonce);
-                            sender_queue.send((content,from)).unwrap();
+                            receive_message(&name, None, content, packet.timestamp, from, &mut clocks, &context);
                         }
                     },
-                    Content::AcknowledgeText(text,nonce) =>
+                    Content::Ratchet(message) =>
                     {
-                        let connection_list = connection_list.read().unwrap();
-                        if let Some(name) = connection_list.get_name(&from)
+                        let name = connection_list.read().unwrap().get_name(&from).map(|name| name.to_string());
+                        if let Some(name) = name
                         {
-                            text_list.write().unwrap().add(name, 
-                                TextInfo { 
-                                    text: text.clone(), 
-                                    nonce,
-                                    direction: TextDirection::Outgoing });
-                            pending_messages.remove(&(name.to_string(),text,nonce));
+                            match open_ratchet(&name, &message, connection_list, config)
+                            {
+                                Ok(content @ (Content::Text(..) | Content::ReplyText(..) | Content::EditText(..) | Content::DeleteText(..) | Content::Reaction(..) | Content::Typing | Content::DisappearingTimer(..))) => 
+                                {
+                                    receive_message(&name, None, content, packet.timestamp, from, &mut clocks, &context);
+                                },
+                                Ok(Content::GroupUpdate(signed_group_info,key)) =>
+                                {
+                                    receive_group_update(&name, &signed_group_info, key, connection_list, log, config);
+                                },
+                                Ok(Content::GroupText(id,ciphertext)) =>
+                                {
+                                    receive_group_text(&name, &id, &ciphertext, packet.timestamp, from, &mut clocks, &context);
+                                },
+                                Ok(content) =>
+                                {
+                                    log.log(MessageKind::Error, &format!("Unexpected ratchet content from {}: {:?}", name, content)).unwrap();
+                                },
+                                Err(e) =>
+                                {
+                                    log.log(MessageKind::Error, &format!("Error decrypting ratchet message from {}: {}", name, e)).unwrap();
+                                    // a session that lost sync, for example after a crash, is started again on both sides
+                                    if reset_ratchet(&name, Some(&message), log, config)
+                                    {
+                                        sender_queue.send((Content::ResetRatchet,from)).unwrap();
+                                        log.log(MessageKind::Event, &format!("The secure session with {} is started again", name)).unwrap();
+                                    }
+                                },
+                            }
                         }
-                    }
+                    },
+                    Content::ResetRatchet =>
+                    {
+                        let name = connection_list.read().unwrap().get_name(&from).map(|name| name.to_string());
+                        if let Some(name) = name
+                        {
+                            // our next message starts a new session
+                            if reset_ratchet(&name, None, log, config)
+                            {
+                                log.log(MessageKind::Event, &format!("{} started the secure session again", name)).unwrap();
+                            }
+                        }
+                    },
+                    Content::GroupUpdate(signed_group_info,key) =>
+                    {
+                        let name = connection_list.read().unwrap().get_name(&from).map(|name| name.to_string());
+                        if let Some(name) = name.filter(|name| !is_downgrade(name, log, config))
+                        {
+                            receive_group_update(&name, &signed_group_info, key, connection_list, log, config);
+                        }
+                    },
+                    Content::GroupText(id,ciphertext) =>
+                    {
+                        let name = connection_list.read().unwrap().get_name(&from).map(|name| name.to_string());
+                        if let Some(name) = name.filter(|name| !is_downgrade(name, log, config))
+                        {
+                            receive_group_text(&name, &id, &ciphertext, packet.timestamp, from, &mut clocks, &context);
+                        }
+                    },
+                    Content::AcknowledgeText(nonce) =>
+                    {
+                        let name = connection_list.read().unwrap().get_name(&from).map(|name| name.to_string());
+                        if let Some(name) = name
+                        {
+                            if pending_messages.remove(&(name.clone(),nonce)).is_some()
+                            {
+                                unqueue_text(&mut outbox, &name, nonce, log, config);
+                                let info = text_list.write().unwrap().update(&name, nonce, &TextDirection::Outgoing, |info| {
+                                    info.state = DeliveryState::Delivered;
+                                    info.received = SystemTime::now();
+                                });
+                                if let Some(info) = info
+                                {
+                                    save_history(&name, &info, false, connection_list, log, config);
+                                }
+                            }
+                        }
+                    },
+                    Content::AcknowledgeChange(nonce) =>
+                    {
+                        let name = connection_list.read().unwrap().get_name(&from).map(|name| name.to_string());
+                        if let Some(name) = name
+                        {
+                            if pending_changes.remove(&(name.clone(),nonce)).is_some()
+                            {
+                                unqueue_text(&mut outbox, &name, nonce, log, config);
+                            }
+                        }
+                    },
+                    Content::ReadReceipt(nonces) =>
+                    {
+                        let name = connection_list.read().unwrap().get_name(&from).map(|name| name.to_string());
+                        if let Some(name) = name
+                        {
+                            for nonce in nonces
+                            {
+                                // the receipt may arrive before the ack
+                                let acknowledged = pending_messages.remove(&(name.clone(),nonce)).is_some();
+                                if acknowledged
+                                {
+                                    unqueue_text(&mut outbox, &name, nonce, log, config);
+                                }
+                                let info = text_list.write().unwrap().update(&name, nonce, &TextDirection::Outgoing, |info| {
+                                    if acknowledged
+                                    {
+                                        info.received = SystemTime::now();
+                                    }
+                                    info.state = DeliveryState::Read;
+                                });
+                                if let Some(info) = info
+                                {
+                                    save_history(&name, &info, false, connection_list, log, config);
+                                }
+                            }
+                        }
+                    },
                     _ => unreachable!("Text thread received non-text packet: {:?}",packet)
                 }
             },
@@ -62,32 +175,63 @@ pub fn run(
                 match e
                 {
                     std::sync::mpsc::RecvTimeoutError::Timeout => {
-                        let config = config.read().unwrap().clone();
+                        let timeout = Duration::from_millis(config.read().unwrap().network.timeout_ms);
                         let mut to_remove = Vec::new();
-                        for ((name,message,nonce),last_seen) in pending_messages.iter_mut()
+                        let mut to_drop = Vec::new();
+                        let mut to_fail = Vec::new();
+                        for ((name,nonce),last_seen) in pending_messages.iter_mut()
                         {
-                            if last_seen.elapsed() >= Duration::from_millis(config.network.timeout_ms)
+                            if last_seen.elapsed() >= timeout
                             {
                                 // check if the user is still connected
-                                let connection_list = connection_list.read().unwrap();
-                                if let Some(addr) = connection_list.get_address(name)
+                                let address = connection_list.read().unwrap().get_address(name).copied();
+                                let info = text_list.read().unwrap().get_info(name, *nonce, &TextDirection::Outgoing)
+                                    .filter(|info| !info.deleted);
+                                match (address, info)
                                 {
-                                    // resend the message
-                                    let content = Content::Text(message.clone(),*nonce);
-                                    sender_queue.send((content,*addr)).unwrap();
-                                    // update the last seen time
-                                    *last_seen = Instant::now();
-                                }
-                                else {
-                                    // remove the message
-                                    to_remove.push((name.to_string(),message.clone(),*nonce));
+                                    // it was sent before the limit, it can never be delivered
+                                    (_, Some(info)) if info.is_too_long() => to_fail.push((name.to_string(),*nonce)),
+                                    (Some(addr), Some(info)) =>
+                                    {
+                                        // resend the message, the ratchet needs a fresh encryption every time
+                                        match seal(name, text_content(&info), connection_list, config)
+                                        {
+                                            Ok(content) => sender_queue.send((content,addr)).unwrap(),
+                                            Err(e) => log.log(MessageKind::Error, &format!("Error encrypting message for {}: {}", name, e)).unwrap(),
+                                        }
+                                        // update the last seen time
+                                        *last_seen = Instant::now();
+                                    },
+                                    // queue the message until the peer comes back
+                                    (None, Some(_)) => to_remove.push((name.to_string(),*nonce)),
+                                    // deleted or cleared
+                                    (_, None) => to_drop.push((name.to_string(),*nonce)),
                                 }
                             }
                         }
-                        for (name,message,nonce) in to_remove
+                        for (name,nonce) in to_drop
+                        {
+                            pending_messages.remove(&(name.clone(),nonce));
+                            unqueue_text(&mut outbox, &name, nonce, log, config);
+                        }
+                        for (name,nonce) in to_fail
+                        {
+                            log.log(MessageKind::Error, &format!("Cannot send a message over {}B to {}", defines::MAX_TEXT_LEN, name)).unwrap();
+                            pending_messages.remove(&(name.clone(),nonce));
+                            unqueue_text(&mut outbox, &name, nonce, log, config);
+                            text_list.write().unwrap().update(&name, nonce, &TextDirection::Outgoing, |info| info.state = DeliveryState::Failed);
+                        }
+                        for (name,nonce) in to_remove
                         {
-                            pending_messages.remove(&(name,message,nonce));
+                            pending_messages.remove(&(name.clone(),nonce));
+                            let info = text_list.read().unwrap().get_info(&name, nonce, &TextDirection::Outgoing);
+                            if let Some(info) = info
+                            {
+                                let state = queue_text(&name, info, &mut outbox, connection_list, log, config);
+                                text_list.write().unwrap().update(&name, nonce, &TextDirection::Outgoing, |info| info.state = state);
+                            }
                         }
+                        resend_changes(&mut pending_changes, &mut outbox, timeout, &context);
                     },
                     std::sync::mpsc::RecvTimeoutError::Disconnected => 
                     {
@@ -99,18 +243,120 @@ pub fn run(
                 }
             },
         }
+        // these take the locks of the config and of the connection list even when there is nothing to send
+        if last_announce.elapsed() >= defines::ANNOUNCE_INTERVAL
+        {
+            send_read_receipts(text_list, sender_queue, connection_list, config);
+            flush_outbox(&mut outbox, &mut pending_messages, &mut pending_changes, &context);
+            announce_groups(&mut announced, sender_queue, connection_list, log, config);
+            announce_timers(&mut announced_timers, sender_queue, connection_list, log, config);
+            last_announce = Instant::now();
+        }
+        if last_expiry.elapsed() >= defines::EXPIRY_INTERVAL
+        {
+            expire_messages(&mut outbox, text_list, log, config);
+            last_expiry = Instant::now();
+        }
         // check if there are any new messages to send
         match requests.try_recv()
         {
-            Ok(TextRequest{text, dst}) => 
+            Ok(TextRequest::Send(dst, text, reply_to)) => 
+            {
+                let nonce = gen_nonce();
+                let lamport = tick_clock(&mut clocks, &dst, text_list);
+                let now = SystemTime::now();
+                let info = TextInfo {
+                    text: text.clone(),
+                    nonce,
+                    direction: TextDirection::Outgoing,
+                    author: None,
+                    lamport,
+                    sent: now,
+                    received: now,
+                    state: DeliveryState::Pending,
+                    reply_to,
+                    edited: false,
+                    deleted: false,
+                };
+                text_list.write().unwrap().add(&dst, info.clone());
+                let is_group = config.read().unwrap().text.groups.contains_key(&dst);
+                let address = connection_list.read().unwrap().get_address(&dst).copied();
+                let state = match address
+                {
+                    _ if info.is_too_long() =>
+                    {
+                        log.log(MessageKind::Error, &format!("Cannot send a message over {}B to {}", defines::MAX_TEXT_LEN, dst)).unwrap();
+                        DeliveryState::Failed
+                    },
+                    _ if is_group => send_group_text(&dst, text_content(&info), sender_queue, connection_list, log, config),
+                    Some(addr) => match seal(&dst, text_content(&info), connection_list, config)
+                    {
+                        Ok(content) => 
+                        {
+                            sender_queue.send((content,addr)).unwrap();
+                            pending_messages.insert((dst.clone(),nonce),Instant::now());
+                            DeliveryState::Sent
+                        },
+                        Err(e) => 
+                        {
+                            log.log(MessageKind::Error, &format!("Error encrypting message for {}: {}", dst, e)).unwrap();
+                            DeliveryState::Failed
+                        },
+                    },
+                    None => queue_text(&dst, info, &mut outbox, connection_list, log, config),
+                };
+                text_list.write().unwrap().update(&dst, nonce, &TextDirection::Outgoing, |info| info.state = state);
+            },
+            Ok(TextRequest::Edit(_, _, text)) if text.len() > defines::MAX_TEXT_LEN =>
+            {
+                log.log(MessageKind::Error, &format!("Cannot edit a message to more than {}B", defines::MAX_TEXT_LEN)).unwrap();
+            },
+            Ok(TextRequest::Edit(dst, nonce, text)) =>
+            {
+                let info = text_list.write().unwrap().edit(&dst, nonce, &TextDirection::Outgoing, None, text.clone());
+                if let Some(info) = info
+                {
+                    if info.state.is_delivered()
+                    {
+                        save_history(&dst, &info, true, connection_list, log, config);
+                    }
+                    // a queued message is sent with the new text
+                    if !update_queued(&mut outbox, &dst, info.clone(), log, config)
+                    {
+                        send_text_change(&dst, info, &mut pending_changes, &mut outbox, &context);
+                    }
+                }
+            },
+            Ok(TextRequest::Delete(dst, nonce)) =>
+            {
+                let info = text_list.write().unwrap().delete(&dst, nonce, &TextDirection::Outgoing, None);
+                if let Some(info) = info
+                {
+                    if info.state.is_delivered()
+                    {
+                        save_history(&dst, &info, true, connection_list, log, config);
+                    }
+                    pending_messages.remove(&(dst.clone(), nonce));
+                    if !update_queued(&mut outbox, &dst, info.clone(), log, config)
+                    {
+                        send_text_change(&dst, info, &mut pending_changes, &mut outbox, &context);
+                    }
+                }
+            },
+            Ok(TextRequest::Typing(dst)) =>
             {
-                let connection_list = connection_list.read().unwrap();
-                if let Some(addr) = connection_list.get_address(&dst)
+                send_change(&dst, Content::Typing, sender_queue, connection_list, log, config);
+            },
+            Ok(TextRequest::SetTimer(dst, timer)) =>
+            {
+                set_timer(&dst, timer, SystemTime::now(), config);
+                announced_timers.remove(&dst);
+            },
+            Ok(TextRequest::React(dst, nonce, emoji, added)) =>
+            {
+                if text_list.write().unwrap().react(&dst, nonce, None, &emoji, added)
                 {
-                    let nonce = gen_nonce();
-                    let content = Content::Text(text.clone(),nonce);
-                    sender_queue.send((content,*addr)).unwrap();
-                    pending_messages.insert((dst,text,nonce),Instant::now());
+                    send_change(&dst, Content::Reaction(nonce, emoji, added), sender_queue, connection_list, log, config);
                 }
             },
             Err(e) => 
@@ -131,7 +377,1016 @@ pub fn run(
     }
 }
 
+/// The shared state the functions of the thread work on
+struct TextContext
+{
+    text_list: Arc<RwLock<TextList>>,
+    connection_list: Arc<RwLock<ConnectionList>>,
+    log: Logger,
+    sender_queue: Sender<(Content,SocketAddr)>,
+    hook_events: Sender<HookEvent>,
+    config: Arc<RwLock<Config>>,
+}
+
+/// Applies a text, an edit or a delete sent by `name`, group messages come with the group id and the name of their author
+fn receive_message(
+    name: &str,
+    group: Option<(&str,&str)>,
+    content: Content,
+    sent: SystemTime,
+    from: SocketAddr,
+    clocks: &mut HashMap<String,u64>,
+    context: &TextContext)
+{
+    let TextContext { text_list, connection_list, log, sender_queue, hook_events, config } = context;
+    let (conversation, author) = match group
+    {
+        Some((id, author)) => (id, Some(author)),
+        None => (name, None),
+    };
+    let (text, nonce, lamport, reply_to) = match content
+    {
+        Content::Text(text,nonce,lamport) => (text, nonce, lamport, None),
+        Content::ReplyText(text,nonce,lamport,reply_to) => (text, nonce, lamport, Some(reply_to)),
+        Content::EditText(nonce,text) =>
+        {
+            let info = text_list.write().unwrap().edit(conversation, nonce, &TextDirection::Incoming, author, text);
+            if let Some(info) = info
+            {
+                save_history(conversation, &info, true, connection_list, log, config);
+            }
+            if group.is_none()
+            {
+                sender_queue.send((Content::AcknowledgeChange(nonce),from)).unwrap();
+            }
+            return;
+        },
+        Content::DeleteText(nonce) =>
+        {
+            let info = text_list.write().unwrap().delete(conversation, nonce, &TextDirection::Incoming, author);
+            if let Some(info) = info
+            {
+                save_history(conversation, &info, true, connection_list, log, config);
+            }
+            if group.is_none()
+            {
+                sender_queue.send((Content::AcknowledgeChange(nonce),from)).unwrap();
+            }
+            return;
+        },
+        Content::Typing =>
+        {
+            text_list.write().unwrap().set_typing(conversation, author.unwrap_or(name));
+            return;
+        },
+        Content::DisappearingTimer(timer, changed) if group.is_none() =>
+        {
+            if timer.is_some_and(|timer| !DisappearingTimer::OPTIONS.contains(&timer))
+            {
+                log.log(MessageKind::Error, &format!("{} sent an unknown disappearing timer", name)).unwrap();
+            }
+            else if set_timer(name, timer, changed, config)
+            {
+                let message = match timer
+                {
+                    Some(timer) => format!("{} set disappearing messages to {}", name, timer.name()),
+                    None => format!("{} turned off disappearing messages", name),
+                };
+                log.log(MessageKind::Event, &message).unwrap();
+            }
+            return;
+        },
+        Content::Reaction(nonce,emoji,added) =>
+        {
+            if emoji.chars().count() <= defines::MAX_REACTION_LEN
+            {
+                text_list.write().unwrap().react(conversation, nonce, Some(author.unwrap_or(name)), &emoji, added);
+            }
+            return;
+        },
+        content =>
+        {
+            log.log(MessageKind::Error, &format!("Unexpected content from {}: {:?}", name, content)).unwrap();
+            return;
+        }
+    };
+    let lamport = observe_clock(clocks, conversation, lamport, text_list);
+    let info = TextInfo {
+        text,
+        nonce,
+        direction: TextDirection::Incoming,
+        author: author.map(str::to_string),
+        lamport,
+        sent,
+        received: SystemTime::now(),
+        state: DeliveryState::Delivered,
+        reply_to,
+        edited: false,
+        deleted: false,
+    };
+    let event = HookEvent::Message(conversation.to_string(), author.unwrap_or(name).to_string(), info.text.clone());
+    if store_text(conversation, info, text_list, connection_list, log, config)
+    {
+        hook_events.send(event).unwrap();
+    }
+    // group messages are not acknowledged
+    if group.is_none()
+    {
+        sender_queue.send((Content::AcknowledgeText(nonce),from)).unwrap();
+    }
+}
+
+fn text_content(info: &TextInfo) -> Content
+{
+    match info.reply_to
+    {
+        Some(reply_to) => Content::ReplyText(info.text.clone(), info.nonce, info.lamport, reply_to),
+        None => Content::Text(info.text.clone(), info.nonce, info.lamport),
+    }
+}
+
+/// Sends a reaction, a typing notification or a timer, peers that are offline miss it
+fn send_change(
+    dst: &str,
+    content: Content,
+    sender_queue: &Sender<(Content,SocketAddr)>,
+    connection_list: &Arc<RwLock<ConnectionList>>,
+    log: &Logger,
+    config: &Arc<RwLock<Config>>)
+{
+    let is_group = config.read().unwrap().text.groups.contains_key(dst);
+    if is_group
+    {
+        send_group_text(dst, content, sender_queue, connection_list, log, config);
+        return;
+    }
+    let address = connection_list.read().unwrap().get_address(dst).copied();
+    if let Some(addr) = address
+    {
+        match seal(dst, content, connection_list, config)
+        {
+            Ok(content) => sender_queue.send((content,addr)).unwrap(),
+            Err(e) => log.log(MessageKind::Error, &format!("Error encrypting message for {}: {}", dst, e)).unwrap(),
+        }
+    }
+}
+
+/// The edit or the deletion of one of our messages
+fn change_content(info: &TextInfo) -> Content
+{
+    if info.deleted
+    {
+        Content::DeleteText(info.nonce)
+    }
+    else
+    {
+        Content::EditText(info.nonce, info.text.clone())
+    }
+}
+
+/// Sends the edit or the deletion of one of our messages, it's sent again until it's acknowledged
+/// and queued while the contact is offline. Groups get it once like their texts
+fn send_text_change(
+    dst: &str,
+    info: TextInfo,
+    pending_changes: &mut HashMap<(String,u64),Instant>,
+    queues: &mut OutboxQueues,
+    context: &TextContext)
+{
+    let TextContext { connection_list, log, sender_queue, config, .. } = context;
+    let is_group = config.read().unwrap().text.groups.contains_key(dst);
+    if is_group
+    {
+        send_group_text(dst, change_content(&info), sender_queue, connection_list, log, config);
+        return;
+    }
+    let address = connection_list.read().unwrap().get_address(dst).copied();
+    match address
+    {
+        Some(addr) => match seal(dst, change_content(&info), connection_list, config)
+        {
+            Ok(content) =>
+            {
+                sender_queue.send((content,addr)).unwrap();
+                pending_changes.insert((dst.to_string(),info.nonce),Instant::now());
+            },
+            Err(e) =>
+            {
+                log.log(MessageKind::Error, &format!("Error encrypting message for {}: {}", dst, e)).unwrap();
+                unqueue_text(queues, dst, info.nonce, log, config);
+            },
+        },
+        None => queue_change(dst, info, queues, connection_list, log, config),
+    }
+}
+
+/// Sends again the changes that were not acknowledged, the ones of contacts that went offline are queued
+fn resend_changes(
+    pending_changes: &mut HashMap<(String,u64),Instant>,
+    queues: &mut OutboxQueues,
+    timeout: Duration,
+    context: &TextContext)
+{
+    let expired: Vec<(String,u64)> = pending_changes.iter()
+        .filter(|(_, last_seen)| last_seen.elapsed() >= timeout)
+        .map(|(key, _)| key.clone())
+        .collect();
+    for (name, nonce) in expired
+    {
+        pending_changes.remove(&(name.clone(),nonce));
+        let info = context.text_list.read().unwrap().get_info(&name, nonce, &TextDirection::Outgoing);
+        // a cleared message has nothing left to change
+        if let Some(info) = info
+        {
+            send_text_change(&name, info, pending_changes, queues, context);
+        }
+    }
+}
+
+/// Adds the text to the list and to the history on disk if it's new, returns false if it was already there
+fn store_text(
+    name: &str,
+    info: TextInfo,
+    text_list: &Arc<RwLock<TextList>>,
+    connection_list: &Arc<RwLock<ConnectionList>>,
+    log: &Logger,
+    config: &Arc<RwLock<Config>>) -> bool
+{
+    {
+        let mut text_list = text_list.write().unwrap();
+        if text_list.contains(name, &info)
+        {
+            return false;
+        }
+        text_list.add(name, info.clone());
+    }
+    save_history(name, &info, false, connection_list, log, config);
+    true
+}
+
+/// Appends the message to the history, a message saved again replaces the older copy when loaded.
+/// Replacing also removes the older copies from the file
+fn save_history(
+    name: &str,
+    info: &TextInfo,
+    replace: bool,
+    connection_list: &Arc<RwLock<ConnectionList>>,
+    log: &Logger,
+    config: &Arc<RwLock<Config>>)
+{
+    let connection_identity = connection_identity(name, connection_list);
+    let config = config.read().unwrap();
+    if config.text.history_retention_for(name) == HistoryRetention::Off
+    {
+        return;
+    }
+    let identity = match config.text.groups.contains_key(name)
+    {
+        true => Some(History::group_identity(name)),
+        false => connection_identity
+            .or_else(|| config.network.known_hosts.get(name).map(|known_host| known_host.crypto_info().clone()))
+            .map(|identity| History::identity(&identity.public_key)),
+    };
+    if let Some(identity) = identity
+    {
+        let history = History::from_config(&config);
+        let entry = HistoryEntry::now(info.clone());
+        let result = if replace
+        {
+            history.replace(&identity, &entry)
+        }
+        else
+        {
+            history.append(&identity, &entry)
+        };
+        if let Err(e) = result
+        {
+            log.log(MessageKind::Error, &format!("Error saving the history of {}: {}", name, e)).unwrap();
+        }
+    }
+}
+
+/// Tells the peers which of their messages were shown in the chat
+fn send_read_receipts(
+    text_list: &Arc<RwLock<TextList>>,
+    sender_queue: &Sender<(Content,SocketAddr)>,
+    connection_list: &Arc<RwLock<ConnectionList>>,
+    config: &Arc<RwLock<Config>>)
+{
+    let read = text_list.write().unwrap().take_read();
+    if read.is_empty() || !config.read().unwrap().text.read_receipts
+    {
+        return;
+    }
+    let connection_list = connection_list.read().unwrap();
+    for (name, nonces) in read
+    {
+        if let Some(addr) = connection_list.get_address(&name)
+        {
+            sender_queue.send((Content::ReadReceipt(nonces),*addr)).unwrap();
+        }
+    }
+}
+
+/// The identity keys of the connected peers
+fn connected_peers(connection_list: &Arc<RwLock<ConnectionList>>, config: &Config) -> Vec<(String,SocketAddr,PublicKey)>
+{
+    let connection_list = connection_list.read().unwrap();
+    connection_list.get_names().into_iter().filter_map(|name| {
+        let address = *connection_list.get_address(&name)?;
+        let identity = connection_list.get_info_from_name(&name).and_then(|info| info.identity.clone())
+            .or_else(|| config.network.known_hosts.get(&name).map(|known_host| known_host.crypto_info().clone()))?;
+        Some((name, address, identity.public_key))
+    }).collect()
+}
+
+/// Accepts a membership list sent by the owner of the group, a list without us means we were removed
+fn receive_group_update(
+    name: &str,
+    signed_group_info: &SignedGroupInfo,
+    key: Option<String>,
+    connection_list: &Arc<RwLock<ConnectionList>>,
+    log: &Logger,
+    config: &Arc<RwLock<Config>>)
+{
+    let group_info = match signed_group_info.into_group_info()
+    {
+        Ok(group_info) => group_info,
+        Err(e) =>
+        {
+            log.log(MessageKind::Error, &format!("Invalid group from {}: {}", name, e)).unwrap();
+            return;
+        }
+    };
+    let mut config = config.write().unwrap();
+    let sender_key = connected_peers(connection_list, &config).into_iter()
+        .find(|(peer, _, _)| peer == name)
+        .map(|(_, _, public_key)| public_key);
+    let owner_changed = config.text.groups.get(&group_info.id).map(|group| group.info.owner != group_info.owner).unwrap_or(false);
+    if sender_key.as_ref() != Some(&group_info.owner) || owner_changed
+    {
+        log.audit(&format!("{} sent a membership list for the group {} it doesn't own", name, group_info.name));
+        return;
+    }
+    if let Some(group) = config.text.groups.get(&group_info.id)
+    {
+        if group.left || group_info.version <= group.info.version
+        {
+            return;
+        }
+    }
+    let local_key = config.network.private_key.public_key();
+    if group_info.member(&local_key).is_none()
+    {
+        if config.text.groups.remove(&group_info.id).is_some()
+        {
+            log.log(MessageKind::Event, &format!("{} removed you from the group {}", name, group_info.name)).unwrap();
+        }
+        return;
+    }
+    let key = match key.and_then(|key| SymmetricKey::from_str(&key).ok())
+    {
+        Some(key) => key,
+        None =>
+        {
+            log.log(MessageKind::Error, &format!("{} sent the group {} without a valid key", name, group_info.name)).unwrap();
+            return;
+        }
+    };
+    if !config.text.groups.contains_key(&group_info.id)
+    {
+        log.log(MessageKind::Event, &format!("{} added you to the group {}", name, group_info.name)).unwrap();
+    }
+    config.text.groups.insert(group_info.id.clone(), Group { info: group_info, key, removed: Vec::new(), left: false });
+}
+
+fn receive_group_text(
+    name: &str,
+    id: &str,
+    ciphertext: &Ciphertext,
+    sent: SystemTime,
+    from: SocketAddr,
+    clocks: &mut HashMap<String,u64>,
+    context: &TextContext)
+{
+    let TextContext { connection_list, log, config, .. } = context;
+    let (author, content) = {
+        let config = config.read().unwrap();
+        let group = match config.text.groups.get(id)
+        {
+            Some(group) => group,
+            None =>
+            {
+                log.log(MessageKind::Error, &format!("{} sent a message to an unknown group", name)).unwrap();
+                return;
+            }
+        };
+        // the author is authenticated by the session it sent the message on
+        if group.left
+        {
+            return;
+        }
+        let author = connected_peers(connection_list, &config).into_iter()
+            .find(|(peer, _, _)| peer == name)
+            .and_then(|(_, _, public_key)| group.info.member(&public_key).map(|member| member.name.clone()));
+        match author
+        {
+            Some(author) => (author, group.open(ciphertext)),
+            None =>
+            {
+                log.audit(&format!("{} sent a message to the group {} without being a member", name, group.info.name));
+                return;
+            }
+        }
+    };
+    match content
+    {
+        Ok(content) => receive_message(name, Some((id, &author)), content, sent, from, clocks, context),
+        Err(e) => log.log(MessageKind::Error, &format!("Error decrypting group message from {}: {}", name, e)).unwrap(),
+    }
+}
+
+/// Sends the text to the connected members, the ones that are offline miss it
+fn send_group_text(
+    id: &str,
+    content: Content,
+    sender_queue: &Sender<(Content,SocketAddr)>,
+    connection_list: &Arc<RwLock<ConnectionList>>,
+    log: &Logger,
+    config: &Arc<RwLock<Config>>) -> DeliveryState
+{
+    let recipients: Vec<(String,SocketAddr,Ciphertext)> = {
+        let config = config.read().unwrap();
+        let group = match config.text.groups.get(id)
+        {
+            Some(group) => group,
+            None => return DeliveryState::Failed,
+        };
+        let ciphertext = group.seal(&content);
+        connected_peers(connection_list, &config).into_iter()
+            .filter(|(_, _, public_key)| group.info.member(public_key).is_some())
+            .map(|(name, address, _)| (name, address, ciphertext.clone()))
+            .collect()
+    };
+    let mut state = DeliveryState::Failed;
+    for (name, address, ciphertext) in recipients
+    {
+        match seal(&name, Content::GroupText(id.to_string(), ciphertext), connection_list, config)
+        {
+            Ok(content) =>
+            {
+                sender_queue.send((content,address)).unwrap();
+                state = DeliveryState::Sent;
+            },
+            Err(e) => log.log(MessageKind::Error, &format!("Error encrypting message for {}: {}", name, e)).unwrap(),
+        }
+    }
+    state
+}
+
+/// Sends the membership list of the groups we own to the connected members that don't have the last one,
+/// the members removed since the last change are told too (without the key)
+fn announce_groups(
+    announced: &mut HashSet<(String,String,u64)>,
+    sender_queue: &Sender<(Content,SocketAddr)>,
+    connection_list: &Arc<RwLock<ConnectionList>>,
+    log: &Logger,
+    config: &Arc<RwLock<Config>>)
+{
+    let mut updates = Vec::new();
+    // group id and key of the removed members that were told
+    let mut told = Vec::new();
+    {
+        let config = config.read().unwrap();
+        let peers = connected_peers(connection_list, &config);
+        // a member that reconnects gets the list again
+        announced.retain(|(_, name, _)| peers.iter().any(|(peer, _, _)| peer == name));
+        let private_key = &config.network.private_key;
+        let local_key = private_key.public_key();
+        for group in config.text.groups.values().filter(|group| group.is_owned_by(&local_key))
+        {
+            let mut signed_group_info = None;
+            for (name, address, public_key) in &peers
+            {
+                let is_member = group.info.member(public_key).is_some();
+                let is_removed = group.removed.iter().any(|member| &member.public_key == public_key);
+                let entry = (group.info.id.clone(), name.clone(), group.info.version);
+                if (!is_member && !is_removed) || announced.contains(&entry)
+                {
+                    continue;
+                }
+                let signed_group_info = signed_group_info.get_or_insert_with(|| SignedGroupInfo::from_group_info(&group.info, private_key));
+                let key = is_member.then(|| group.key.to_string());
+                updates.push((name.clone(), *address, Content::GroupUpdate(signed_group_info.clone(), key)));
+                if is_removed
+                {
+                    told.push((group.info.id.clone(), public_key.clone()));
+                }
+                else
+                {
+                    announced.insert(entry);
+                }
+            }
+        }
+    }
+    // the config is only written when a removed member was told
+    if !told.is_empty()
+    {
+        let mut config = config.write().unwrap();
+        for (id, public_key) in told
+        {
+            if let Some(group) = config.text.groups.get_mut(&id)
+            {
+                group.removed.retain(|member| member.public_key != public_key);
+            }
+        }
+    }
+    for (name, address, content) in updates
+    {
+        match seal(&name, content, connection_list, config)
+        {
+            Ok(content) => sender_queue.send((content,address)).unwrap(),
+            Err(e) => log.log(MessageKind::Error, &format!("Error encrypting the group list for {}: {}", name, e)).unwrap(),
+        }
+    }
+}
+
+/// Queued messages of each contact, with the identity they are meant for
+type OutboxQueues = HashMap<String,(PublicKey,Vec<TextInfo>)>;
+
+fn load_outbox(text_list: &Arc<RwLock<TextList>>, log: &Logger, config: &Arc<RwLock<Config>>) -> OutboxQueues
+{
+    let config = config.read().unwrap();
+    let outbox = Outbox::from_config(&config);
+    let mut queues = OutboxQueues::new();
+    for (name, known_host) in &config.network.known_hosts
+    {
+        let public_key = &known_host.crypto_info().public_key;
+        match outbox.load(public_key)
+        {
+            Ok(texts) if !texts.is_empty() =>
+            {
+                text_list.write().unwrap().add_history(name, texts.clone());
+                queues.insert(name.clone(), (public_key.clone(), texts));
+            },
+            Ok(_) => {},
+            Err(e) => log.log(MessageKind::Error, &format!("Error loading the queued messages for {}: {}", name, e)).unwrap(),
+        }
+    }
+    queues
+}
+
+/// Keeps the message until a session with the identity of the contact is established.
+/// Contacts we never connected to have no identity to bind the message to
+fn queue_text(
+    name: &str,
+    mut info: TextInfo,
+    queues: &mut OutboxQueues,
+    connection_list: &Arc<RwLock<ConnectionList>>,
+    log: &Logger,
+    config: &Arc<RwLock<Config>>) -> DeliveryState
+{
+    let connection_identity = connection_identity(name, connection_list);
+    let config = config.read().unwrap();
+    let identity = connection_identity.or_else(|| config.network.known_hosts.get(name).map(|known_host| known_host.crypto_info().clone()));
+    let public_key = match identity
+    {
+        Some(identity) => identity.public_key,
+        None => return DeliveryState::Failed,
+    };
+    info.state = DeliveryState::Queued;
+    let (public_key, texts) = queues.entry(name.to_string()).or_insert((public_key, Vec::new()));
+    // a message sent from the queue is still there until it's acknowledged
+    match texts.iter_mut().find(|text| text.nonce == info.nonce)
+    {
+        Some(text) => *text = info,
+        None => texts.push(info),
+    }
+    if let Err(e) = Outbox::from_config(&config).store(public_key, texts)
+    {
+        log.log(MessageKind::Error, &format!("Error saving the queued messages for {}: {}", name, e)).unwrap();
+    }
+    DeliveryState::Queued
+}
+
+/// Keeps the edit or the deletion of a message the contact already has until it connects again,
+/// the message keeps its delivery state so it's told apart from the queued texts
+fn queue_change(
+    name: &str,
+    info: TextInfo,
+    queues: &mut OutboxQueues,
+    connection_list: &Arc<RwLock<ConnectionList>>,
+    log: &Logger,
+    config: &Arc<RwLock<Config>>)
+{
+    let connection_identity = connection_identity(name, connection_list);
+    let config = config.read().unwrap();
+    let identity = connection_identity.or_else(|| config.network.known_hosts.get(name).map(|known_host| known_host.crypto_info().clone()));
+    let public_key = match identity
+    {
+        Some(identity) => identity.public_key,
+        None => return,
+    };
+    let (public_key, texts) = queues.entry(name.to_string()).or_insert((public_key, Vec::new()));
+    match texts.iter_mut().find(|text| text.nonce == info.nonce)
+    {
+        Some(text) => *text = info,
+        None => texts.push(info),
+    }
+    if let Err(e) = Outbox::from_config(&config).store(public_key, texts)
+    {
+        log.log(MessageKind::Error, &format!("Error saving the queued messages for {}: {}", name, e)).unwrap();
+    }
+}
+
+/// Changes a queued message, a deleted one leaves the queue.
+/// Returns false if the message isn't queued or was already sent, then the change must be sent too
+fn update_queued(queues: &mut OutboxQueues, name: &str, info: TextInfo, log: &Logger, config: &Arc<RwLock<Config>>) -> bool
+{
+    let (public_key, texts) = match queues.get_mut(name)
+    {
+        Some(queue) => queue,
+        None => return false,
+    };
+    // queued changes are replaced when the new change is sent or queued
+    let position = match texts.iter().position(|text| text.nonce == info.nonce && text.state == DeliveryState::Queued)
+    {
+        Some(position) => position,
+        None => return false,
+    };
+    let sent = info.state != DeliveryState::Queued;
+    if info.deleted
+    {
+        texts.remove(position);
+    }
+    else
+    {
+        texts[position] = TextInfo { state: DeliveryState::Queued, ..info };
+    }
+    if let Err(e) = Outbox::from_config(&config.read().unwrap()).store(public_key, texts)
+    {
+        log.log(MessageKind::Error, &format!("Error saving the queued messages for {}: {}", name, e)).unwrap();
+    }
+    !sent
+}
+
+/// Removes an acknowledged message from the queue of the contact
+fn unqueue_text(queues: &mut OutboxQueues, name: &str, nonce: u64, log: &Logger, config: &Arc<RwLock<Config>>)
+{
+    if let Some((public_key, texts)) = queues.get_mut(name)
+    {
+        let count = texts.len();
+        texts.retain(|text| text.nonce != nonce);
+        if texts.len() != count
+        {
+            if let Err(e) = Outbox::from_config(&config.read().unwrap()).store(public_key, texts)
+            {
+                log.log(MessageKind::Error, &format!("Error saving the queued messages for {}: {}", name, e)).unwrap();
+            }
+            if texts.is_empty()
+            {
+                queues.remove(name);
+            }
+        }
+    }
+}
+
+/// Sends the queued messages and changes of the contacts connected with the identity they were queued for.
+/// They stay in the queue until they are acknowledged, so they survive a restart
+fn flush_outbox(
+    queues: &mut OutboxQueues,
+    pending_messages: &mut HashMap<(String,u64),Instant>,
+    pending_changes: &mut HashMap<(String,u64),Instant>,
+    context: &TextContext)
+{
+    let TextContext { text_list, connection_list, log, sender_queue, config, .. } = context;
+    // the texts are queued as such, the changes keep the state of the message they change
+    let is_sent = |pending_messages: &HashMap<(String,u64),Instant>, pending_changes: &HashMap<(String,u64),Instant>, name: &str, info: &TextInfo| {
+        let key = (name.to_string(), info.nonce);
+        match info.state
+        {
+            DeliveryState::Queued => pending_messages.contains_key(&key),
+            _ => pending_changes.contains_key(&key),
+        }
+    };
+    let ready: Vec<(String,SocketAddr)> = queues.iter().filter_map(|(name, (public_key, texts))| {
+        // the ones already sent are sent again until acknowledged
+        if texts.iter().all(|info| is_sent(pending_messages, pending_changes, name, info))
+        {
+            return None;
+        }
+        let address = connection_list.read().unwrap().get_address(name).copied()?;
+        let identity = connection_identity(name, connection_list)
+            .or_else(|| config.read().unwrap().network.known_hosts.get(name).map(|known_host| known_host.crypto_info().clone()))?;
+        (&identity.public_key == public_key).then(|| (name.clone(), address))
+    }).collect();
+    for (name, addr) in ready
+    {
+        let texts: Vec<TextInfo> = queues[&name].1.iter()
+            .filter(|info| !is_sent(pending_messages, pending_changes, &name, info))
+            .cloned()
+            .collect();
+        for info in texts
+        {
+            if info.state != DeliveryState::Queued
+            {
+                send_text_change(&name, info, pending_changes, queues, context);
+                continue;
+            }
+            if info.is_too_long()
+            {
+                log.log(MessageKind::Error, &format!("Cannot send a message over {}B to {}", defines::MAX_TEXT_LEN, name)).unwrap();
+                unqueue_text(queues, &name, info.nonce, log, config);
+                text_list.write().unwrap().update(&name, info.nonce, &TextDirection::Outgoing, |info| info.state = DeliveryState::Failed);
+                continue;
+            }
+            let state = match seal(&name, text_content(&info), connection_list, config)
+            {
+                Ok(content) =>
+                {
+                    sender_queue.send((content,addr)).unwrap();
+                    pending_messages.insert((name.clone(),info.nonce),Instant::now());
+                    DeliveryState::Sent
+                },
+                Err(e) =>
+                {
+                    log.log(MessageKind::Error, &format!("Error encrypting message for {}: {}", name, e)).unwrap();
+                    unqueue_text(queues, &name, info.nonce, log, config);
+                    DeliveryState::Failed
+                },
+            };
+            text_list.write().unwrap().update(&name, info.nonce, &TextDirection::Outgoing, |info| info.state = state);
+        }
+    }
+}
+
+/// Returns true if the timer of the contact changed, a change older than the last one is ignored
+fn set_timer(name: &str, timer: Option<DisappearingTimer>, changed: SystemTime, config: &Arc<RwLock<Config>>) -> bool
+{
+    let mut config = config.write().unwrap();
+    if config.text.disappearing_changed.get(name).is_some_and(|last| *last >= changed)
+    {
+        return false;
+    }
+    config.text.disappearing_changed.insert(name.to_string(), changed);
+    let old = match timer
+    {
+        Some(timer) => config.text.disappearing.insert(name.to_string(), timer),
+        None => config.text.disappearing.remove(name),
+    };
+    old != timer
+}
+
+/// Tells every contact that connects about the timer, so a change made while it was offline reaches it
+fn announce_timers(
+    announced: &mut HashSet<String>,
+    sender_queue: &Sender<(Content,SocketAddr)>,
+    connection_list: &Arc<RwLock<ConnectionList>>,
+    log: &Logger,
+    config: &Arc<RwLock<Config>>)
+{
+    let connected = connection_list.read().unwrap().get_names();
+    // a contact that reconnects is told again
+    announced.retain(|name| connected.contains(name));
+    let updates: Vec<(String,Content)> = {
+        let config = config.read().unwrap();
+        connected.into_iter()
+            .filter(|name| !announced.contains(name))
+            // contacts whose timer was never changed have nothing to agree on
+            .filter_map(|name| {
+                let changed = *config.text.disappearing_changed.get(&name)?;
+                let timer = config.text.disappearing.get(&name).copied();
+                Some((name, Content::DisappearingTimer(timer, changed)))
+            })
+            .collect()
+    };
+    for (name, content) in updates
+    {
+        send_change(&name, content, sender_queue, connection_list, log, config);
+        announced.insert(name);
+    }
+}
+
+/// Removes the messages older than the disappearing timer of their contact from the list, the history and the outbox
+fn expire_messages(queues: &mut OutboxQueues, text_list: &Arc<RwLock<TextList>>, log: &Logger, config: &Arc<RwLock<Config>>)
+{
+    let timers: Vec<(String,DisappearingTimer)> = config.read().unwrap().text.disappearing.iter()
+        .map(|(name, timer)| (name.clone(), *timer))
+        .collect();
+    for (name, timer) in timers
+    {
+        // the queued messages are in the list too
+        if text_list.write().unwrap().expire(&name, timer.max_age()) == 0
+        {
+            continue;
+        }
+        let config = config.read().unwrap();
+        let identity = match config.text.groups.contains_key(&name)
+        {
+            true => Some(History::group_identity(&name)),
+            false => config.network.known_hosts.get(&name).map(|known_host| History::identity(&known_host.crypto_info().public_key)),
+        };
+        if let Some(identity) = identity
+        {
+            if let Err(e) = History::from_config(&config).expire(&identity, timer.max_age())
+            {
+                log.log(MessageKind::Error, &format!("Error removing the expired messages of {}: {}", name, e)).unwrap();
+            }
+        }
+        if let Some((public_key, texts)) = queues.get_mut(&name)
+        {
+            let now = SystemTime::now();
+            texts.retain(|text| now.duration_since(text.local_time()).map(|age| age <= timer.max_age()).unwrap_or(true));
+            if let Err(e) = Outbox::from_config(&config).store(public_key, texts)
+            {
+                log.log(MessageKind::Error, &format!("Error saving the queued messages for {}: {}", name, e)).unwrap();
+            }
+        }
+    }
+}
+
+/// Lamport clock of the conversation, it starts from the last message in the history
+fn clock<'a>(clocks: &'a mut HashMap<String,u64>, name: &str, text_list: &Arc<RwLock<TextList>>) -> &'a mut u64
+{
+    clocks.entry(name.to_string()).or_insert_with(|| text_list.read().unwrap().last_lamport(name))
+}
+
+fn tick_clock(clocks: &mut HashMap<String,u64>, name: &str, text_list: &Arc<RwLock<TextList>>) -> u64
+{
+    let clock = clock(clocks, name, text_list);
+    *clock += 1;
+    *clock
+}
+
+/// Moves the clock past a received message, the message keeps the time of the sender
+fn observe_clock(clocks: &mut HashMap<String,u64>, name: &str, lamport: u64, text_list: &Arc<RwLock<TextList>>) -> u64
+{
+    let clock = clock(clocks, name, text_list);
+    *clock = (*clock).max(lamport);
+    lamport
+}
+
+fn load_history(text_list: &Arc<RwLock<TextList>>, log: &Logger, config: &Arc<RwLock<Config>>)
+{
+    let config = config.read().unwrap();
+    let history = History::from_config(&config);
+    let contacts = config.network.known_hosts.iter().map(|(name, known_host)| (name, History::identity(&known_host.crypto_info().public_key)));
+    let groups = config.text.groups.keys().map(|id| (id, History::group_identity(id)));
+    for (name, identity) in contacts.chain(groups)
+    {
+        match history.load(&identity, config.text.history_retention_for(name))
+        {
+            Ok((entries, unreadable)) =>
+            {
+                if unreadable > 0
+                {
+                    log.log(MessageKind::Error, &format!("Skipped {} unreadable lines in the history of {}", unreadable, name)).unwrap();
+                }
+                if !entries.is_empty()
+                {
+                    text_list.write().unwrap().add_history(name, entries.into_iter().map(|entry| entry.info).collect());
+                }
+            },
+            Err(e) => log.log(MessageKind::Error, &format!("Error loading the history of {}: {}", name, e)).unwrap(),
+        }
+    }
+}
+
+/// Wraps the content in the ratchet session of the peer, starting one if we know its prekey.
+/// Peers that never published a prekey, or whose keys belong to another suite than ours,
+/// get the content as it is, protected only by the session key.
+fn seal(name: &str, content: Content, connection_list: &Arc<RwLock<ConnectionList>>, config: &Arc<RwLock<Config>>) -> Result<Content,Box<dyn Error>>
+{
+    let connection_identity = connection_identity(name, connection_list);
+    let mut config = config.write().unwrap();
+    let config = &mut *config;
+    if !config.text.ratchet_sessions.contains_key(name)
+    {
+        let identity = connection_identity.or_else(|| config.network.known_hosts.get(name).map(|known_host| known_host.crypto_info().clone()));
+        if let Some(CryptoLastingInfo { public_key, prekey: Some(prekey) }) = identity
+            .filter(|identity| identity.prekey.as_ref().is_some_and(|prekey| 
+                RatchetSession::can_initiate(&config.network.private_key, &config.network.prekey, &identity.public_key, prekey)))
+        {
+            let session = RatchetSession::initiate(&config.network.private_key, &config.network.prekey, &public_key, &prekey)?;
+            config.text.ratchet_sessions.insert(name.to_string(), session);
+        }
+    }
+    let content = match config.text.ratchet_sessions.get_mut(name)
+    {
+        Some(session) => Content::Ratchet(session.encrypt(&content.serialize())?),
+        None => return Ok(content),
+    };
+    // a session that goes back to an older state after a crash can't be decrypted by the peer anymore
+    config.save()?;
+    Ok(content)
+}
+
+fn open_ratchet(name: &str, message: &RatchetMessage, connection_list: &Arc<RwLock<ConnectionList>>, config: &Arc<RwLock<Config>>) -> Result<Content,Box<dyn Error>>
+{
+    let connection_identity = connection_identity(name, connection_list);
+    let mut config = config.write().unwrap();
+    let config = &mut *config;
+    let remote_identity = match connection_identity
+    {
+        Some(identity) => identity,
+        None => config.network.known_hosts.get(name)
+            .ok_or("Unknown identity")?
+            .crypto_info().clone(),
+    };
+    let remote_identity_key = &remote_identity.public_key;
+    if let Some(session) = config.text.ratchet_sessions.get_mut(name)
+    {
+        match session.decrypt(message)
+        {
+            Ok(plaintext) =>
+            {
+                config.save()?;
+                return parse_ratchet_payload(&plaintext);
+            },
+            Err(e) =>
+            {
+                // the peer started a new session, if both peers started one at the same time
+                // the one with the greater identity key wins, a copy of the message that started
+                // the current session must not throw it away
+                let local_identity_key = config.network.private_key.public_key();
+                let replace = match &message.header.ephemeral_key
+                {
+                    Some(ephemeral_key) => !session.started_with(ephemeral_key) &&
+                        (!session.is_pending() || remote_identity_key.to_string() > local_identity_key.to_string()),
+                    None => false,
+                };
+                if !replace
+                {
+                    return Err(e);
+                }
+            }
+        }
+    }
+    let ephemeral_key = message.header.ephemeral_key.as_ref().ok_or("No ratchet session")?;
+    let mut session = RatchetSession::respond(&config.network.private_key, &config.network.prekey, remote_identity_key, remote_identity.prekey.as_ref(), ephemeral_key)?;
+    let plaintext = session.decrypt(message)?;
+    config.text.ratchet_sessions.insert(name.to_string(), session);
+    config.save()?;
+    parse_ratchet_payload(&plaintext)
+}
+
+/// Drops the session with the peer unless the message that could not be decrypted was a repeated one,
+/// returns true if the session has to be started again
+fn reset_ratchet(name: &str, message: Option<&RatchetMessage>, log: &Logger, config: &Arc<RwLock<Config>>) -> bool
+{
+    let mut config = config.write().unwrap();
+    let repeated = match (config.text.ratchet_sessions.get(name), message)
+    {
+        (Some(session), Some(message)) => session.is_repeated(&message.header),
+        _ => false,
+    };
+    if repeated
+    {
+        return false;
+    }
+    // a peer without a session can still ask us to drop ours
+    if config.text.ratchet_sessions.remove(name).is_none() && message.is_none()
+    {
+        return false;
+    }
+    if let Err(e) = config.save()
+    {
+        log.log(MessageKind::Error, &format!("Error saving the config: {}", e)).unwrap();
+    }
+    true
+}
+
+/// Content that must come in the ratchet session of the peer is refused outside of it, so the conversation can't be downgraded
+fn is_downgrade(name: &str, log: &Logger, config: &Arc<RwLock<Config>>) -> bool
+{
+    let downgrade = config.read().unwrap().text.ratchet_sessions.contains_key(name);
+    if downgrade
+    {
+        log.audit(&format!("{} sent a message outside of the secure session, it was dropped", name));
+    }
+    downgrade
+}
+
+/// The identity the peer used for the current connection, it may not be the one in the known hosts
+fn connection_identity(name: &str, connection_list: &Arc<RwLock<ConnectionList>>) -> Option<CryptoLastingInfo>
+{
+    connection_list.read().unwrap().get_info_from_name(name).and_then(|info| info.identity.clone())
+}
+
+fn parse_ratchet_payload(plaintext: &[u8]) -> Result<Content,Box<dyn Error>>
+{
+    let (content, len) = Content::deserialize(plaintext)?;
+    if len != plaintext.len()
+    {
+        Err("Ratchet payload length mismatch".into())
+    }
+    else
+    {
+        Ok(content)
+    }
+}
+
 fn gen_nonce() -> u64
 {
     rand::thread_rng().next_u64()
-}
\ No newline at end of file
+}
@@ -1,14 +1,16 @@
 use std::{thread::JoinHandle, sync::{mpsc::{Receiver, Sender}, Arc, RwLock}, net::SocketAddr};
 
-use crate::{config::Config, network::{Packet, threads::connection, Content, ConnectionList, ConnectionRequest}, log::Logger};
+use crate::{config::Config, network::{Packet, threads::connection, Content, ConnectionList, ConnectionRequest}, log::Logger, ui::UiNotification, hooks::HookEvent};
 
 pub fn start(
     running: Arc<RwLock<bool>>,
     connection_list: Arc<RwLock<ConnectionList>>,
     log: Logger,
     requests: Receiver<ConnectionRequest>,
+    ui_notifications: Sender<UiNotification>,
     connection_queue: Receiver<(Packet,SocketAddr)>,
     sender_queue: Sender<(Content,SocketAddr)>,
+    hook_events: Sender<HookEvent>,
     config: Arc<RwLock<Config>>
 ) -> Vec<JoinHandle<()>>
 {
@@ -19,8 +21,10 @@ pub fn start(
             connection_list, 
             log,
             requests,
+            ui_notifications,
             connection_queue,
             sender_queue,
+            hook_events,
             config)
     })
     {
@@ -34,28 +38,31 @@ mod tests
 {
     use core::panic;
 
-    use crate::{thread, network::ContactInfo, config::defines, crypto::{SignedContactInfo, CryptoConnectionInfo}};
+    use crate::{thread, network::ContactInfo, config::defines, crypto::{SignedContactInfo, CryptoConnectionInfo, CryptoHandshakeInfo, CipherSuite}};
     use super::*;
 
     #[test]
     fn incoming_connection()
     {
-        let context = thread::Context::new(None);
+        let context = thread::Context::temporary();
         let handles = start(
             context.unmovable.running.clone(),
             context.movable.connection_list.clone(),
             context.movable.log.clone(),
             context.movable.connection_requests_rx,
+            context.movable.ui_notifications_tx.clone(),
             context.movable.connection_queue_rx,
             context.movable.sender_queue_tx.clone(),
+            context.movable.hook_events_tx.clone(),
             context.unmovable.config.clone());
         assert_eq!(handles.len(),1);
         let remote_address = "0.0.0.0:4848".parse().unwrap();
         let remote_private_key = crate::crypto::PrivateKey::new();
-        let remote_ecdhe_private_key = crate::crypto::PrivateKey::new();
         let remote_crypto_info = CryptoConnectionInfo{
-            ecdhe_public_key: remote_ecdhe_private_key.public_key(),
+            ecdhe_keys: CryptoHandshakeInfo::offer(&CipherSuite::all()).public_keys(),
             public_key: remote_private_key.public_key(),
+            prekey: crate::crypto::PrivateKey::new().public_key(),
+            key_rotations: Vec::new(),
         };
         let remote_contact_info = SignedContactInfo::from_contact_info(ContactInfo::new("Test", &remote_crypto_info), &remote_private_key);
         context.movable.connection_queue_tx.send(
@@ -69,7 +76,8 @@ mod tests
         {
             if let Content::RequestConnection(info) = content
             {
-                let contact_info = info.into_contact_info(&remote_private_key.public_key()).unwrap();
+                let local_public_key = context.unmovable.config.read().unwrap().network.private_key.public_key();
+                let contact_info = info.into_contact_info(&local_public_key).unwrap();
                 assert_eq!(contact_info.name(),context.unmovable.config.read().unwrap().network.name);
                 assert_eq!(dst,remote_address);
             }
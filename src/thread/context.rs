@@ -1,6 +1,6 @@
 use std::{sync::{Arc, RwLock, mpsc::{Receiver, Sender}, Mutex}, net::SocketAddr};
 
-use crate::{network::{ConnectionList, Packet, Content, ConnectionRequest}, config::Config, text::{TextList, TextRequest}, log::{Logger, MessageKind}, voice::VoiceRequest, ui::UiNotification, file::FileRequest};
+use crate::{network::{ConnectionList, Packet, Content, ConnectionRequest, BandwidthStats}, config::{Config, defines, LockedConfig, StoredConfig}, text::{TextList, TextRequest}, log::{Logger, MessageKind}, voice::VoiceRequest, ui::UiNotification, file::FileRequest, hooks::HookEvent, plugins::{PluginList, PluginRequest}};
 
 pub struct Context
 {
@@ -12,6 +12,8 @@ pub struct MovableContext
 {
     pub connection_list: Arc<RwLock<ConnectionList>>,
     pub text_list: Arc<RwLock<TextList>>,
+    pub bandwidth_stats: Arc<RwLock<BandwidthStats>>,
+    pub plugin_list: Arc<RwLock<PluginList>>,
     pub log: Logger,
 
     pub connection_requests_rx: Receiver<ConnectionRequest>,
@@ -25,10 +27,18 @@ pub struct MovableContext
 
     pub voice_requests_rx: Receiver<VoiceRequest>,
     pub voice_requests_tx: Sender<VoiceRequest>,
+
+    pub plugin_requests_rx: Receiver<PluginRequest>,
+    pub plugin_requests_tx: Sender<PluginRequest>,
     /// changing this in a thread diffrerent from the voice thread will cause a lot of errors
     pub voice_interlocutor: Arc<Mutex<Option<SocketAddr>>>,
     pub ui_notifications_rx: Receiver<UiNotification>,
     pub ui_notifications_tx: Sender<UiNotification>,
+    pub hook_events_rx: Receiver<HookEvent>,
+    pub hook_events_tx: Sender<HookEvent>,
+    /// the events the hooks thread passes on to the plugins
+    pub plugin_events_rx: Receiver<HookEvent>,
+    pub plugin_events_tx: Sender<HookEvent>,
 
     pub connection_queue_rx: Receiver<(Packet,SocketAddr)>,
     pub text_queue_rx: Receiver<(Packet,SocketAddr)>,
@@ -47,43 +57,70 @@ pub struct UnmovableContext
 {
     pub running: Arc<RwLock<bool>>,
     pub config: Arc<RwLock<Config>>,
+    /// while this is set the config is a placeholder and the threads must not be started
+    pub locked_config: Arc<Mutex<Option<LockedConfig>>>,
 }
 
 impl Context
 {
     pub fn new(config_path: Option<&str>) -> Self
     { 
-        let log = Logger::new();
-        let config = match config_path
+        let log = match config_path
+        {
+            Some(path) => Logger::with_audit_trail(&Config::path_next_to(path, defines::AUDIT_TRAIL_PATH)),
+            None => Logger::new(),
+        };
+        let (config, locked_config) = match config_path
         {
             Some(path) =>
             {
-                match Config::from_file(path)
+                let (mut config, locked_config, save) = match StoredConfig::from_file(path)
                 {
-                    Ok(c) => c,
+                    Ok(StoredConfig::Unlocked(c)) => (c, None, true),
+                    Ok(StoredConfig::Locked(locked)) => (Config::default(), Some(locked), false),
                     Err(e) => {
                         log.log(
                             MessageKind::Error, 
                             &format!("Error occured while reading config file: {}",e))
                         .expect("The program is still singlethreaded, so this should never happen");
-                        Config::default()
+                        // a config that can't be read is not overwritten
+                        (Config::default(), None, !std::path::Path::new(path).exists())
+                    }
+                };
+                // the placeholder of a locked config keeps the path for the unlocked one
+                config.path = Some(path.to_string());
+                // the keys generated for the missing fields, like the history key, 
+                // must be on disk before anything is encrypted with them
+                if save
+                {
+                    if let Err(e) = config.save()
+                    {
+                        log.log(MessageKind::Error, &format!("Error saving config file: {}", e))
+                            .expect("The program is still singlethreaded, so this should never happen");
                     }
                 }
+                (config, locked_config)
             }
-            None => {Config::default()}
+            None => {(Config::default(), None)}
         };
         
         let config = std::sync::Arc::new(std::sync::RwLock::new(config));
+        let locked_config = Arc::new(Mutex::new(locked_config));
 
         let connection_list = Arc::new(RwLock::new(ConnectionList::new()));
         let text_list = Arc::new(RwLock::new(TextList::new()));
+        let bandwidth_stats = Arc::new(RwLock::new(BandwidthStats::new()));
+        let plugin_list = Arc::new(RwLock::new(PluginList::new()));
 
         let (connection_requests_tx, connection_requests_rx) = std::sync::mpsc::channel::<ConnectionRequest>();
         let (text_requests_tx, text_requests_rx) = std::sync::mpsc::channel::<TextRequest>();
         let (file_requests_tx, file_requests_rx) = std::sync::mpsc::channel::<FileRequest>();
         let (voice_requests_tx, voice_requests_rx) = std::sync::mpsc::channel::<VoiceRequest>();
+        let (plugin_requests_tx, plugin_requests_rx) = std::sync::mpsc::channel::<PluginRequest>();
         let voice_interlocutor = Arc::new(Mutex::new(None));
         let (ui_notifications_tx, ui_notifications_rx) = std::sync::mpsc::channel::<UiNotification>();
+        let (hook_events_tx, hook_events_rx) = std::sync::mpsc::channel::<HookEvent>();
+        let (plugin_events_tx, plugin_events_rx) = std::sync::mpsc::channel::<HookEvent>();
 
         let (text_queue_tx, text_queue_rx) = std::sync::mpsc::channel::<(Packet,SocketAddr)>();
         let (file_queue_tx, file_queue_rx) = std::sync::mpsc::channel::<(Packet,SocketAddr)>();
@@ -97,6 +134,8 @@ impl Context
             {
                 connection_list,
                 text_list,
+                bandwidth_stats,
+                plugin_list,
                 log,
                 
                 connection_requests_rx,
@@ -107,9 +146,15 @@ impl Context
                 file_requests_tx,
                 voice_requests_rx,
                 voice_requests_tx,
+                plugin_requests_rx,
+                plugin_requests_tx,
                 voice_interlocutor,
                 ui_notifications_rx,
                 ui_notifications_tx,
+                hook_events_rx,
+                hook_events_tx,
+                plugin_events_rx,
+                plugin_events_tx,
 
                 connection_queue_rx,
                 text_queue_rx,
@@ -126,11 +171,26 @@ impl Context
             {
                 running,
                 config,
+                locked_config,
             }
         }
     }
 }
 
+#[cfg(test)]
+impl Context
+{
+    /// A context without a config file whose history and outbox are kept in a new temporary folder
+    pub fn temporary() -> Self
+    {
+        let context = Self::new(None);
+        let folder = std::env::temp_dir().join(format!("mokaccino_context_{:x}", rand::random::<u64>()));
+        std::fs::create_dir_all(&folder).unwrap();
+        context.unmovable.config.write().unwrap().path = Some(folder.join(defines::CONFIG_PATH).to_string_lossy().to_string());
+        context
+    }
+}
+
 impl UnmovableContext
 {
     pub fn stop(&self)
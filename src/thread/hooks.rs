@@ -0,0 +1,28 @@
+use std::{thread::JoinHandle, sync::{mpsc::{Receiver, Sender}, Arc, RwLock}};
+
+use crate::{hooks::{threads::hooks, HookEvent}, config::Config, log::Logger, text::TextRequest};
+
+pub fn start(
+    running: Arc<RwLock<bool>>,
+    log: Logger,
+    hook_events: Receiver<HookEvent>,
+    plugin_events: Sender<HookEvent>,
+    text_requests: Sender<TextRequest>,
+    config: Arc<RwLock<Config>>
+) -> Vec<JoinHandle<()>>
+{
+    let builder = std::thread::Builder::new().name("Hooks".to_string());
+    match builder.spawn(move || {
+        hooks::run(
+            running,
+            log,
+            hook_events,
+            plugin_events,
+            text_requests,
+            config)
+    })
+    {
+        Ok(handle) => vec![handle],
+        Err(e) => panic!("Error creating thread Hooks: {e}")
+    }
+}
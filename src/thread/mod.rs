@@ -4,6 +4,8 @@ pub mod text;
 pub mod voice;
 pub mod context;
 pub mod file;
+pub mod hooks;
+pub mod plugins;
 pub mod threads;
 pub mod supervisor;
 
@@ -1,6 +1,6 @@
 use std::{thread::JoinHandle, sync::{Arc, mpsc::{Sender, Receiver}, RwLock}, net::SocketAddr};
 
-use crate::{network::{socket, threads::listener, threads::sender, Packet, Content, ConnectionList}, config::Config, log::Logger};
+use crate::{network::{socket, threads::listener, threads::sender, Packet, Content, ConnectionList, BandwidthStats}, config::Config, log::Logger};
 
 pub fn start(
     running: Arc<RwLock<bool>>,
@@ -15,6 +15,7 @@ pub fn start(
 
     sender_queue: Receiver<(Content,SocketAddr)>,
 
+    bandwidth_stats: Arc<RwLock<BandwidthStats>>,
     config: Arc<RwLock<Config>>
 ) -> Vec<JoinHandle<()>>
 {
@@ -39,6 +40,8 @@ pub fn start(
     let sender_running = running.clone();
     let listener_log = log.clone();
     let sender_log = log.clone();
+    let listener_bandwidth_stats = bandwidth_stats.clone();
+    let sender_bandwidth_stats = bandwidth_stats.clone();
     let listener = match listener_builder.spawn(move || {
         listener::run(
             listener_running, 
@@ -49,6 +52,7 @@ pub fn start(
             file_queue,
             connection_queue, 
             voice_queue, 
+            listener_bandwidth_stats,
             listener_config)
     })
     {
@@ -62,6 +66,7 @@ pub fn start(
             sender_connection_list, 
             sender_log,
             sender_queue, 
+            sender_bandwidth_stats,
             sender_config)
     })
     {
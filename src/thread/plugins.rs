@@ -0,0 +1,30 @@
+use std::{thread::JoinHandle, sync::{mpsc::{Receiver, Sender}, Arc, RwLock}};
+
+use crate::{plugins::{threads::plugins, PluginList, PluginRequest}, hooks::HookEvent, config::Config, log::Logger, text::TextRequest};
+
+pub fn start(
+    running: Arc<RwLock<bool>>,
+    log: Logger,
+    requests: Receiver<PluginRequest>,
+    plugin_events: Receiver<HookEvent>,
+    text_requests: Sender<TextRequest>,
+    plugin_list: Arc<RwLock<PluginList>>,
+    config: Arc<RwLock<Config>>
+) -> Vec<JoinHandle<()>>
+{
+    let builder = std::thread::Builder::new().name("Plugins".to_string());
+    match builder.spawn(move || {
+        plugins::run(
+            running,
+            log,
+            requests,
+            plugin_events,
+            text_requests,
+            plugin_list,
+            config)
+    })
+    {
+        Ok(handle) => vec![handle],
+        Err(e) => panic!("Error creating thread Plugins: {e}")
+    }
+}
@@ -1,6 +1,6 @@
 use std::{thread::JoinHandle, sync::{mpsc::{Receiver, Sender}, Arc, RwLock}, net::SocketAddr};
 
-use crate::{network::{Packet, ConnectionList, Content}, config::Config, text::{threads::text, TextList, TextRequest}, log::Logger};
+use crate::{network::{Packet, ConnectionList, Content}, config::Config, text::{threads::text, TextList, TextRequest}, log::Logger, hooks::HookEvent};
 
 pub fn start(
     running: Arc<RwLock<bool>>,
@@ -10,6 +10,7 @@ pub fn start(
     requests: Receiver<TextRequest>,
     text_queue: Receiver<(Packet,SocketAddr)>,
     sender_queue: Sender<(Content,SocketAddr)>,
+    hook_events: Sender<HookEvent>,
     config: Arc<RwLock<Config>>
 ) -> Vec<JoinHandle<()>>
 {
@@ -22,6 +23,7 @@ pub fn start(
             requests,
             text_queue, 
             sender_queue,
+            hook_events,
             config)
     })
     {
@@ -33,15 +35,18 @@ pub fn start(
 #[cfg(test)]
 mod tests {
     use crate::config::defines;
-    use crate::crypto::SymmetricKey;
+    use crate::crypto::{SymmetricKey, RatchetSession, CryptoLastingInfo};
+    use crate::network::LastingContactInfo;
+    use crate::text::DeliveryState;
     use crate::thread::Context;
+    use serializable::Serializable;
 
     use super::*;
     use std::thread;
 
     #[test]
     fn send_text() {
-        let context = Context::new(None);
+        let context = Context::temporary();
 
         let handles = start(
             context.unmovable.running.clone(),
@@ -51,14 +56,16 @@ mod tests {
             context.movable.text_requests_rx,
             context.movable.text_queue_rx,
             context.movable.sender_queue_tx.clone(),
+            context.movable.hook_events_tx.clone(),
             context.unmovable.config.clone(),
         );
 
         // Send a text request
-        let request = TextRequest{
-            text: "TestText".to_string(),
-            dst: "TEST".to_string(),
-        };
+        let request = TextRequest::Send(
+            "TEST".to_string(),
+            "TestText".to_string(),
+            None,
+        );
         let symmetric_key = SymmetricKey::random();
         context.movable.connection_list.write().unwrap().add("TEST", "127.0.0.1:4848".parse().unwrap(), symmetric_key);
         context.movable.text_requests_tx.send(request).unwrap();
@@ -68,7 +75,7 @@ mod tests {
 
         // Check the sender queue for the packet
         if let Ok((content,dst)) = context.movable.sender_queue_rx.recv_timeout(defines::THREAD_QUEUE_TIMEOUT) {
-            if let Content::Text(text,_nonce) = content
+            if let Content::Text(text,_nonce,_lamport) = content
             {
                 assert_eq!(text, "TestText".to_string());
                 assert_eq!(dst, "127.0.0.1:4848".parse().unwrap());
@@ -81,6 +88,18 @@ mod tests {
             panic!("No packet was sent to the sender queue");
         }        
 
+        // a message that doesn't fit in a packet is not sent
+        let request = TextRequest::Send(
+            "TEST".to_string(),
+            "a".repeat(defines::MAX_TEXT_LEN + 1),
+            None,
+        );
+        context.movable.text_requests_tx.send(request).unwrap();
+        thread::sleep(2*defines::THREAD_QUEUE_TIMEOUT);
+        assert!(context.movable.sender_queue_rx.try_iter().all(|(content,_dst)| !matches!(content, Content::Text(text,_,_) if text.len() > defines::MAX_TEXT_LEN)));
+        let state = context.movable.text_list.read().unwrap().messages("TEST").unwrap().back().unwrap().state;
+        assert_eq!(state, DeliveryState::Failed);
+
         // Stop the server
         context.unmovable.stop();
 
@@ -89,4 +108,140 @@ mod tests {
             handle.join().unwrap();
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn send_ratchet_text() {
+        let context = Context::temporary();
+
+        let handles = start(
+            context.unmovable.running.clone(),
+            context.movable.text_list.clone(),
+            context.movable.connection_list.clone(),
+            context.movable.log.clone(),
+            context.movable.text_requests_rx,
+            context.movable.text_queue_rx,
+            context.movable.sender_queue_tx.clone(),
+            context.movable.hook_events_tx.clone(),
+            context.unmovable.config.clone(),
+        );
+
+        // The remote peer published its prekey during the handshake, its keys use the same suite as ours
+        let cipher_suite = context.unmovable.config.read().unwrap().network.private_key.cipher_suite();
+        let remote_private_key = cipher_suite.generate_identity_key();
+        let remote_prekey = cipher_suite.generate_exchange_key();
+        context.unmovable.config.write().unwrap().network.known_hosts.insert("TEST".to_string(), LastingContactInfo::new(
+            "TEST",
+            &CryptoLastingInfo::with_prekey(&remote_private_key.public_key(), &remote_prekey.public_key())));
+        let local_public_key = context.unmovable.config.read().unwrap().network.private_key.public_key();
+        let local_prekey = context.unmovable.config.read().unwrap().network.prekey.public_key();
+
+        let request = TextRequest::Send(
+            "TEST".to_string(),
+            "TestText".to_string(),
+            None,
+        );
+        let symmetric_key = SymmetricKey::random();
+        context.movable.connection_list.write().unwrap().add("TEST", "127.0.0.1:4848".parse().unwrap(), symmetric_key);
+        context.movable.text_requests_tx.send(request).unwrap();
+
+        thread::sleep(2*defines::THREAD_QUEUE_TIMEOUT);
+
+        if let Ok((content,_dst)) = context.movable.sender_queue_rx.recv_timeout(defines::THREAD_QUEUE_TIMEOUT) {
+            if let Content::Ratchet(message) = content
+            {
+                let ephemeral_key = message.header.ephemeral_key.clone().expect("First message must carry the handshake");
+                let mut session = RatchetSession::respond(&remote_private_key, &remote_prekey, &local_public_key, Some(&local_prekey), &ephemeral_key).unwrap();
+                let plaintext = session.decrypt(&message).unwrap();
+                let (inner, _len) = <Content as Serializable>::deserialize(&plaintext).unwrap();
+                assert!(matches!(inner, Content::Text(text,_nonce,_lamport) if text == "TestText"));
+            }
+            else {
+                panic!("Wrong content type");
+            }
+        }
+        else {
+            panic!("No packet was sent to the sender queue");
+        }
+
+        // now that the session exists a text outside of it is a downgrade and is dropped
+        let plain = Content::Text("PlainText".to_string(), 1, 1);
+        context.movable.text_queue_tx.send((Packet::from_content_now(plain), "127.0.0.1:4848".parse().unwrap())).unwrap();
+        thread::sleep(2*defines::THREAD_QUEUE_TIMEOUT);
+        assert!(context.movable.sender_queue_rx.try_iter().all(|(content,_dst)| !matches!(content, Content::AcknowledgeText(1))));
+
+        context.unmovable.stop();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn read_receipts() {
+        let context = Context::temporary();
+
+        let handles = start(
+            context.unmovable.running.clone(),
+            context.movable.text_list.clone(),
+            context.movable.connection_list.clone(),
+            context.movable.log.clone(),
+            context.movable.text_requests_rx,
+            context.movable.text_queue_rx,
+            context.movable.sender_queue_tx.clone(),
+            context.movable.hook_events_tx.clone(),
+            context.unmovable.config.clone(),
+        );
+        let address: SocketAddr = "127.0.0.1:4848".parse().unwrap();
+        context.movable.connection_list.write().unwrap().add("TEST", address, SymmetricKey::random());
+        let read_receipt = |nonce: u64| {
+            // the messages shown in the chat are marked as read, the receipts go out with the next announce
+            context.movable.text_list.write().unwrap().get("TEST");
+            thread::sleep(defines::ANNOUNCE_INTERVAL + 2*defines::THREAD_QUEUE_TIMEOUT);
+            context.movable.sender_queue_rx.try_iter().any(|(content,_dst)| content == Content::ReadReceipt(vec![nonce]))
+        };
+
+        context.movable.text_queue_tx.send((Packet::from_content_now(Content::Text("Read".to_string(), 1, 1)), address)).unwrap();
+        thread::sleep(2*defines::THREAD_QUEUE_TIMEOUT);
+        assert!(read_receipt(1));
+
+        context.unmovable.config.write().unwrap().text.read_receipts = false;
+        context.movable.text_queue_tx.send((Packet::from_content_now(Content::Text("Unread".to_string(), 2, 2)), address)).unwrap();
+        thread::sleep(2*defines::THREAD_QUEUE_TIMEOUT);
+        assert!(!read_receipt(2));
+
+        context.unmovable.stop();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn typing() {
+        let context = Context::temporary();
+
+        let handles = start(
+            context.unmovable.running.clone(),
+            context.movable.text_list.clone(),
+            context.movable.connection_list.clone(),
+            context.movable.log.clone(),
+            context.movable.text_requests_rx,
+            context.movable.text_queue_rx,
+            context.movable.sender_queue_tx.clone(),
+            context.movable.hook_events_tx.clone(),
+            context.unmovable.config.clone(),
+        );
+        let address: SocketAddr = "127.0.0.1:4848".parse().unwrap();
+        context.movable.connection_list.write().unwrap().add("TEST", address, SymmetricKey::random());
+
+        // the peer is shown as typing until it stops for a while
+        context.movable.text_queue_tx.send((Packet::from_content_now(Content::Typing), address)).unwrap();
+        thread::sleep(2*defines::THREAD_QUEUE_TIMEOUT);
+        assert_eq!(context.movable.text_list.read().unwrap().typing("TEST"), vec!["TEST".to_string()]);
+        thread::sleep(defines::TYPING_TIMEOUT);
+        assert!(context.movable.text_list.read().unwrap().typing("TEST").is_empty());
+
+        context.unmovable.stop();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    }
+}
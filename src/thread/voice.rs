@@ -1,6 +1,6 @@
 use std::{thread::JoinHandle, sync::{mpsc::{Receiver, Sender}, Arc, RwLock, Mutex}, net::SocketAddr};
 
-use crate::{voice::{threads::voice, VoiceRequest}, network::{Packet, Content, ConnectionList}, config::Config, log::Logger, ui::ui_notification::UiNotification};
+use crate::{voice::{threads::voice, VoiceRequest}, network::{Packet, Content, ConnectionList}, config::Config, log::Logger, ui::ui_notification::UiNotification, hooks::HookEvent};
 
 pub fn start(
     running: Arc<RwLock<bool>>,
@@ -11,6 +11,7 @@ pub fn start(
     ui_notifications: Sender<UiNotification>,
     voice_queue: Receiver<(Packet,SocketAddr)>, 
     sender_queue: Sender<(Content,SocketAddr)>,
+    hook_events: Sender<HookEvent>,
     config: Arc<RwLock<Config>>) -> Vec<JoinHandle<()>>
 {
     let builder = std::thread::Builder::new().name("Voice".to_string());
@@ -24,6 +25,7 @@ pub fn start(
             ui_notifications,
             voice_queue, 
             sender_queue, 
+            hook_events,
             config)
     })
     {
@@ -0,0 +1,245 @@
+use eframe::{egui::{Ui, Response, Label, RichText, Frame, Margin}, epaint::Rounding};
+
+use super::{MarkdownBlock, MarkdownSpan};
+
+/// The subset of Markdown shown in the chat: bold, italics, inline code, fenced code blocks and links
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Markdown
+{
+    pub blocks: Vec<MarkdownBlock>,
+}
+
+impl Markdown
+{
+    pub fn parse(text: &str) -> Self
+    {
+        let mut blocks = Vec::new();
+        let mut code: Option<Vec<&str>> = None;
+        for line in text.split('\n')
+        {
+            let is_fence = line.trim_start().starts_with("```");
+            match (&mut code, is_fence)
+            {
+                (Some(lines), false) => lines.push(line),
+                (Some(lines), true) =>
+                {
+                    blocks.push(MarkdownBlock::Code(lines.join("\n")));
+                    code = None;
+                },
+                // the language after the fence is not used
+                (None, true) => code = Some(Vec::new()),
+                (None, false) => blocks.push(MarkdownBlock::Line(Self::parse_line(line))),
+            }
+        }
+        // an unclosed block goes on until the end of the message
+        if let Some(lines) = code
+        {
+            blocks.push(MarkdownBlock::Code(lines.join("\n")));
+        }
+        Self { blocks }
+    }
+
+    fn parse_line(line: &str) -> Vec<MarkdownSpan>
+    {
+        let chars: Vec<char> = line.chars().collect();
+        let mut spans = Vec::new();
+        let mut current = MarkdownSpan::default();
+        // the marker that opened the italic text
+        let mut italic: Option<char> = None;
+        let flush = |spans: &mut Vec<MarkdownSpan>, current: &mut MarkdownSpan|
+        {
+            if !current.text.is_empty()
+            {
+                let text = std::mem::take(&mut current.text);
+                spans.push(MarkdownSpan { text, ..current.clone() });
+            }
+        };
+        // a marker only opens if it's closed later on, the last ones tell it without scanning the rest of the line
+        let last = |marker: char| chars.iter().rposition(|c| *c == marker);
+        let (last_code, last_star, last_underscore) = (last('`'), last('*'), last('_'));
+        let last_bold = chars.windows(2).rposition(|pair| pair == ['*', '*']);
+        let closed_after = |last: Option<usize>, index: usize| last.is_some_and(|last| last >= index);
+        let mut i = 0;
+        while i < chars.len()
+        {
+            let c = chars[i];
+            let is_bold = chars[i..].starts_with(&['*', '*']);
+            let is_word = |index: Option<usize>| index.and_then(|index| chars.get(index)).is_some_and(|c| c.is_alphanumeric());
+            if c == '\\' && chars.get(i+1).is_some_and(|next| "\\`*_[]()".contains(*next))
+            {
+                current.text.push(chars[i+1]);
+                i += 2;
+            }
+            else if c == '`' && closed_after(last_code, i+1)
+            {
+                flush(&mut spans, &mut current);
+                let end = i + 1 + chars[i+1..].iter().position(|c| *c == '`').unwrap();
+                spans.push(MarkdownSpan {
+                    text: chars[i+1..end].iter().collect(),
+                    code: true,
+                    ..current.clone()
+                });
+                i = end + 1;
+            }
+            else if is_bold && (current.bold || closed_after(last_bold, i+2))
+            {
+                flush(&mut spans, &mut current);
+                current.bold = !current.bold;
+                i += 2;
+            }
+            else if c == '*' && (italic == Some('*') || (italic.is_none() && chars.get(i+1).is_some_and(|next| !next.is_whitespace()) && closed_after(last_star, i+1)))
+            {
+                flush(&mut spans, &mut current);
+                italic = if italic.is_some() {None} else {Some('*')};
+                current.italic = italic.is_some();
+                i += 1;
+            }
+            // underscores inside words (like snake_case) are not markers
+            else if c == '_' && italic == Some('_') && !is_word(Some(i+1))
+            {
+                flush(&mut spans, &mut current);
+                italic = None;
+                current.italic = false;
+                i += 1;
+            }
+            else if c == '_' && italic.is_none() && !is_word(i.checked_sub(1)) && closed_after(last_underscore, i+1)
+            {
+                flush(&mut spans, &mut current);
+                italic = Some('_');
+                current.italic = true;
+                i += 1;
+            }
+            else if let Some((label, url, len)) = Self::parse_link(&chars[i..]).filter(|_| c == '[' || !is_word(i.checked_sub(1)))
+            {
+                flush(&mut spans, &mut current);
+                spans.push(MarkdownSpan {
+                    text: label,
+                    link: Some(url),
+                    ..current.clone()
+                });
+                i += len;
+            }
+            else
+            {
+                current.text.push(c);
+                i += 1;
+            }
+        }
+        flush(&mut spans, &mut current);
+        spans
+    }
+
+    /// Label, url and length in chars of a link at the start of the text,
+    /// only web links are accepted
+    fn parse_link(text: &[char]) -> Option<(String, String, usize)>
+    {
+        let is_url = |url: &[char]| url.starts_with(&['h', 't', 't', 'p', ':', '/', '/']) || url.starts_with(&['h', 't', 't', 'p', 's', ':', '/', '/']);
+        if text.first() == Some(&'[')
+        {
+            let label_end = text.windows(2).position(|pair| pair == [']', '('])?;
+            let url_end = label_end + text[label_end..].iter().position(|c| *c == ')')?;
+            let label = &text[1..label_end];
+            let url = &text[label_end+2..url_end];
+            if is_url(url) && !url.iter().any(|c| c.is_whitespace())
+            {
+                return Some((label.iter().collect(), url.iter().collect(), url_end + 1));
+            }
+            None
+        }
+        else if is_url(text)
+        {
+            let end = text.iter().position(|c| c.is_whitespace()).unwrap_or(text.len());
+            // punctuation at the end belongs to the sentence
+            let url: String = text[..end].iter().collect();
+            let url = url.trim_end_matches(|c| ".,;:!?)".contains(c));
+            Some((url.to_string(), url.to_string(), url.chars().count()))
+        }
+        else
+        {
+            None
+        }
+    }
+
+    pub fn show(&self, ui: &mut Ui) -> Response
+    {
+        ui.vertical(|ui|{
+            for block in &self.blocks
+            {
+                match block
+                {
+                    MarkdownBlock::Line(spans) =>
+                    {
+                        ui.horizontal_wrapped(|ui|{
+                            ui.spacing_mut().item_spacing.x = 0.0;
+                            if spans.is_empty()
+                            {
+                                ui.label("");
+                            }
+                            for span in spans
+                            {
+                                match &span.link
+                                {
+                                    Some(url) => ui.hyperlink_to(span.rich_text(), url),
+                                    None => ui.add(Label::new(span.rich_text()).wrap(true)),
+                                };
+                            }
+                        });
+                    },
+                    MarkdownBlock::Code(code) =>
+                    {
+                        Frame::none()
+                        .fill(ui.visuals().extreme_bg_color)
+                        .rounding(Rounding::same(3.0))
+                        .inner_margin(Margin::same(4.0))
+                        .show(ui, |ui|{
+                            ui.add(Label::new(RichText::new(code).monospace()).wrap(true));
+                        });
+                    },
+                }
+            }
+        }).response
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn span(text: &str) -> MarkdownSpan
+    {
+        MarkdownSpan { text: text.to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn parse()
+    {
+        let markdown = Markdown::parse("**bold** and *it* `a*b` snake_case [site](https://example.com)\n```rust\nfn main() {}\n```\nsee https://example.com.");
+        assert_eq!(markdown.blocks, vec![
+            MarkdownBlock::Line(vec![
+                MarkdownSpan { bold: true, ..span("bold") },
+                span(" and "),
+                MarkdownSpan { italic: true, ..span("it") },
+                span(" "),
+                MarkdownSpan { code: true, ..span("a*b") },
+                span(" snake_case "),
+                MarkdownSpan { link: Some("https://example.com".to_string()), ..span("site") },
+            ]),
+            MarkdownBlock::Code("fn main() {}".to_string()),
+            MarkdownBlock::Line(vec![
+                span("see "),
+                MarkdownSpan { link: Some("https://example.com".to_string()), ..span("https://example.com") },
+                span("."),
+            ]),
+        ]);
+        // unmatched markers and other schemes are left as they are
+        assert_eq!(Markdown::parse("2 * 3 [x](javascript:alert)").blocks,
+            vec![MarkdownBlock::Line(vec![span("2 * 3 [x](javascript:alert)")])]);
+        assert_eq!(Markdown::parse("é [ü](https://example.com)").blocks, vec![MarkdownBlock::Line(vec![
+            span("é "),
+            MarkdownSpan { link: Some("https://example.com".to_string()), ..span("ü") },
+        ])]);
+        // a long line with many markers is parsed in one pass
+        assert_eq!(Markdown::parse(&"a * b _ c ".repeat(50_000)).blocks.len(), 1);
+    }
+}
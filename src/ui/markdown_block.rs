@@ -0,0 +1,9 @@
+use super::MarkdownSpan;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarkdownBlock
+{
+    Line(Vec<MarkdownSpan>),
+    /// content of a fenced code block, shown in monospace as it is
+    Code(String),
+}
@@ -0,0 +1,34 @@
+use eframe::egui::RichText;
+
+/// A piece of a line with the same style
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MarkdownSpan
+{
+    pub text: String,
+    pub bold: bool,
+    pub italic: bool,
+    pub code: bool,
+    /// the span is a link to this url
+    pub link: Option<String>,
+}
+
+impl MarkdownSpan
+{
+    pub fn rich_text(&self) -> RichText
+    {
+        let mut text = RichText::new(&self.text);
+        if self.bold
+        {
+            text = text.strong();
+        }
+        if self.italic
+        {
+            text = text.italics();
+        }
+        if self.code
+        {
+            text = text.code();
+        }
+        text
+    }
+}
@@ -2,7 +2,14 @@ pub mod ui;
 pub mod load_icon;
 pub mod load_image;
 pub mod ui_notification;
+pub mod qr_image;
+pub mod markdown;
+pub mod markdown_block;
+pub mod markdown_span;
 
 pub use ui::UI;
 pub use ui::run;
-pub use ui_notification::UiNotification;
\ No newline at end of file
+pub use ui_notification::UiNotification;
+pub use markdown::Markdown;
+pub use markdown_block::MarkdownBlock;
+pub use markdown_span::MarkdownSpan;
\ No newline at end of file
@@ -0,0 +1,30 @@
+use eframe::egui::ColorImage;
+use image::{GrayImage, Luma, DynamicImage};
+use qrcode::{QrCode, Color};
+
+const QR_BORDER: u32 = 4;
+
+pub fn qr_image(data: &str, scale: u32) -> Option<ColorImage>
+{
+    let code = QrCode::new(data.as_bytes()).ok()?;
+    let width = code.width() as u32;
+    let colors = code.to_colors();
+    let size = (width + 2*QR_BORDER) * scale;
+    let image = GrayImage::from_fn(size, size, |x, y| {
+        let (module_x, module_y) = (x / scale, y / scale);
+        if module_x < QR_BORDER || module_y < QR_BORDER || module_x >= width + QR_BORDER || module_y >= width + QR_BORDER
+        {
+            Luma([255])
+        }
+        else
+        {
+            match colors[((module_y - QR_BORDER) * width + (module_x - QR_BORDER)) as usize]
+            {
+                Color::Dark => Luma([0]),
+                Color::Light => Luma([255]),
+            }
+        }
+    });
+    let image = DynamicImage::ImageLuma8(image).into_rgba8();
+    Some(ColorImage::from_rgba_unmultiplied([size as usize, size as usize], image.as_raw().as_slice()))
+}
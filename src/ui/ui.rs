@@ -1,14 +1,14 @@
-use std::{sync::{Arc, RwLock, mpsc::{Sender, Receiver}, Mutex}, net::{SocketAddr, ToSocketAddrs}};
+use std::{sync::{Arc, RwLock, mpsc::{Sender, Receiver}, Mutex}, net::{SocketAddr, ToSocketAddrs}, str::FromStr, collections::{HashMap, HashSet}};
 
 use chrono::{Local, DateTime};
 use cpal::traits::{HostTrait, DeviceTrait};
-use eframe::{egui::{self, Margin, Frame, Label, ScrollArea, Button, TextEdit, CentralPanel, Key, Ui, Slider, Style, Visuals, style::Selection, ComboBox, TextureOptions, ImageButton, Layout, load::SizedTexture, Image}, epaint::{Vec2, Rounding, Stroke, TextureHandle, Color32}, NativeOptions, emath::{Align2, Align}, CreationContext};
+use eframe::{egui::{self, Margin, Frame, Label, RichText, Sense, ScrollArea, Button, TextEdit, CentralPanel, Key, Modifiers, Ui, Slider, Style, Visuals, style::Selection, ComboBox, TextureOptions, ImageButton, Layout, load::SizedTexture, Image, DragValue}, epaint::{Vec2, Rounding, Stroke, TextureHandle, Color32}, NativeOptions, emath::{Align2, Align}, CreationContext};
 
-use crate::{network::{ConnectionList, ConnectionRequest}, text::{TextList, TextRequest, TextDirection}, thread::context::UnmovableContext, log::{Logger, MessageKind}, config::defines, voice::VoiceRequest};
+use crate::{network::{ConnectionList, ConnectionRequest, KeyChangeDecision, Invite, UserInfo, BandwidthStats}, text::{TextList, TextRequest, TextDirection, TextInfo, History, DeliveryState, Group, GroupMember, ExportFormat, ExportEntry, ChatExport, DisappearingTimer}, thread::context::UnmovableContext, log::{Logger, MessageKind}, config::{defines, ProtectionMode, ConfigProtection, AclRule, AclPattern, AclAction, PaddingPolicy, HistoryRetention}, voice::VoiceRequest, plugins::{PluginList, PluginRequest}, crypto::{SafetyNumber, PublicKey, PrivateKey, CipherSuite, SymmetricKey}};
 
 use crate::load_image;
 
-use super::UiNotification;
+use super::{UiNotification, Markdown, qr_image::qr_image};
 
 pub fn run(
     connection_list: Arc<RwLock<ConnectionList>>,
@@ -17,8 +17,11 @@ pub fn run(
     connection_requests: Sender<ConnectionRequest>,
     text_requests: Sender<TextRequest>,
     voice_requests: Sender<VoiceRequest>,
+    plugin_requests: Sender<PluginRequest>,
     voice_interlocutor: Arc<Mutex<Option<SocketAddr>>>,
     ui_notifications: Receiver<UiNotification>,
+    bandwidth_stats: Arc<RwLock<BandwidthStats>>,
+    plugin_list: Arc<RwLock<PluginList>>,
 
     unmovable_context: UnmovableContext,
     is_still_loading: Arc<Mutex<bool>>
@@ -41,8 +44,11 @@ pub fn run(
             connection_requests,
             text_requests,
             voice_requests,
+            plugin_requests,
             voice_interlocutor,
             ui_notifications,
+            bandwidth_stats,
+            plugin_list,
 
             unmovable_context,
             is_still_loading,
@@ -59,11 +65,37 @@ pub struct UI
     is_still_loading: Arc<Mutex<bool>>,
     first_running_frame: bool,
     input_buffer: String,
+    /// nonce and preview of the message we are answering
+    reply_to: Option<(u64,String)>,
+    /// nonce of the message we are changing
+    editing: Option<u64>,
+    /// contact and time of the last typing notification we sent
+    last_typing: Option<(String,std::time::Instant)>,
     new_connection_url_buffer: String,
+    invite_address_buffer: String,
+    invite_one_time: bool,
     search_user_buffer: String,
     settings_port_buffer: String,
+    settings_protection_mode_buffer: Option<ProtectionMode>,
+    settings_current_passphrase_buffer: String,
+    settings_new_passphrase_buffer: String,
+    settings_confirm_passphrase_buffer: String,
+    settings_identity_path_buffer: String,
+    settings_identity_passphrase_buffer: String,
+    settings_psk_buffer: String,
+    unlock_passphrase_buffer: String,
+    unlock_error: Option<String>,
 
     active_contact: Option<String>,
+    /// how many messages of the active chat are shown, older ones are loaded when scrolling back
+    visible_messages: Option<(String,usize)>,
+    search_buffer: String,
+    /// message picked from the search results, it's highlighted in its chat
+    search_selection: Option<(String,u64,TextDirection)>,
+    /// the chat scrolls to the selected message on the next frame
+    scroll_to_selection: bool,
+    /// text and parsed Markdown of the messages shown in the chat, parsed again only when the text changes
+    markdown_cache: HashMap<(u64,TextDirection),(String,Markdown)>,
 
     connection_list: Arc<RwLock<ConnectionList>>,
     text_list: Arc<RwLock<TextList>>,
@@ -71,15 +103,37 @@ pub struct UI
     connection_requests: Sender<ConnectionRequest>,
     text_requests: Sender<TextRequest>,
     voice_requests: Sender<VoiceRequest>,
+    plugin_requests: Sender<PluginRequest>,
     voice_interlocutor: Arc<Mutex<Option<SocketAddr>>>,
 
     ui_notifications: Receiver<UiNotification>,
+    bandwidth_stats: Arc<RwLock<BandwidthStats>>,
+    plugin_list: Arc<RwLock<PluginList>>,
 
     unmovable_context: UnmovableContext,
 
     show_new_connection_dialog: bool,
+    show_invite_dialog: bool,
+    invite: Option<(String,Option<TextureHandle>)>,
     show_settings_dialog: bool,
+    show_acl_dialog: bool,
     show_incoming_call_dialog: Option<String>,
+    show_contact_info_dialog: Option<String>,
+    contact_info_qr: Option<(String,TextureHandle)>,
+    introduce_to_buffer: Option<String>,
+    show_new_group_dialog: bool,
+    group_name_buffer: String,
+    show_export_dialog: bool,
+    /// None is the System log
+    export_conversation: Option<String>,
+    export_path_buffer: String,
+    export_format: ExportFormat,
+    show_plugins_dialog: bool,
+    /// id of the group
+    show_group_dialog: Option<String>,
+    group_member_buffer: Option<String>,
+    key_change_dialogs: Vec<(String,PublicKey,PublicKey)>,
+    incoming_connection_dialogs: Vec<(String,PublicKey,SocketAddr)>,
 
     input_devices: Vec<String>,
     output_devices: Vec<String>,
@@ -106,14 +160,18 @@ impl UI
         connection_requests: Sender<ConnectionRequest>,
         text_requests: Sender<TextRequest>,
         voice_requests: Sender<VoiceRequest>,
+        plugin_requests: Sender<PluginRequest>,
         voice_interlocutor: Arc<Mutex<Option<SocketAddr>>>,
         ui_notifications: Receiver<UiNotification>,
+        bandwidth_stats: Arc<RwLock<BandwidthStats>>,
+        plugin_list: Arc<RwLock<PluginList>>,
         unmovable_context: UnmovableContext,
         is_still_loading: Arc<Mutex<bool>>,
         cc: &CreationContext
     ) -> Self
     {   
         let settings_port_buffer = unmovable_context.config.read().unwrap().network.port.to_string();
+        let settings_psk_buffer = unmovable_context.config.read().unwrap().network.psk.clone().unwrap_or_default();
         let texture_options = TextureOptions::LINEAR;
         let loading_image = cc.egui_ctx.load_texture("Loading", 
             load_image!("../../assets/loading.png"),
@@ -123,22 +181,61 @@ impl UI
             is_still_loading,
             first_running_frame: true,
             input_buffer: String::new(), 
+            reply_to: None,
+            editing: None,
+            last_typing: None,
             new_connection_url_buffer: String::new(),
+            invite_address_buffer: String::new(),
+            invite_one_time: true,
             search_user_buffer: String::new(),
             settings_port_buffer,
+            settings_protection_mode_buffer: None,
+            settings_current_passphrase_buffer: String::new(),
+            settings_new_passphrase_buffer: String::new(),
+            settings_confirm_passphrase_buffer: String::new(),
+            settings_identity_path_buffer: defines::IDENTITY_EXPORT_PATH.to_string(),
+            settings_identity_passphrase_buffer: String::new(),
+            settings_psk_buffer,
+            unlock_passphrase_buffer: String::new(),
+            unlock_error: None,
             active_contact: None, 
+            visible_messages: None,
+            search_buffer: String::new(),
+            search_selection: None,
+            scroll_to_selection: false,
+            markdown_cache: HashMap::new(),
             connection_list, 
             text_list, 
             log,
             connection_requests, 
             text_requests,
             voice_requests,
+            plugin_requests,
             voice_interlocutor,
             ui_notifications,
+            bandwidth_stats,
+            plugin_list,
             unmovable_context,
             show_new_connection_dialog: false,
+            show_invite_dialog: false,
+            invite: None,
             show_settings_dialog: false,
+            show_acl_dialog: false,
             show_incoming_call_dialog: None,
+            show_contact_info_dialog: None,
+            contact_info_qr: None,
+            introduce_to_buffer: None,
+            show_new_group_dialog: false,
+            group_name_buffer: String::new(),
+            show_export_dialog: false,
+            export_conversation: None,
+            export_path_buffer: String::new(),
+            export_format: ExportFormat::Json,
+            show_plugins_dialog: false,
+            show_group_dialog: None,
+            group_member_buffer: None,
+            key_change_dialogs: Vec::new(),
+            incoming_connection_dialogs: Vec::new(),
             input_devices: Vec::new(),
             output_devices: Vec::new(),
             loading_image,
@@ -214,7 +311,11 @@ impl UI
 
     fn validate_new_connection_url(&self) -> bool
     {
-        if let Ok(iter) = self.new_connection_url_buffer.to_socket_addrs()
+        if self.new_connection_url_buffer.starts_with(defines::INVITE_SCHEME)
+        {
+            Invite::from_str(&self.new_connection_url_buffer).is_ok()
+        }
+        else if let Ok(iter) = self.new_connection_url_buffer.to_socket_addrs()
         {
             iter.len() > 0
         }
@@ -226,6 +327,11 @@ impl UI
 
     fn save_config(&self)
     {
+        if self.unmovable_context.locked_config.lock().unwrap().is_some()
+        {
+            // the config in memory is only a placeholder, don't overwrite the protected one
+            return;
+        }
         // save config to file
         match self.unmovable_context.config.write()
         {
@@ -261,27 +367,55 @@ impl UI
         ui.set_height(size.y - 2.0*group_margin - input_height);
         ui.set_min_width(left_group_min_width);
         ui.set_width(left_group_width);
+        ui.add(TextEdit::singleline(&mut self.search_buffer)
+            .hint_text("Search messages")
+            .desired_width(f32::INFINITY));
+        if self.search_buffer.trim().is_empty()
+        {
+            self.search_selection = None;
+        }
         //contacts
         ScrollArea::vertical()
         .id_source("ContactsScrollArea")
         .auto_shrink([false;2])
         .show(ui, |ui|{
             ui.vertical(|ui|{
-                let mut contacts = 
+                if !self.search_buffer.trim().is_empty()
+                {
+                    // the results replace the contacts while searching
+                    self.add_search_results(ui);
+                    return;
+                }
+                let (mut contacts, mut offline_contacts, mut groups) = 
                 {
                     let connection_list = self.connection_list.read().unwrap();
+                    let config = self.unmovable_context.config.read().unwrap();
+                    let groups: Vec<(String,String)> = config.text.groups.values()
+                        .filter(|group| !group.left)
+                        .map(|group| (group.info.id.clone(), group.info.name.clone()))
+                        .collect();
                     if let Some(name) = &self.active_contact
                     {
-                        if connection_list.get_address(name).is_none()
+                        if connection_list.get_address(name).is_none() && 
+                            !config.network.known_hosts.contains_key(name) &&
+                            !groups.iter().any(|(id, _)| id == name)
                         {
                             self.active_contact = None;
                         }
                     }
-                    connection_list.get_names()
+                    let contacts = connection_list.get_names();
+                    // messages to known contacts are queued while they are offline
+                    let offline_contacts: Vec<String> = config.network.known_hosts.keys()
+                        .filter(|name| !contacts.contains(name))
+                        .cloned()
+                        .collect();
+                    (contacts, offline_contacts, groups)
                 };
                 contacts.sort_by(|c1,c2|{
                     c1.cmp(c2)    
                 });
+                offline_contacts.sort();
+                groups.sort_by(|(_, n1),(_, n2)| n1.cmp(n2));
                 { // add system button
                     let has_new_messages = self.log.has_new_messages().unwrap();
                     let button_text = if has_new_messages {"System*"} else {"System"};
@@ -298,13 +432,20 @@ impl UI
                         self.active_contact = None;
                     }
                 }
-                for c in contacts
+                let contacts = contacts.into_iter().map(|c| (c,true))
+                    .chain(offline_contacts.into_iter().map(|c| (c,false)));
+                for (c, is_online) in contacts
                 {
                     let has_new_messages = 
                     {
                         let text_list = self.text_list.read().unwrap();
                         text_list.has_new_messages(&c)
                     };
+                    let is_verified = 
+                    {
+                        let config = self.unmovable_context.config.read().unwrap();
+                        config.network.known_hosts.get(&c).map(|host| host.is_verified()).unwrap_or(false)
+                    };
                     let max_chars = ((ui.available_width() - 40.0)/ 5.0) as usize;
                     let shortened_name = 
                     if c.len() > max_chars
@@ -317,6 +458,7 @@ impl UI
                     };
                     let button_text = 
                     {
+                        let shortened_name = if is_verified {format!("✔{}",shortened_name)} else {shortened_name};
                         if has_new_messages
                         {
                             format!("{}*",shortened_name)
@@ -326,6 +468,22 @@ impl UI
                             shortened_name.clone()    
                         }
                     };
+                    if !is_online
+                    {
+                        let mut button = Button::new(RichText::new(&button_text).weak());
+                        if self.active_contact == Some(c.clone())
+                        {
+                            button = button.fill(accent_color);
+                        }
+                        if ui.add_sized(
+                            Vec2::new(ui.available_width(),20.0), 
+                            button).on_hover_text("Offline").clicked()
+                        {
+                            // offline user selected
+                            self.active_contact = Some(c.clone());
+                        }
+                        continue;
+                    }
                     ui.horizontal(|ui|{
                         ui.style_mut().spacing.item_spacing.x = 4.0;
                         let mut button = Button::new(&button_text)
@@ -360,6 +518,23 @@ impl UI
                         }
                     });
                 }
+                for (id, name) in groups
+                {
+                    let has_new_messages = self.text_list.read().unwrap().has_new_messages(&id);
+                    let button_text = if has_new_messages {format!("#{}*",name)} else {format!("#{}",name)};
+                    let mut button = Button::new(button_text);
+                    if self.active_contact.as_ref() == Some(&id)
+                    {
+                        button = button.fill(accent_color);
+                    }
+                    if ui.add_sized(
+                        Vec2::new(ui.available_width(),20.0), 
+                        button).clicked()
+                    {
+                        // group selected
+                        self.active_contact = Some(id);
+                    }
+                }
                 { // add new contact button
                     let button = Button::new("+");
                     if ui.add_sized(
@@ -370,10 +545,52 @@ impl UI
                         self.show_new_connection_dialog = true;
                     }
                 }
+                { // add new group button
+                    let button = Button::new("+ Group");
+                    if ui.add_sized(
+                        Vec2::new(ui.available_width(),20.0), 
+                        button).clicked()
+                    {
+                        self.show_new_group_dialog = true;
+                    }
+                }
             });
         });   
     }
 
+    fn add_search_results(&mut self, ui: &mut Ui)
+    {
+        let results = self.text_list.read().unwrap().search(&self.search_buffer);
+        if results.is_empty()
+        {
+            ui.weak("No messages found");
+        }
+        for result in results
+        {
+            let conversation = match self.unmovable_context.config.read().unwrap().text.groups.get(&result.conversation)
+            {
+                Some(group) => format!("#{}", group.info.name),
+                None => result.conversation.clone(),
+            };
+            let author = match (&result.direction, &result.author)
+            {
+                (TextDirection::Incoming, Some(author)) => author.as_str(),
+                (TextDirection::Incoming, None) => result.conversation.as_str(),
+                (TextDirection::Outgoing, _) => "You",
+            };
+            let sent = DateTime::<Local>::from(result.sent).format("%Y-%m-%d %H:%M");
+            let key = (result.conversation.clone(), result.nonce, result.direction.clone());
+            let selected = self.search_selection.as_ref() == Some(&key);
+            if ui.selectable_label(selected, format!("{} · {}\n{}: {}", conversation, sent, author, result.snippet)).clicked()
+            {
+                // jump to the message in its chat
+                self.active_contact = Some(result.conversation.clone());
+                self.search_selection = Some(key);
+                self.scroll_to_selection = true;
+            }
+        }
+    }
+
     fn add_chat(
         &mut self,
         ui: &mut Ui,
@@ -385,29 +602,228 @@ impl UI
     {
         ui.set_height(size.y - 2.0*group_margin - input_height);
         ui.set_width(ui.available_width());
+        if let Some(c) = &self.active_contact
+        {
+            let timer = self.unmovable_context.config.read().unwrap().text.disappearing.get(c).copied();
+            ui.horizontal(|ui|{
+                ui.strong(self.conversation_title(Some(c)));
+                if let Some(timer) = timer
+                {
+                    ui.weak(format!("⏱ {}", timer.name()))
+                        .on_hover_text(format!("Messages disappear after {}", timer.name()));
+                }
+            });
+            ui.separator();
+        }
+        let typing = match &self.active_contact
+        {
+            Some(c) => self.text_list.read().unwrap().typing(c),
+            None => Vec::new(),
+        };
+        let typing_height = if typing.is_empty() { 0.0 } else { ui.text_style_height(&egui::TextStyle::Body) + ui.spacing().item_spacing.y };
         ScrollArea::vertical()
         .id_source("ChatScrollArea")
+        .max_height(ui.available_height() - typing_height)
         .auto_shrink([false;2])
-        .stick_to_bottom(true)
+        .stick_to_bottom(!self.scroll_to_selection)
         .show(ui, |ui|{
             //chat
             if let Some(c) = &self.active_contact
             {
                 let mut text_list = self.text_list.write().unwrap();
+                let mut limit = match &self.visible_messages
+                {
+                    Some((contact, limit)) if contact == c => *limit,
+                    _ => defines::CHAT_PAGE_SIZE,
+                };
+                let selected = self.search_selection.as_ref()
+                    .filter(|(conversation, _, _)| conversation == c)
+                    .map(|(_, nonce, direction)| (*nonce, direction.clone()));
+                let mut show_selected = None;
+                let mut load_older = false;
+                let mut resend = None;
+                let mut reply = None;
+                let mut edit = None;
+                let mut delete = None;
+                let mut react = None;
+                let mut shown = HashSet::new();
+                let reactions = text_list.reactions(c).cloned().unwrap_or_default();
                 if let Some(messages) = text_list.get(c)
                 {
-                    for m in messages
+                    let by_nonce: HashMap<u64,&TextInfo> = messages.iter().map(|m| (m.nonce, m)).collect();
+                    if let Some((nonce, direction)) = selected.as_ref().filter(|_| self.scroll_to_selection)
+                    {
+                        // older messages are loaded up to the one to show
+                        if let Some(index) = messages.iter().position(|m| m.nonce == *nonce && m.direction == *direction)
+                        {
+                            if messages.len() - index > limit
+                            {
+                                limit = messages.len() - index;
+                                show_selected = Some(limit);
+                            }
+                        }
+                    }
+                    if messages.len() > limit
+                    {
+                        ui.vertical_centered(|ui|{
+                            load_older = ui.button("Load older messages").clicked();
+                        });
+                    }
+                    let mut last_day = None;
+                    for m in messages.iter().skip(messages.len().saturating_sub(limit))
                     {    
-                        ui.horizontal(|ui|{
-                            
-                            ui.label(format!("{}:",
-                            if m.direction == TextDirection::Incoming {c} else {"You"}));
-                            ui.add(Label::new(m.text.clone())
-                                .wrap(true)
-                            );
+                        let sent = DateTime::<Local>::from(m.sent);
+                        if last_day != Some(sent.date_naive())
+                        {
+                            last_day = Some(sent.date_naive());
+                            ui.vertical_centered(|ui|{
+                                ui.weak(sent.format("%A %d %B %Y").to_string());
+                            });
+                        }
+                        if let Some(quoted) = m.reply_to.and_then(|nonce| by_nonce.get(&nonce))
+                        {
+                            ui.weak(format!("↪ {}", message_preview(quoted, c)));
+                        }
+                        let row = ui.horizontal(|ui|{
+                            let received = DateTime::<Local>::from(m.received);
+                            ui.weak(sent.format("%H:%M").to_string())
+                                .on_hover_text(format!("Sent: {}\nReceived: {}",
+                                    sent.format("%Y-%m-%d %H:%M:%S"),
+                                    received.format("%Y-%m-%d %H:%M:%S")));
+                            ui.label(format!("{}:", message_author(m, c)));
+                            if m.direction == TextDirection::Outgoing
+                            {
+                                if m.state == DeliveryState::Failed
+                                {
+                                    ui.colored_label(defines::LOG_ERROR_COLOR, m.state.symbol())
+                                        .on_hover_text(m.state.name());
+                                    if ui.small_button("Resend").clicked()
+                                    {
+                                        resend = Some((m.nonce, m.text.clone(), m.reply_to));
+                                    }
+                                }
+                                else
+                                {
+                                    ui.weak(m.state.symbol())
+                                        .on_hover_text(m.state.name());
+                                }
+                            }
+                            if m.deleted
+                            {
+                                ui.label(RichText::new("message deleted").italics().weak());
+                                return;
+                            }
+                            shown.insert((m.nonce, m.direction.clone()));
+                            cached_markdown(&mut self.markdown_cache, m).show(ui)
+                            .interact(Sense::click())
+                            .context_menu(|ui|{
+                                if ui.button("Reply").clicked()
+                                {
+                                    reply = Some((m.nonce, message_preview(m, c)));
+                                    ui.close_menu();
+                                }
+                                if m.direction == TextDirection::Outgoing
+                                {
+                                    if ui.button("Edit").clicked()
+                                    {
+                                        edit = Some((m.nonce, m.text.clone()));
+                                        ui.close_menu();
+                                    }
+                                    if ui.button("Delete for everyone").clicked()
+                                    {
+                                        delete = Some(m.nonce);
+                                        ui.close_menu();
+                                    }
+                                }
+                            });
+                            if m.edited
+                            {
+                                ui.weak("(edited)");
+                            }
+                            // the picker is shown while the message is hovered
+                            let picker_id = ui.make_persistent_id(("ReactionPicker", m.nonce));
+                            if ui.memory(|memory| memory.is_popup_open(picker_id)) || ui.rect_contains_pointer(ui.max_rect())
+                            {
+                                let button = ui.small_button("☺").on_hover_text("React");
+                                if button.clicked()
+                                {
+                                    ui.memory_mut(|memory| memory.toggle_popup(picker_id));
+                                }
+                                egui::popup_below_widget(ui, picker_id, &button, |ui|{
+                                    ui.horizontal(|ui|{
+                                        for emoji in defines::REACTIONS
+                                        {
+                                            if ui.button(*emoji).clicked()
+                                            {
+                                                react = Some((m.nonce, emoji.to_string(), true));
+                                            }
+                                        }
+                                    });
+                                });
+                            }
                         });
+                        if selected.as_ref().is_some_and(|(nonce, direction)| *nonce == m.nonce && *direction == m.direction)
+                        {
+                            ui.painter().rect_stroke(row.response.rect.expand(2.0), 3.0, ui.visuals().selection.stroke);
+                            if self.scroll_to_selection
+                            {
+                                row.response.scroll_to_me(Some(Align::Center));
+                            }
+                        }
+                        if let Some(message_reactions) = reactions.get(&m.nonce).filter(|_| !m.deleted)
+                        {
+                            ui.horizontal(|ui|{
+                                for (emoji, people) in message_reactions.iter()
+                                {
+                                    let mine = people.contains(&None);
+                                    let names: Vec<&str> = people.iter().map(|who| who.as_deref().unwrap_or("You")).collect();
+                                    if ui.selectable_label(mine, format!("{} {}", emoji, people.len()))
+                                        .on_hover_text(names.join(", "))
+                                        .clicked()
+                                    {
+                                        react = Some((m.nonce, emoji.clone(), !mine));
+                                    }
+                                }
+                            });
+                        }
                     }
                 }
+                // only the messages on screen are kept
+                self.markdown_cache.retain(|key, _| shown.contains(key));
+                if let Some((nonce, emoji, added)) = react
+                {
+                    self.text_requests.send(TextRequest::React(c.clone(), nonce, emoji, added)).unwrap();
+                }
+                if let Some(reply) = reply
+                {
+                    self.editing = None;
+                    self.reply_to = Some(reply);
+                }
+                if let Some((nonce, text)) = edit
+                {
+                    self.reply_to = None;
+                    self.editing = Some(nonce);
+                    self.input_buffer = text;
+                }
+                if let Some(nonce) = delete
+                {
+                    self.text_requests.send(TextRequest::Delete(c.clone(), nonce)).unwrap();
+                }
+                self.scroll_to_selection = false;
+                if let Some(limit) = show_selected
+                {
+                    self.visible_messages = Some((c.clone(), limit));
+                }
+                if load_older
+                {
+                    self.visible_messages = Some((c.clone(), limit + defines::CHAT_PAGE_SIZE));
+                }
+                if let Some((nonce, text, reply_to)) = resend
+                {
+                    // the message is sent again as a new one
+                    text_list.remove(c, nonce, &TextDirection::Outgoing);
+                    self.text_requests.send(TextRequest::Send(c.clone(), text, reply_to)).unwrap();
+                }
             }
             else
             {
@@ -421,11 +837,13 @@ impl UI
                             MessageKind::Event => text_color,
                             MessageKind::Command => defines::LOG_COMMAND_COLOR,
                             MessageKind::Error => defines::LOG_ERROR_COLOR, 
+                            MessageKind::Security => defines::LOG_SECURITY_COLOR,
                         };
                         let text = match m.kind {
                             MessageKind::Command =>  format!("{} ({}) Command:",time_string,m.src),
                             MessageKind::Event =>  format!("{} ({}):",time_string,m.src),
                             MessageKind::Error =>  format!("{} ({}) Error:",time_string,m.src),
+                            MessageKind::Security =>  format!("{} ({}) Security:",time_string,m.src),
                         };
                         ui.colored_label(color,text);
                         ui.add(Label::new(m.text.clone())
@@ -435,6 +853,27 @@ impl UI
                 }
             }
         });
+        match typing.as_slice()
+        {
+            [] => {},
+            [who] => { ui.weak(format!("{} is typing…", who)); },
+            _ => { ui.weak(format!("{} are typing…", typing.join(", "))); },
+        }
+    }
+
+    /// Tells the active contact we are writing, at most once every [`defines::TYPING_INTERVAL`]
+    fn send_typing(&mut self)
+    {
+        if let Some(c) = &self.active_contact
+        {
+            let recently_sent = matches!(&self.last_typing,
+                Some((contact, last_sent)) if contact == c && last_sent.elapsed() < defines::TYPING_INTERVAL);
+            if !recently_sent
+            {
+                self.text_requests.send(TextRequest::Typing(c.clone())).unwrap();
+                self.last_typing = Some((c.clone(), std::time::Instant::now()));
+            }
+        }
     }
 
     fn add_input(
@@ -449,48 +888,97 @@ impl UI
             //text input
             ui.with_layout(Layout::right_to_left(Align::Center), |ui|{
                 let mut just_sent = false;
+                let composer_id = egui::Id::new("Composer");
+                // Shift+Enter is left to the composer for a new line
+                let enter_pressed = ui.memory(|memory| memory.has_focus(composer_id))
+                    && ui.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Enter));
+                // a message must fit in a packet, the text is kept so it can be shortened
+                let too_long = self.active_contact.is_some() && self.input_buffer.len() > defines::MAX_TEXT_LEN;
                 // send button
                 if (ui.add(ImageButton::new(send_image)).clicked() 
-                    || ui.input(|i|i.key_pressed(Key::Enter)))
+                    || enter_pressed)
                     && self.input_buffer.len()>0
+                    && !too_long
                 {
                     if let Some(c) = &self.active_contact
                     {
-                        // sent a message
-                        let is_connected = {
+                        // sent a message, it's queued if the contact is offline
+                        let is_known = {
                             let connection_list = self.connection_list.read().unwrap();
-                            connection_list.get_address(c).is_some()
+                            let config = self.unmovable_context.config.read().unwrap();
+                            connection_list.get_address(c).is_some() ||
+                                config.network.known_hosts.contains_key(c) ||
+                                config.text.groups.contains_key(c)
                         };
-                        if is_connected
+                        if is_known
                         {
-                            self.text_requests.send(TextRequest { text: self.input_buffer.clone(), dst: c.clone() }).unwrap();
+                            let request = match self.editing
+                            {
+                                Some(nonce) => TextRequest::Edit(c.clone(), nonce, self.input_buffer.clone()),
+                                None => TextRequest::Send(c.clone(), self.input_buffer.clone(), self.reply_to.as_ref().map(|(nonce, _)| *nonce)),
+                            };
+                            self.text_requests.send(request).unwrap();
                         }
+                        self.reply_to = None;
+                        self.editing = None;
                     }
                     else
                     {
                         // sent command
                         self.log.log(MessageKind::Command,&self.input_buffer).unwrap();
-                        todo!("Parse command");
+                        let command = self.input_buffer.clone();
+                        self.run_command(&command);
                     }
                     self.input_buffer.clear();
                     //set focus to text input
                     just_sent = true;
                 }
+                if self.reply_to.is_some() || self.editing.is_some()
+                {
+                    let hover_text = if self.editing.is_some() {"Cancel edit"} else {"Cancel reply"};
+                    if ui.button("✖").on_hover_text(hover_text).clicked() || ui.input(|i| i.key_pressed(Key::Escape))
+                    {
+                        if self.editing.is_some()
+                        {
+                            self.input_buffer.clear();
+                        }
+                        self.reply_to = None;
+                        self.editing = None;
+                    }
+                }
+                if too_long
+                {
+                    ui.colored_label(defines::LOG_ERROR_COLOR, format!("{}/{}B", self.input_buffer.len(), defines::MAX_TEXT_LEN))
+                        .on_hover_text("The message is too long to be sent");
+                }
                 //press button enter if enter key is pressed with text input focused
                 let text_hint = 
-                    if let Some(_c) = &self.active_contact
-                    {"Type a message"}
-                    else 
-                    {"Type a command"};
-
-                let text_edit = ui.add_sized(
-                    Vec2::new(
-                        ui.available_width(),
-                        ui.available_height()),
-                    TextEdit::singleline(&mut self.input_buffer)
-                    //.vertical_align(Align::Max) // it's a bit buggy as it does not align the hint text
-                    .hint_text(text_hint)
-                );
+                    match (&self.active_contact, &self.reply_to, self.editing)
+                    {
+                        (Some(_c), _, Some(_nonce)) => "Edit the message".to_string(),
+                        (Some(_c), Some((_nonce, preview)), None) => format!("Reply to {}", preview),
+                        (Some(_c), None, None) => "Type a message".to_string(),
+                        (None, _, _) => "Type a command".to_string(),
+                    };
+
+                let input_buffer = &mut self.input_buffer;
+                let text_edit = ScrollArea::vertical()
+                .id_source("ComposerScrollArea")
+                .stick_to_bottom(true)
+                .show(ui, |ui|{
+                    ui.add_sized(
+                        Vec2::new(
+                            ui.available_width(),
+                            ui.available_height()),
+                        TextEdit::multiline(input_buffer)
+                        .id(composer_id)
+                        .hint_text(text_hint)
+                    )
+                }).inner;
+                if text_edit.changed() && !self.input_buffer.is_empty() && self.editing.is_none()
+                {
+                    self.send_typing();
+                }
                 if just_sent
                 {
                     text_edit.request_focus();
@@ -519,6 +1007,20 @@ impl UI
                 .clicked()
                 {
                     self.show_settings_dialog = true;
+                    self.settings_protection_mode_buffer = self.unmovable_context.config.read().unwrap().protection.as_ref().map(|protection| protection.mode);
+                }
+                if ui.add(Button::new("⬇")).on_hover_text("Export chat").clicked()
+                {
+                    self.export_conversation = self.active_contact.clone();
+                    let file_name = self.conversation_title(self.export_conversation.as_deref())
+                        .replace(|c: char| !c.is_alphanumeric() && c != '-' && c != '_', "_");
+                    self.export_path_buffer = format!("{}.{}", file_name, self.export_format.extension());
+                    self.show_export_dialog = true;
+                }
+                let has_panels = self.plugin_list.read().unwrap().get_infos().iter().any(|plugin| !plugin.panels.is_empty());
+                if has_panels && ui.add(Button::new("🧩")).on_hover_text("Plugins").clicked()
+                {
+                    self.show_plugins_dialog = !self.show_plugins_dialog;
                 }
                 if let Some(contact) = &self.active_contact
                 {
@@ -556,6 +1058,18 @@ impl UI
                             }
                         }
                     }
+                    let is_group = self.unmovable_context.config.read().unwrap().text.groups.contains_key(contact);
+                    if is_group
+                    {
+                        if ui.add(Button::new("👥")).on_hover_text("Group info").clicked()
+                        {
+                            self.show_group_dialog = Some(contact.clone());
+                        }
+                    }
+                    else if ui.add(Button::new("🔑")).on_hover_text("Contact info").clicked()
+                    {
+                        self.show_contact_info_dialog = Some(contact.clone());
+                    }
                 }
             });
         });
@@ -590,6 +1104,41 @@ impl UI
                     {
                         config.network.port = port;
                     }
+                    if ui.add_sized(
+                        Vec2::new(ui.available_width(),20.0),
+                        Button::new(format!("Access control ({} rules)", config.network.acl.rules.len()))).clicked()
+                    {
+                        self.show_acl_dialog = true;
+                    }
+                    ui.label("Padding");
+                    ComboBox::new(
+                        "PaddingComboBox",
+                        "",
+                    ).width(ui.available_width() - 10.0)
+                    .selected_text(config.network.padding.name())
+                    .show_ui(ui, |ui|{
+                        for padding in PaddingPolicy::all()
+                        {
+                            ui.selectable_value(&mut config.network.padding, padding, padding.name());
+                        }
+                    });
+                    ui.horizontal(|ui|{
+                        let mut cover_traffic = config.network.cover_traffic_ms.is_some();
+                        if ui.checkbox(&mut cover_traffic, "Cover traffic while idle (ms)").changed()
+                        {
+                            config.network.cover_traffic_ms = if cover_traffic {Some(defines::DEFAULT_COVER_TRAFFIC_MS)} else {None};
+                        }
+                        if let Some(cover_traffic_ms) = &mut config.network.cover_traffic_ms
+                        {
+                            ui.add(DragValue::new(cover_traffic_ms).clamp_range(100..=60000));
+                        }
+                    });
+                    let stats = *self.bandwidth_stats.read().unwrap();
+                    ui.label(format!("Sent {} in {} packets, {:.1}% padding and cover traffic", 
+                        format_bytes(stats.sent_bytes), 
+                        stats.sent_packets, 
+                        stats.overhead() * 100.0));
+                    ui.label(format!("Received {} in {} packets", format_bytes(stats.received_bytes), stats.received_packets));
                 });
             }
             {//Voice
@@ -659,128 +1208,1253 @@ impl UI
                         .clamp_to_range(true));
                 });
             }
-            if ui.add_sized(
-                Vec2::new(ui.available_width(),20.0),
-                Button::new("Close")).clicked()
-            {
-                self.show_settings_dialog = false;
-                save_config = true;
-            }
-        });
-        if save_config
-        {
-            self.save_config();
-        }
-    }
-
-    fn show_new_connection(
-        &mut self, 
-        window_frame: Frame,
-        ctx: &egui::Context,
-        search_image: SizedTexture,
-        connect_image: SizedTexture
-    )
-    {
-        let text_color_addr = 
-            if self.validate_new_connection_url() {None} else {Some(egui::Color32::RED)};
-        egui::Window::new("Connect")
-        .frame(window_frame)
-        .collapsible(false)
-        .resizable(false)
-        .anchor(Align2::CENTER_CENTER, Vec2::new(0.0,0.0))
-        .show(ctx,|ui|{
-            let mut close_window = false;
-            ui.horizontal(|ui|{
-                ui.with_layout(Layout::right_to_left(Align::Center), |ui|{
-                    if ui.add(ImageButton::new(search_image)).clicked()
+            {//History
+                ui.label("History");
+                ui.group(|ui|{
+                    ui.set_width(ui.available_width());
+                    ui.label("Keep the chat history");
+                    let mut retention = Some(config.text.history_retention);
+                    if history_retention_editor(ui, "HistoryRetentionComboBox", &mut retention, false)
                     {
-                        self.connection_requests.send(ConnectionRequest::Find(self.search_user_buffer.clone())).unwrap();
-                        close_window = true;
+                        config.text.history_retention = retention.unwrap_or_default();
+                        save_config = true;
+                    }
+                    if config.protection.is_none() && config.text.history_retention != HistoryRetention::Off
+                    {
+                        ui.colored_label(defines::LOG_SECURITY_COLOR, "The key of the history is stored in clear in the config, set a passphrase to protect it");
+                    }
+                    if ui.checkbox(&mut config.text.read_receipts, "Send read receipts").changed()
+                    {
+                        save_config = true;
                     }
-                    ui.add_sized(Vec2::new(ui.available_width(),20.0), TextEdit::singleline(&mut self.search_user_buffer)
-                        .hint_text("user"));
                 });
-            });
-            ui.vertical_centered(|ui|{
-                ui.label("or");
-            });
-            ui.horizontal(|ui|{
-                ui.with_layout(Layout::right_to_left(Align::Center), |ui|{
-                    if ui.add(ImageButton::new(connect_image)).clicked()
+            }
+            {//Plugins
+                ui.label("Plugins");
+                ui.group(|ui|{
+                    ui.set_width(ui.available_width());
+                    let plugins = self.plugin_list.read().unwrap().get_infos().to_vec();
+                    if plugins.is_empty()
+                    {
+                        ui.label(format!("Put .wasm plugins in the \"{}\" folder", config.data_path(defines::PLUGINS_PATH)));
+                    }
+                    let mut reload = false;
+                    for plugin in plugins
                     {
-                        if let Ok(addesses) = self.new_connection_url_buffer.to_socket_addrs()
+                        let mut enabled = config.plugins.contains(&plugin.name);
+                        if ui.checkbox(&mut enabled, &plugin.name).changed()
                         {
-                            for address in addesses
-                            {    
-                                if defines::HOST.is_ipv4() == address.is_ipv4() && defines::HOST.is_ipv6() == address.is_ipv6()
-                                {
-                                    self.connection_requests.send(ConnectionRequest::Connect(address)).unwrap();
-                                    close_window = true;
-                                    break;   
-                                }
+                            if enabled
+                            {
+                                config.plugins.insert(plugin.name.clone());
+                            }
+                            else
+                            {
+                                config.plugins.remove(&plugin.name);
                             }
+                            save_config = true;
+                            reload = true;
+                        }
+                        if let Some(error) = &plugin.error
+                        {
+                            ui.colored_label(defines::LOG_ERROR_COLOR, error);
                         }
                     }
-                    ui.add_sized(Vec2::new(ui.available_width(),20.0),TextEdit::singleline(&mut self.new_connection_url_buffer)
-                    .hint_text("url")
-                    .text_color_opt(text_color_addr));
+                    if ui.add_sized(Vec2::new(ui.available_width(),20.0), Button::new("Reload"))
+                    .on_hover_text("Look for new plugins and restart the enabled ones")
+                    .clicked()
+                    {
+                        reload = true;
+                    }
+                    if reload
+                    {
+                        self.plugin_requests.send(PluginRequest::Reload).unwrap();
+                    }
                 });
-            });
-            
-            ui.horizontal(|ui|{
-                if ui.add_sized(
-                    Vec2::new(ui.available_width(),20.0),
-                    Button::new("Cancel")).clicked() ||
-                    ui.input(|i| i.key_pressed(Key::Escape))
-                {
-                    close_window = true;
-                }
-            });
-            if close_window
-            {
-                self.new_connection_url_buffer.clear();
-                self.show_new_connection_dialog = false;
             }
-        });
+            {//Security
+                ui.label("Security");
+                ui.group(|ui|{
+                    ui.set_width(ui.available_width());
+                    ui.style_mut().spacing.combo_width = ui.available_width() - 10.0;
+                    ui.label("Cipher suites");
+                    for cipher_suite in CipherSuite::all()
+                    {
+                        let mut enabled = config.network.cipher_suites.contains(&cipher_suite);
+                        // at least one suite must stay enabled
+                        let can_change = !enabled || config.network.cipher_suites.len() > 1;
+                        if ui.add_enabled(can_change, egui::Checkbox::new(&mut enabled, cipher_suite.name())).changed()
+                        {
+                            if enabled
+                            {
+                                config.network.cipher_suites.push(cipher_suite);
+                            }
+                            else
+                            {
+                                config.network.cipher_suites.retain(|suite| *suite != cipher_suite);
+                            }
+                            save_config = true;
+                        }
+                    }
+                    ui.label("Pre-shared key")
+                        .on_hover_text("Only peers with the same key can connect, share it with the members of your network");
+                    ui.add_sized(
+                        Vec2::new(ui.available_width(),20.0),
+                        TextEdit::singleline(&mut self.settings_psk_buffer).password(true).hint_text("none"));
+                    ui.horizontal(|ui|{
+                        if ui.add_sized(Vec2::new(ui.available_width()/2.0,20.0),Button::new("Generate"))
+                        .on_hover_text("Generate a random key and copy it")
+                        .clicked()
+                        {
+                            self.settings_psk_buffer = SymmetricKey::random().to_string();
+                            ui.output_mut(|o| o.copied_text = self.settings_psk_buffer.clone());
+                        }
+                        if ui.add_sized(Vec2::new(ui.available_width(),20.0),Button::new("Apply")).clicked()
+                        {
+                            let psk = if self.settings_psk_buffer.is_empty() {None} else {Some(self.settings_psk_buffer.clone())};
+                            if psk != config.network.psk
+                            {
+                                let message = if psk.is_some() {"Pre-shared key changed, it will be used for new connections"} else {"Pre-shared key removed"};
+                                config.network.psk = psk;
+                                self.log.audit(message);
+                                save_config = true;
+                            }
+                        }
+                    });
+                    ui.label("Passphrase protection");
+                    ComboBox::new(
+                        "ProtectionModeComboBox",
+                        "",
+                    ).selected_text(protection_mode_name(self.settings_protection_mode_buffer))
+                    .show_ui(ui, |ui|{
+                        for mode in [None, Some(ProtectionMode::PrivateKey), Some(ProtectionMode::Full)]
+                        {
+                            ui.selectable_value(&mut self.settings_protection_mode_buffer, mode, protection_mode_name(mode));
+                        }
+                    });
+                    if config.protection.is_some()
+                    {
+                        ui.label("Current passphrase");
+                        ui.add_sized(
+                            Vec2::new(ui.available_width(),20.0),
+                            TextEdit::singleline(&mut self.settings_current_passphrase_buffer).password(true));
+                    }
+                    if self.settings_protection_mode_buffer.is_some()
+                    {
+                        ui.label("New passphrase");
+                        ui.add_sized(
+                            Vec2::new(ui.available_width(),20.0),
+                            TextEdit::singleline(&mut self.settings_new_passphrase_buffer).password(true));
+                        ui.label("Confirm passphrase");
+                        ui.add_sized(
+                            Vec2::new(ui.available_width(),20.0),
+                            TextEdit::singleline(&mut self.settings_confirm_passphrase_buffer).password(true));
+                    }
+                    if ui.add_sized(
+                        Vec2::new(ui.available_width(),20.0),
+                        Button::new("Change passphrase")).clicked()
+                    {
+                        let protection = if config.protection.as_ref().map(|protection| !protection.check_passphrase(&self.settings_current_passphrase_buffer)).unwrap_or(false)
+                        {
+                            Err("Wrong passphrase".to_string())
+                        }
+                        else 
+                        {
+                            match self.settings_protection_mode_buffer
+                            {
+                                None => Ok(None),
+                                Some(_) if self.settings_new_passphrase_buffer.is_empty() => Err("The passphrase is empty".to_string()),
+                                Some(_) if self.settings_new_passphrase_buffer != self.settings_confirm_passphrase_buffer => Err("The passphrases don't match".to_string()),
+                                Some(mode) => ConfigProtection::new(&self.settings_new_passphrase_buffer, mode).map(Some),
+                            }
+                        };
+                        match protection
+                        {
+                            Ok(protection) =>
+                            {
+                                config.protection = protection;
+                                self.log.audit(&format!("Passphrase protection set to: {}", protection_mode_name(self.settings_protection_mode_buffer)));
+                                self.settings_current_passphrase_buffer.clear();
+                                self.settings_new_passphrase_buffer.clear();
+                                self.settings_confirm_passphrase_buffer.clear();
+                                save_config = true;
+                            },
+                            Err(e) => self.log.log(MessageKind::Error, &format!("Error changing passphrase: {}", e)).unwrap(),
+                        }
+                    }
+                });
+            }
+            {//Identity
+                ui.label("Identity");
+                ui.group(|ui|{
+                    ui.set_width(ui.available_width());
+                    ui.label("Fingerprint");
+                    ui.label(config.network.private_key.public_key().fingerprint_string());
+                    ui.label("PEM file");
+                    ui.add_sized(
+                        Vec2::new(ui.available_width(),20.0),
+                        TextEdit::singleline(&mut self.settings_identity_path_buffer));
+                    ui.label("File passphrase (optional)");
+                    ui.add_sized(
+                        Vec2::new(ui.available_width(),20.0),
+                        TextEdit::singleline(&mut self.settings_identity_passphrase_buffer).password(true));
+                    let passphrase = if self.settings_identity_passphrase_buffer.is_empty() {None} else {Some(self.settings_identity_passphrase_buffer.as_str())};
+                    ui.horizontal(|ui|{
+                        if ui.add_sized(Vec2::new(ui.available_width()/3.0,20.0),Button::new("Export")).clicked()
+                        {
+                            let result = config.network.private_key.to_pem(passphrase)
+                                .map_err(|e| e.to_string())
+                                .and_then(|pem| std::fs::write(&self.settings_identity_path_buffer, pem).map_err(|e| e.to_string()));
+                            match result
+                            {
+                                Ok(_) => self.log.audit(&format!("Identity key exported to {}", self.settings_identity_path_buffer)),
+                                Err(e) => self.log.log(MessageKind::Error, &format!("Error exporting identity: {}", e)).unwrap(),
+                            }
+                        }
+                        if ui.add_sized(Vec2::new(ui.available_width()/2.0,20.0),Button::new("Import")).clicked()
+                        {
+                            let result = std::fs::read_to_string(&self.settings_identity_path_buffer)
+                                .map_err(|e| e.to_string())
+                                .and_then(|pem| PrivateKey::from_pem(&pem, passphrase).map_err(|e| e.to_string()));
+                            match result
+                            {
+                                Ok(private_key) =>
+                                {
+                                    let fingerprint = private_key.public_key().fingerprint_string();
+                                    config.network.import_private_key(private_key);
+                                    self.log.audit(&format!("Identity key imported from {} ({}), it will be used for new connections", self.settings_identity_path_buffer, fingerprint));
+                                    save_config = true;
+                                },
+                                Err(e) => self.log.log(MessageKind::Error, &format!("Error importing identity: {}", e)).unwrap(),
+                            }
+                        }
+                        if ui.add_sized(Vec2::new(ui.available_width(),20.0),Button::new("Rotate key"))
+                        .on_hover_text("Replace the identity key with a new one certified by the current one")
+                        .clicked()
+                        {
+                            let old_fingerprint = config.network.private_key.public_key().fingerprint_string();
+                            config.network.rotate_private_key();
+                            self.log.audit(&format!("Identity key rotated from {} to {}, it will be used for new connections", 
+                                old_fingerprint, 
+                                config.network.private_key.public_key().fingerprint_string()));
+                            save_config = true;
+                        }
+                    });
+                });
+            }
+            if ui.add_sized(
+                Vec2::new(ui.available_width(),20.0),
+                Button::new("Close")).clicked()
+            {
+                self.show_settings_dialog = false;
+                save_config = true;
+            }
+        });
+        if save_config
+        {
+            self.save_config();
+        }
+    }
+
+    fn show_acl(
+        &mut self,
+        window_frame: Frame,
+        ctx: &egui::Context)
+    {
+        let mut close_window = false;
+        egui::Window::new("Access control")
+        .frame(window_frame)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, Vec2::new(0.0,0.0))
+        .show(ctx, |ui|{
+            let mut config = self.unmovable_context.config.write().unwrap();
+            let acl = &mut config.network.acl;
+            ui.label("Rules, the first one that matches is applied");
+            let mut remove = None;
+            let mut move_up = None;
+            ScrollArea::vertical().max_height(300.0).show(ui, |ui|{
+                for (index, rule) in acl.rules.iter_mut().enumerate()
+                {
+                    ui.horizontal(|ui|{
+                        let value = rule.pattern.value().to_string();
+                        ComboBox::new(("AclPatternComboBox", index), "")
+                        .width(100.0)
+                        .selected_text(rule.pattern.kind())
+                        .show_ui(ui, |ui|{
+                            for pattern in [AclPattern::Fingerprint(value.clone()), AclPattern::Name(value.clone()), AclPattern::Address(value.clone())]
+                            {
+                                let kind = pattern.kind();
+                                ui.selectable_value(&mut rule.pattern, pattern, kind);
+                            }
+                        });
+                        ComboBox::new(("AclActionComboBox", index), "")
+                        .width(80.0)
+                        .selected_text(rule.action.name())
+                        .show_ui(ui, |ui|{
+                            for action in AclAction::all()
+                            {
+                                ui.selectable_value(&mut rule.action, action, action.name());
+                            }
+                        });
+                        if ui.add_enabled(index > 0, Button::new("⏶")).clicked()
+                        {
+                            move_up = Some(index);
+                        }
+                        if ui.button("✖").clicked()
+                        {
+                            remove = Some(index);
+                        }
+                        let hint = match rule.pattern
+                        {
+                            AclPattern::Fingerprint(_) => "fingerprint prefix",
+                            AclPattern::Name(_) => "name, * is a wildcard",
+                            AclPattern::Address(_) => "address or cidr",
+                        };
+                        ui.add_sized(Vec2::new(ui.available_width(),20.0), TextEdit::singleline(rule.pattern.value_mut()).hint_text(hint));
+                    });
+                }
+            });
+            if let Some(index) = move_up
+            {
+                acl.rules.swap(index, index - 1);
+            }
+            if let Some(index) = remove
+            {
+                acl.rules.remove(index);
+            }
+            if ui.add_sized(Vec2::new(ui.available_width(),20.0), Button::new("Add rule")).clicked()
+            {
+                acl.rules.push(AclRule::new(AclPattern::Name(String::new()), AclAction::Accept));
+            }
+            ui.separator();
+            ui.horizontal(|ui|{
+                ui.label("When no rule matches");
+                ComboBox::new("AclDefaultActionComboBox", "")
+                .selected_text(acl.default_action.name())
+                .show_ui(ui, |ui|{
+                    for action in AclAction::all()
+                    {
+                        ui.selectable_value(&mut acl.default_action, action, action.name());
+                    }
+                });
+            });
+            ui.horizontal(|ui|{
+                let mut limited = acl.max_strangers_per_minute.is_some();
+                if ui.checkbox(&mut limited, "Limit new peers per minute").changed()
+                {
+                    acl.max_strangers_per_minute = if limited {Some(defines::DEFAULT_MAX_STRANGERS_PER_MINUTE)} else {None};
+                }
+                if let Some(limit) = &mut acl.max_strangers_per_minute
+                {
+                    ui.add(DragValue::new(limit).clamp_range(1..=1000));
+                }
+            });
+            if ui.add_sized(Vec2::new(ui.available_width(),20.0), Button::new("Close")).clicked() ||
+                ui.input(|i| i.key_pressed(Key::Escape))
+            {
+                close_window = true;
+            }
+        });
+        if close_window
+        {
+            self.show_acl_dialog = false;
+            self.save_config();
+        }
+    }
+
+    fn show_incoming_connection(
+        &mut self,
+        name: String,
+        public_key: PublicKey,
+        address: SocketAddr,
+        window_frame: Frame,
+        ctx: &egui::Context,
+        accent_color: egui::Color32)
+    {
+        let mut answer = None;
+        egui::Window::new(format!("{} wants to connect",name))
+        .frame(window_frame)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, Vec2::new(0.0,0.0))
+        .show(ctx, |ui|{
+            ui.label("Address");
+            ui.label(address.to_string());
+            ui.label("Fingerprint");
+            ui.label(public_key.fingerprint_string());
+            ui.separator();
+            ui.horizontal(|ui|{
+                if ui.add_sized(Vec2::new(ui.available_width()/3.0,20.0), Button::new("Accept").fill(accent_color)).clicked()
+                {
+                    answer = Some((true, false));
+                }
+                if ui.add_sized(Vec2::new(ui.available_width()/2.0,20.0), Button::new("Always accept"))
+                .on_hover_text("Add a rule that accepts this identity key")
+                .clicked()
+                {
+                    answer = Some((true, true));
+                }
+                if ui.add_sized(Vec2::new(ui.available_width(),20.0), Button::new("Decline")).clicked()
+                {
+                    answer = Some((false, false));
+                }
+            });
+        });
+        if let Some((accept, always)) = answer
+        {
+            if always
+            {
+                let fingerprint = public_key.fingerprint().iter().map(|b| format!("{:02x}", b)).collect();
+                self.unmovable_context.config.write().unwrap().network.acl.rules
+                    .insert(0, AclRule::new(AclPattern::Fingerprint(fingerprint), AclAction::Accept));
+                self.save_config();
+            }
+            self.connection_requests.send(ConnectionRequest::AnswerIncoming(address, accept)).unwrap();
+            self.incoming_connection_dialogs.remove(0);
+        }
+    }
+
+    fn show_new_connection(
+        &mut self, 
+        window_frame: Frame,
+        ctx: &egui::Context,
+        search_image: SizedTexture,
+        connect_image: SizedTexture
+    )
+    {
+        let text_color_addr = 
+            if self.validate_new_connection_url() {None} else {Some(egui::Color32::RED)};
+        egui::Window::new("Connect")
+        .frame(window_frame)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, Vec2::new(0.0,0.0))
+        .show(ctx,|ui|{
+            let mut close_window = false;
+            ui.horizontal(|ui|{
+                ui.with_layout(Layout::right_to_left(Align::Center), |ui|{
+                    if ui.add(ImageButton::new(search_image)).clicked()
+                    {
+                        self.connection_requests.send(ConnectionRequest::Find(self.search_user_buffer.clone())).unwrap();
+                        close_window = true;
+                    }
+                    ui.add_sized(Vec2::new(ui.available_width(),20.0), TextEdit::singleline(&mut self.search_user_buffer)
+                        .hint_text("user"));
+                });
+            });
+            ui.vertical_centered(|ui|{
+                ui.label("or");
+            });
+            ui.horizontal(|ui|{
+                ui.with_layout(Layout::right_to_left(Align::Center), |ui|{
+                    if ui.add(ImageButton::new(connect_image)).clicked()
+                    {
+                        if let Ok(invite) = Invite::from_str(&self.new_connection_url_buffer)
+                        {
+                            self.connection_requests.send(ConnectionRequest::AcceptInvite(invite)).unwrap();
+                            close_window = true;
+                        }
+                        else if let Ok(addesses) = self.new_connection_url_buffer.to_socket_addrs()
+                        {
+                            for address in addesses
+                            {    
+                                if defines::HOST.is_ipv4() == address.is_ipv4() && defines::HOST.is_ipv6() == address.is_ipv6()
+                                {
+                                    self.connection_requests.send(ConnectionRequest::Connect(address)).unwrap();
+                                    close_window = true;
+                                    break;   
+                                }
+                            }
+                        }
+                    }
+                    ui.add_sized(Vec2::new(ui.available_width(),20.0),TextEdit::singleline(&mut self.new_connection_url_buffer)
+                    .hint_text("url or invite")
+                    .text_color_opt(text_color_addr));
+                });
+            });
+            
+            ui.horizontal(|ui|{
+                if ui.add_sized(
+                    Vec2::new(ui.available_width(),20.0),
+                    Button::new("Create invite")).clicked()
+                {
+                    let port = self.unmovable_context.config.read().unwrap().network.port;
+                    self.invite_address_buffer = Invite::local_address(port).map(|address| address.to_string()).unwrap_or_default();
+                    self.invite = None;
+                    self.show_invite_dialog = true;
+                    close_window = true;
+                }
+            });
+            ui.horizontal(|ui|{
+                if ui.add_sized(
+                    Vec2::new(ui.available_width(),20.0),
+                    Button::new("Cancel")).clicked() ||
+                    ui.input(|i| i.key_pressed(Key::Escape))
+                {
+                    close_window = true;
+                }
+            });
+            if close_window
+            {
+                self.new_connection_url_buffer.clear();
+                self.show_new_connection_dialog = false;
+            }
+        });
+    }
+
+    fn show_invite(
+        &mut self,
+        window_frame: Frame,
+        ctx: &egui::Context,
+        accent_color: egui::Color32)
+    {
+        let mut close_window = false;
+        let mut generate = false;
+        egui::Window::new("Invite")
+        .frame(window_frame)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, Vec2::new(0.0,0.0))
+        .show(ctx, |ui|{
+            let text_color_addr =
+                if self.invite_address_buffer.parse::<SocketAddr>().is_ok() {None} else {Some(egui::Color32::RED)};
+            ui.label("Address");
+            ui.add_sized(Vec2::new(ui.available_width(),20.0), TextEdit::singleline(&mut self.invite_address_buffer)
+                .hint_text("address:port")
+                .text_color_opt(text_color_addr));
+            ui.checkbox(&mut self.invite_one_time, "One-time token")
+                .on_hover_text("Lets the invited user connect once when no access control rule matches them");
+            if ui.add_sized(Vec2::new(ui.available_width(),20.0), Button::new("Generate").fill(accent_color)).clicked()
+            {
+                generate = true;
+            }
+            if let Some((uri, texture)) = &self.invite
+            {
+                ui.separator();
+                let mut uri_text = uri.clone();
+                ui.add_sized(Vec2::new(ui.available_width(),20.0), TextEdit::singleline(&mut uri_text).interactive(false));
+                if ui.add_sized(Vec2::new(ui.available_width(),20.0), Button::new("Copy")).clicked()
+                {
+                    ctx.output_mut(|o| o.copied_text = uri.clone());
+                }
+                if let Some(texture) = texture
+                {
+                    ui.vertical_centered(|ui|{
+                        ui.add(Image::new(SizedTexture::new(texture, Vec2::new(200.0,200.0))));
+                    });
+                }
+                ui.label("Send this link over a channel you trust, it pins your identity key.");
+            }
+            if ui.add_sized(Vec2::new(ui.available_width(),20.0), Button::new("Close")).clicked() ||
+                ui.input(|i| i.key_pressed(Key::Escape))
+            {
+                close_window = true;
+            }
+        });
+        if generate
+        {
+            match self.invite_address_buffer.parse::<SocketAddr>()
+            {
+                Ok(address) =>
+                {
+                    let token = if self.invite_one_time {Some(Invite::generate_token())} else {None};
+                    let invite = {
+                        let mut config = self.unmovable_context.config.write().unwrap();
+                        if let Some(token) = &token
+                        {
+                            config.network.invite_tokens.push(token.clone());
+                        }
+                        Invite::from_user_info(&UserInfo::self_from_config(&config), vec![address], token)
+                    };
+                    if let Some(invite) = invite
+                    {
+                        let uri = invite.to_string();
+                        let texture = qr_image(&uri, 3)
+                            .map(|image| ctx.load_texture("InviteQr", image, TextureOptions::NEAREST));
+                        self.invite = Some((uri, texture));
+                    }
+                    self.save_config();
+                },
+                Err(_) => self.log.log(MessageKind::Error, "Invalid invite address").unwrap(),
+            }
+        }
+        if close_window
+        {
+            self.show_invite_dialog = false;
+            self.invite = None;
+        }
+    }
+
+    fn show_incoming_call(
+        &mut self, 
+        from: String,
+        window_frame: Frame,
+        ctx: &egui::Context, 
+        accent_color: egui::Color32)
+    {
+        
+        egui::Window::new(format!("{} is calling!",from))
+        .frame(window_frame)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, Vec2::new(0.0,0.0))
+        .show(ctx, |ui|{
+            ui.horizontal(|ui|{
+                if ui.add_sized(Vec2::new(ui.available_width()/2.0,20.0),Button::new("Accept")
+                .fill(accent_color))
+                .clicked()
+                {
+                    let connection_list = self.connection_list.read().unwrap();
+                    if let Some(address) = connection_list.get_address(&from)
+                    {
+                        self.voice_requests.send(VoiceRequest::StartTransmission(address.to_owned())).unwrap()
+                    }
+                    self.show_incoming_call_dialog = None;
+                }
+                if ui.add_sized(Vec2::new(ui.available_width(),20.0),Button::new("Decline")).clicked()
+                {
+                    let connection_list = self.connection_list.read().unwrap();
+                    if let Some(address) = connection_list.get_address(&from)
+                    {
+                        self.voice_requests.send(VoiceRequest::StopTransmission(address.to_owned())).unwrap()
+                    }
+                    self.show_incoming_call_dialog = None;
+                }
+            });
+        });
+    }
+
+    fn show_contact_info(
+        &mut self,
+        name: String,
+        window_frame: Frame,
+        ctx: &egui::Context,
+        accent_color: egui::Color32)
+    {
+        let (local_key, remote_info) = {
+            let config = self.unmovable_context.config.read().unwrap();
+            (config.network.private_key.public_key(), config.network.known_hosts.get(&name).cloned())
+        };
+        let connected_contacts: Vec<String> = self.connection_list.read().unwrap().get_names().into_iter()
+            .filter(|contact| *contact != name)
+            .collect();
+        let mut history_retention = self.unmovable_context.config.read().unwrap().text.contact_history_retention.get(&name).copied();
+        let mut history_retention_changed = false;
+        let mut disappearing_timer = self.unmovable_context.config.read().unwrap().text.disappearing.get(&name).copied();
+        let mut disappearing_timer_changed = false;
+        let mut clear_history = false;
+        let mut close_window = false;
+        let mut set_verified = None;
+        egui::Window::new(format!("{} info",name))
+        .frame(window_frame)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, Vec2::new(0.0,0.0))
+        .show(ctx, |ui|{
+            if let Some(remote_info) = &remote_info
+            {
+                let remote_key = &remote_info.crypto_info().public_key;
+                let safety_number = SafetyNumber::new(&local_key, remote_key);
+                ui.label("Fingerprint");
+                ui.label(remote_key.fingerprint_string());
+                ui.separator();
+                ui.label("Safety number");
+                ui.label(safety_number.to_string());
+                let is_cached = self.contact_info_qr.as_ref().map(|(cached_name,_texture)| *cached_name == name).unwrap_or(false);
+                if !is_cached
+                {
+                    self.contact_info_qr = qr_image(safety_number.digits(), 4)
+                        .map(|image| (name.clone(), ctx.load_texture("SafetyNumberQr", image, TextureOptions::NEAREST)));
+                }
+                if let Some((_name, texture)) = &self.contact_info_qr
+                {
+                    ui.vertical_centered(|ui|{
+                        ui.add(Image::new(SizedTexture::new(texture, Vec2::new(160.0,160.0))));
+                    });
+                }
+                ui.label("Compare these digits with the ones shown on your contact's device, or scan the code.");
+                ui.separator();
+                if remote_info.is_verified()
+                {
+                    ui.colored_label(accent_color, "✔ Verified");
+                    if ui.add_sized(Vec2::new(ui.available_width(),20.0), Button::new("Remove verification")).clicked()
+                    {
+                        set_verified = Some(false);
+                    }
+                    ui.separator();
+                    ui.label("Introduce to");
+                    ComboBox::new("IntroduceToComboBox", "")
+                    .width(ui.available_width())
+                    .selected_text(self.introduce_to_buffer.clone().unwrap_or_default())
+                    .show_ui(ui, |ui|{
+                        for contact in &connected_contacts
+                        {
+                            ui.selectable_value(&mut self.introduce_to_buffer, Some(contact.clone()), contact);
+                        }
+                    });
+                    if ui.add_enabled(self.introduce_to_buffer.is_some(), Button::new("Introduce").min_size(Vec2::new(ui.available_width(),20.0)))
+                        .on_hover_text("Vouch for this contact's identity key with the selected contact")
+                        .clicked()
+                    {
+                        if let Some(to) = self.introduce_to_buffer.take()
+                        {
+                            self.connection_requests.send(ConnectionRequest::Introduce(to, name.clone())).unwrap();
+                        }
+                    }
+                }
+                else
+                {
+                    if let Some(introducer) = remote_info.vouched_by()
+                    {
+                        ui.label(format!("Not verified, vouched by {}",introducer));
+                    }
+                    else
+                    {
+                        ui.label("Not verified");
+                    }
+                    if ui.add_sized(Vec2::new(ui.available_width(),20.0), Button::new("Mark as verified").fill(accent_color)).clicked()
+                    {
+                        set_verified = Some(true);
+                    }
+                }
+            }
+            else
+            {
+                ui.label(format!("No identity key is known for {}",name));
+            }
+            ui.separator();
+            ui.label("Keep the chat history");
+            history_retention_changed = history_retention_editor(ui, "ContactHistoryRetentionComboBox", &mut history_retention, true);
+            if ui.add_sized(Vec2::new(ui.available_width(),20.0), Button::new("Clear history")).clicked()
+            {
+                clear_history = true;
+            }
+            ui.separator();
+            ui.label("Disappearing messages");
+            let timer_name = |timer: &Option<DisappearingTimer>| timer.map(|timer| timer.name()).unwrap_or("Off".to_string());
+            ComboBox::new("DisappearingTimerComboBox", "")
+            .width(ui.available_width())
+            .selected_text(timer_name(&disappearing_timer))
+            .show_ui(ui, |ui|{
+                for option in std::iter::once(None).chain(DisappearingTimer::OPTIONS.map(Some))
+                {
+                    disappearing_timer_changed |= ui.selectable_value(&mut disappearing_timer, option, timer_name(&option)).changed();
+                }
+            });
+            if ui.add_sized(Vec2::new(ui.available_width(),20.0), Button::new("Close")).clicked() ||
+                ui.input(|i| i.key_pressed(Key::Escape))
+            {
+                close_window = true;
+            }
+        });
+        if history_retention_changed
+        {
+            {
+                let mut config = self.unmovable_context.config.write().unwrap();
+                match history_retention
+                {
+                    Some(retention) => config.text.contact_history_retention.insert(name.clone(), retention),
+                    None => config.text.contact_history_retention.remove(&name),
+                };
+            }
+            self.save_config();
+        }
+        if disappearing_timer_changed
+        {
+            {
+                let mut config = self.unmovable_context.config.write().unwrap();
+                match disappearing_timer
+                {
+                    Some(timer) => config.text.disappearing.insert(name.clone(), timer),
+                    None => config.text.disappearing.remove(&name),
+                };
+            }
+            self.save_config();
+            // the contact is told by the text thread
+            self.text_requests.send(TextRequest::SetTimer(name.clone(), disappearing_timer)).unwrap();
+        }
+        if clear_history
+        {
+            let result = {
+                let config = self.unmovable_context.config.read().unwrap();
+                match config.network.known_hosts.get(&name)
+                {
+                    Some(known_host) => History::from_config(&config).clear(&History::identity(&known_host.crypto_info().public_key)),
+                    None => Ok(()),
+                }
+            };
+            self.text_list.write().unwrap().clear(&name);
+            match result
+            {
+                Ok(_) => self.log.log(MessageKind::Event, &format!("History of {} cleared", name)).unwrap(),
+                Err(e) => self.log.log(MessageKind::Error, &format!("Error clearing the history of {}: {}", name, e)).unwrap(),
+            }
+        }
+        if let Some(verified) = set_verified
+        {
+            {
+                let mut config = self.unmovable_context.config.write().unwrap();
+                if let Some(known_host) = config.network.known_hosts.get_mut(&name)
+                {
+                    known_host.set_verified(verified);
+                }
+            }
+            self.save_config();
+            let message = if verified {format!("{} marked as verified",name)} else {format!("{} is no longer verified",name)};
+            self.log.log(MessageKind::Event, &message).unwrap();
+        }
+        if close_window
+        {
+            self.show_contact_info_dialog = None;
+            self.contact_info_qr = None;
+            self.introduce_to_buffer = None;
+        }
+    }
+
+    fn show_new_group(
+        &mut self,
+        window_frame: Frame,
+        ctx: &egui::Context)
+    {
+        let mut close_window = false;
+        egui::Window::new("New group")
+        .frame(window_frame)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, Vec2::new(0.0,0.0))
+        .show(ctx, |ui|{
+            ui.add_sized(Vec2::new(ui.available_width(),20.0), TextEdit::singleline(&mut self.group_name_buffer)
+                .hint_text("group name"));
+            ui.horizontal(|ui|{
+                if ui.add_enabled(!self.group_name_buffer.is_empty(), Button::new("Create")).clicked()
+                {
+                    let id = {
+                        let mut config = self.unmovable_context.config.write().unwrap();
+                        let owner = GroupMember::new(&config.network.name, &config.network.private_key.public_key());
+                        let group = Group::new(&self.group_name_buffer, owner);
+                        let id = group.info.id.clone();
+                        config.text.groups.insert(id.clone(), group);
+                        id
+                    };
+                    self.save_config();
+                    self.log.log(MessageKind::Event, &format!("Group {} created", self.group_name_buffer)).unwrap();
+                    self.active_contact = Some(id.clone());
+                    self.show_group_dialog = Some(id);
+                    close_window = true;
+                }
+                if ui.button("Cancel").clicked() || ui.input(|i| i.key_pressed(Key::Escape))
+                {
+                    close_window = true;
+                }
+            });
+        });
+        if close_window
+        {
+            self.group_name_buffer.clear();
+            self.show_new_group_dialog = false;
+        }
+    }
+
+    fn show_export(
+        &mut self,
+        window_frame: Frame,
+        ctx: &egui::Context)
+    {
+        let title = self.conversation_title(self.export_conversation.as_deref());
+        let mut close_window = false;
+        egui::Window::new(format!("Export {}", title))
+        .frame(window_frame)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, Vec2::new(0.0,0.0))
+        .show(ctx, |ui|{
+            ui.label("Format");
+            let old_format = self.export_format;
+            ComboBox::new("ExportFormatComboBox", "")
+            .width(ui.available_width())
+            .selected_text(self.export_format.name())
+            .show_ui(ui, |ui|{
+                for format in ExportFormat::ALL
+                {
+                    ui.selectable_value(&mut self.export_format, format, format.name());
+                }
+            });
+            if self.export_format != old_format
+            {
+                // keep the extension in sync with the format
+                self.export_path_buffer = std::path::Path::new(&self.export_path_buffer)
+                    .with_extension(self.export_format.extension())
+                    .to_string_lossy()
+                    .to_string();
+            }
+            ui.label("File");
+            ui.add_sized(Vec2::new(ui.available_width(),20.0), TextEdit::singleline(&mut self.export_path_buffer));
+            ui.horizontal(|ui|{
+                if ui.add_enabled(!self.export_path_buffer.is_empty(), Button::new("Export")).clicked()
+                {
+                    self.export_chat(self.export_conversation.clone().as_deref(), &self.export_path_buffer.clone(), self.export_format);
+                    close_window = true;
+                }
+                if ui.button("Cancel").clicked() || ui.input(|i| i.key_pressed(Key::Escape))
+                {
+                    close_window = true;
+                }
+            });
+        });
+        if close_window
+        {
+            self.show_export_dialog = false;
+        }
     }
 
-    fn show_incoming_call(
-        &mut self, 
-        from: String,
+    /// The panels added by the running plugins
+    fn show_plugins(
+        &mut self,
         window_frame: Frame,
-        ctx: &egui::Context, 
-        accent_color: egui::Color32)
+        ctx: &egui::Context)
     {
-        
-        egui::Window::new(format!("{} is calling!",from))
+        let plugins = self.plugin_list.read().unwrap().get_infos().to_vec();
+        let mut close_window = false;
+        egui::Window::new("Plugins")
         .frame(window_frame)
         .collapsible(false)
         .resizable(false)
         .anchor(Align2::CENTER_CENTER, Vec2::new(0.0,0.0))
         .show(ctx, |ui|{
-            ui.horizontal(|ui|{
-                if ui.add_sized(Vec2::new(ui.available_width()/2.0,20.0),Button::new("Accept")
-                .fill(accent_color))
-                .clicked()
+            ScrollArea::vertical().max_height(300.0).show(ui, |ui|{
+                for plugin in plugins.iter().filter(|plugin| plugin.error.is_none())
                 {
-                    let connection_list = self.connection_list.read().unwrap();
-                    if let Some(address) = connection_list.get_address(&from)
+                    for (title, text) in &plugin.panels
                     {
-                        self.voice_requests.send(VoiceRequest::StartTransmission(address.to_owned())).unwrap()
+                        ui.group(|ui|{
+                            ui.set_width(ui.available_width());
+                            ui.label(RichText::new(title).strong()).on_hover_text(&plugin.name);
+                            Markdown::parse(text).show(ui);
+                        });
                     }
-                    self.show_incoming_call_dialog = None;
                 }
-                if ui.add_sized(Vec2::new(ui.available_width(),20.0),Button::new("Decline")).clicked()
+            });
+            if ui.add_sized(Vec2::new(ui.available_width(),20.0), Button::new("Close")).clicked() || ui.input(|i| i.key_pressed(Key::Escape))
+            {
+                close_window = true;
+            }
+        });
+        if close_window
+        {
+            self.show_plugins_dialog = false;
+        }
+    }
+
+    /// Name shown for a conversation, None is the System log
+    fn conversation_title(&self, conversation: Option<&str>) -> String
+    {
+        match conversation
+        {
+            None => "System".to_string(),
+            Some(conversation) => match self.unmovable_context.config.read().unwrap().text.groups.get(conversation)
+            {
+                Some(group) => format!("#{}", group.info.name),
+                None => conversation.to_string(),
+            },
+        }
+    }
+
+    /// Writes the messages of a conversation, or of the System log, to a file
+    fn export_chat(&self, conversation: Option<&str>, path: &str, format: ExportFormat)
+    {
+        let title = self.conversation_title(conversation);
+        let entries = match conversation
+        {
+            None => self.log.get().unwrap().iter().map(ExportEntry::from_log).collect(),
+            Some(conversation) =>
+            {
+                let local_name = self.unmovable_context.config.read().unwrap().network.name.clone();
+                let text_list = self.text_list.read().unwrap();
+                text_list.messages(conversation).map(|messages| messages.iter()
+                    .filter(|message| !message.deleted)
+                    .map(|message| ExportEntry::from_text(message, conversation, &local_name))
+                    .collect())
+                    .unwrap_or_default()
+            },
+        };
+        match ChatExport::new(&title, entries).save(path, format)
+        {
+            Ok(_) => self.log.log(MessageKind::Event, &format!("{} exported to {}", title, path)).unwrap(),
+            Err(e) => self.log.log(MessageKind::Error, &format!("Error exporting {}: {}", title, e)).unwrap(),
+        }
+    }
+
+    /// Runs a command typed in the System chat
+    fn run_command(&mut self, command: &str)
+    {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        match words.as_slice()
+        {
+            ["export", conversation, path @ ..] if !path.is_empty() =>
+            {
+                let path = path.join(" ");
+                let conversation = match *conversation
                 {
-                    let connection_list = self.connection_list.read().unwrap();
-                    if let Some(address) = connection_list.get_address(&from)
+                    "system" => None,
+                    conversation => match conversation.strip_prefix('#')
                     {
-                        self.voice_requests.send(VoiceRequest::StopTransmission(address.to_owned())).unwrap()
+                        // groups are named like in the contacts list
+                        Some(name) => self.unmovable_context.config.read().unwrap().text.groups.values()
+                            .find(|group| group.info.name == name)
+                            .map(|group| group.info.id.clone())
+                            .or(Some(conversation.to_string())),
+                        None => Some(conversation.to_string()),
+                    },
+                };
+                self.export_chat(conversation.as_deref(), &path, ExportFormat::from_path(&path));
+            },
+            [name, ..] if self.plugin_list.read().unwrap().command_owner(name).is_some() =>
+            {
+                self.plugin_requests.send(PluginRequest::Command(command.to_string())).unwrap();
+            },
+            _ =>
+            {
+                let mut usage = defines::COMMAND_USAGE.to_string();
+                for plugin_command in self.plugin_list.read().unwrap().commands()
+                {
+                    usage += &format!(", {}", plugin_command);
+                }
+                self.log.log(MessageKind::Error, &format!("Unknown command, try: {}", usage)).unwrap();
+            },
+        }
+    }
+
+    fn show_group(
+        &mut self,
+        id: String,
+        window_frame: Frame,
+        ctx: &egui::Context)
+    {
+        let (group, local_key, candidates) = {
+            let config = self.unmovable_context.config.read().unwrap();
+            let group = match config.text.groups.get(&id)
+            {
+                Some(group) => group.clone(),
+                None =>
+                {
+                    self.show_group_dialog = None;
+                    return;
+                }
+            };
+            // only contacts with a known identity can be added
+            let mut candidates: Vec<GroupMember> = config.network.known_hosts.iter()
+                .map(|(name, known_host)| GroupMember::new(name, &known_host.crypto_info().public_key))
+                .filter(|candidate| group.info.member(&candidate.public_key).is_none())
+                .collect();
+            candidates.sort_by(|c1, c2| c1.name.cmp(&c2.name));
+            (group, config.network.private_key.public_key(), candidates)
+        };
+        let is_owner = group.is_owned_by(&local_key);
+        let mut members = None;
+        let mut leave = false;
+        let mut close_window = false;
+        egui::Window::new(format!("#{}", group.info.name))
+        .frame(window_frame)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, Vec2::new(0.0,0.0))
+        .show(ctx, |ui|{
+            ui.label("Members");
+            ui.group(|ui|{
+                ui.set_width(ui.available_width());
+                for member in &group.info.members
+                {
+                    ui.horizontal(|ui|{
+                        if is_owner && member.public_key != local_key && ui.small_button("✖").on_hover_text("Remove").clicked()
+                        {
+                            members = Some(group.info.members.iter().filter(|other| other.public_key != member.public_key).cloned().collect());
+                        }
+                        let role = if member.public_key == group.info.owner {" (owner)"} else {""};
+                        ui.label(format!("{}{}", member.name, role));
+                        ui.weak(member.public_key.fingerprint_string());
+                    });
+                }
+            });
+            if is_owner
+            {
+                ui.horizontal(|ui|{
+                    ComboBox::new("GroupMemberComboBox", "")
+                    .selected_text(self.group_member_buffer.clone().unwrap_or_default())
+                    .show_ui(ui, |ui|{
+                        for candidate in &candidates
+                        {
+                            ui.selectable_value(&mut self.group_member_buffer, Some(candidate.name.clone()), &candidate.name);
+                        }
+                    });
+                    let candidate = candidates.iter().find(|candidate| Some(&candidate.name) == self.group_member_buffer.as_ref());
+                    if ui.add_enabled(candidate.is_some(), Button::new("Add")).clicked()
+                    {
+                        let mut new_members = group.info.members.clone();
+                        new_members.extend(candidate.cloned());
+                        members = Some(new_members);
+                        self.group_member_buffer = None;
                     }
-                    self.show_incoming_call_dialog = None;
+                });
+            }
+            ui.horizontal(|ui|{
+                let leave_text = if is_owner {"Delete group"} else {"Leave group"};
+                if ui.button(leave_text).clicked()
+                {
+                    leave = true;
+                }
+                if ui.button("Close").clicked() || ui.input(|i| i.key_pressed(Key::Escape))
+                {
+                    close_window = true;
+                }
+            });
+        });
+        if members.is_some() || leave
+        {
+            {
+                let mut config = self.unmovable_context.config.write().unwrap();
+                if let Some(group) = config.text.groups.get_mut(&id)
+                {
+                    if leave
+                    {
+                        // the owner tells every member the group is gone
+                        if is_owner
+                        {
+                            group.set_members(Vec::new());
+                        }
+                        group.left = true;
+                    }
+                    else if let Some(members) = members
+                    {
+                        group.set_members(members);
+                    }
+                }
+            }
+            self.save_config();
+        }
+        if leave
+        {
+            self.text_list.write().unwrap().clear(&id);
+            if self.active_contact.as_ref() == Some(&id)
+            {
+                self.active_contact = None;
+            }
+            self.log.log(MessageKind::Event, &format!("You left the group {}", group.info.name)).unwrap();
+            close_window = true;
+        }
+        if close_window
+        {
+            self.show_group_dialog = None;
+            self.group_member_buffer = None;
+        }
+    }
+
+    fn show_key_change(
+        &mut self,
+        name: String,
+        stored_key: PublicKey,
+        presented_key: PublicKey,
+        window_frame: Frame,
+        ctx: &egui::Context,
+        accent_color: egui::Color32)
+    {
+        let verified = self.unmovable_context.config.read().unwrap().network.known_hosts.get(&name)
+            .map(|known_host| known_host.is_verified()).unwrap_or(false);
+        let mut decision = None;
+        egui::Window::new(format!("Identity key of {} changed",name))
+        .frame(window_frame)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, Vec2::new(0.0,0.0))
+        .show(ctx, |ui|{
+            ui.label(format!("{} is connecting with a different identity key. This happens after a reinstall, but it could also be someone impersonating them.",name));
+            if verified
+            {
+                ui.colored_label(defines::LOG_SECURITY_COLOR, "You verified the stored key, check the new one with your contact before trusting it.");
+            }
+            ui.separator();
+            ui.label("Stored fingerprint");
+            ui.label(stored_key.fingerprint_string());
+            ui.label("Presented fingerprint");
+            ui.label(presented_key.fingerprint_string());
+            ui.separator();
+            ui.horizontal(|ui|{
+                if ui.add_sized(Vec2::new(ui.available_width()/3.0,20.0),Button::new("Reject")).clicked()
+                {
+                    decision = Some(KeyChangeDecision::Reject);
+                }
+                if ui.add_sized(Vec2::new(ui.available_width()/2.0,20.0),Button::new("Accept once"))
+                .on_hover_text("Trust the new key until restart")
+                .clicked()
+                {
+                    decision = Some(KeyChangeDecision::AcceptOnce);
+                }
+                if ui.add_sized(Vec2::new(ui.available_width(),20.0),Button::new("Replace key").fill(accent_color)).clicked()
+                {
+                    decision = Some(KeyChangeDecision::Replace);
                 }
             });
         });
+        if let Some(decision) = decision
+        {
+            self.connection_requests.send(ConnectionRequest::ResolveKeyChange(name, decision)).unwrap();
+            self.key_change_dialogs.remove(0);
+        }
+    }
+
+    fn show_unlock(
+        &mut self,
+        ctx: &egui::Context,
+        frame: &mut eframe::Frame)
+    {
+        frame.set_decorations(true);
+        frame.set_window_size(Vec2::new(400.0,140.0));
+        let mut unlock = false;
+        CentralPanel::default()
+        .show(ctx, |ui|{
+            ui.label("The config is protected by a passphrase");
+            let response = ui.add_sized(
+                Vec2::new(ui.available_width(),20.0),
+                TextEdit::singleline(&mut self.unlock_passphrase_buffer).password(true));
+            response.request_focus();
+            if ui.add_sized(Vec2::new(ui.available_width(),20.0), Button::new("Unlock")).clicked() ||
+                ui.input(|i| i.key_pressed(Key::Enter))
+            {
+                unlock = true;
+            }
+            if let Some(error) = &self.unlock_error
+            {
+                ui.colored_label(defines::LOG_ERROR_COLOR, error);
+            }
+        });
+        if unlock
+        {
+            let mut locked_config = self.unmovable_context.locked_config.lock().unwrap();
+            if let Some(locked) = locked_config.as_ref()
+            {
+                match locked.unlock(&self.unlock_passphrase_buffer)
+                {
+                    Ok(mut config) =>
+                    {
+                        self.settings_port_buffer = config.network.port.to_string();
+                        config.path = self.unmovable_context.config.read().unwrap().path.clone();
+                        // the keys generated while unlocking must be saved before they are used
+                        if let Err(e) = config.save()
+                        {
+                            println!("Error saving config: {}",e);
+                        }
+                        *self.unmovable_context.config.write().unwrap() = config;
+                        *locked_config = None;
+                        self.unlock_error = None;
+                        // show the loading screen while the threads start
+                        self.loading_timer = Some(std::time::Instant::now());
+                    },
+                    Err(e) => self.unlock_error = Some(e),
+                }
+            }
+            self.unlock_passphrase_buffer.clear();
+        }
+        ctx.request_repaint_after(defines::UPDATE_UI_INTERVAL);
     }
 
     fn handle_notifications(&mut self)
@@ -789,7 +2463,10 @@ impl UI
         {
             match notification 
             {
-                UiNotification::IncomingConnection(_) => todo!(),
+                UiNotification::IncomingConnection(name, public_key, address) =>
+                {
+                    self.incoming_connection_dialogs.push((name, public_key, address));
+                },
                 UiNotification::IncomingCall(from) => 
                 {
                     if self.voice_interlocutor.lock().unwrap().is_none()
@@ -797,6 +2474,12 @@ impl UI
                         self.show_incoming_call_dialog = Some(from);
                     }
                 },
+                UiNotification::IdentityKeyChanged(name, stored_key, presented_key) =>
+                {
+                    // only the latest key presented by a contact is asked about
+                    self.key_change_dialogs.retain(|(pending, _, _)| pending != &name);
+                    self.key_change_dialogs.push((name, stored_key, presented_key));
+                },
             }
         }
     }
@@ -816,6 +2499,11 @@ impl eframe::App for UI
         {
             self.loading_timer = Some(std::time::Instant::now());
         }
+        if self.first_running_frame && self.unmovable_context.locked_config.lock().unwrap().is_some()
+        {
+            self.show_unlock(ctx, frame);
+            return;
+        }
         if self.first_running_frame && (self.loading_timer.unwrap().elapsed() < defines::MIN_LOAD_TIME || *self.is_still_loading.lock().unwrap())
         {
             let size = Vec2::new(1400.0/2.0,256.0/2.0);
@@ -922,6 +2610,9 @@ impl eframe::App for UI
                 let group_margin = margin + 5.0;
                 let left_group_width = size.x * 0.2 - 2.0*group_margin;
                 let input_height = 35.0;
+                // the composer grows with the lines of the message
+                let composer_rows = self.input_buffer.split('\n').count().min(defines::COMPOSER_MAX_ROWS);
+                let composer_height = input_height + (composer_rows - 1) as f32 * ui.text_style_height(&egui::TextStyle::Body);
                 let left_group_min_width = 150.0 - 2.0*group_margin;
                 ui.vertical(|ui|{
                     ui.group(|ui|{
@@ -933,7 +2624,7 @@ impl eframe::App for UI
                 });
                 ui.vertical(|ui|{
                     ui.group(|ui|{
-                        self.add_chat(ui, size, group_margin, input_height, text_color)
+                        self.add_chat(ui, size, group_margin, composer_height, text_color)
                     });
                     ui.group(|ui|{
                         self.add_input(ui, send_image)
@@ -947,15 +2638,60 @@ impl eframe::App for UI
             self.show_new_connection(window_frame, ctx, search_image, connect_image);
         }
 
+        if self.show_invite_dialog
+        {
+            self.show_invite(window_frame, ctx, accent_color);
+        }
+
+        if self.show_export_dialog
+        {
+            self.show_export(window_frame, ctx);
+        }
+
+        if self.show_plugins_dialog
+        {
+            self.show_plugins(window_frame, ctx);
+        }
+
         if self.show_settings_dialog
         {
             self.show_settings(window_frame, ctx);
         }
 
+        if self.show_acl_dialog
+        {
+            self.show_acl(window_frame, ctx);
+        }
+
+        if let Some((name, public_key, address)) = self.incoming_connection_dialogs.first().cloned()
+        {
+            self.show_incoming_connection(name, public_key, address, window_frame, ctx, accent_color);
+        }
+
         if let Some(from) = &self.show_incoming_call_dialog
         {
             self.show_incoming_call(from.clone(), window_frame, ctx, accent_color);
         }
+
+        if let Some(name) = &self.show_contact_info_dialog
+        {
+            self.show_contact_info(name.clone(), window_frame, ctx, accent_color);
+        }
+
+        if self.show_new_group_dialog
+        {
+            self.show_new_group(window_frame, ctx);
+        }
+
+        if let Some(id) = &self.show_group_dialog
+        {
+            self.show_group(id.clone(), window_frame, ctx);
+        }
+
+        if let Some((name, stored_key, presented_key)) = self.key_change_dialogs.first().cloned()
+        {
+            self.show_key_change(name, stored_key, presented_key, window_frame, ctx, accent_color);
+        }
         ctx.request_repaint_after(defines::UPDATE_UI_INTERVAL);  
     }
 
@@ -964,4 +2700,94 @@ impl eframe::App for UI
         // stop the other threads
         self.unmovable_context.stop();
     }
+}
+
+fn message_author<'a>(message: &'a TextInfo, contact: &'a str) -> &'a str
+{
+    match (&message.direction, &message.author)
+    {
+        (TextDirection::Incoming, Some(author)) => author,
+        (TextDirection::Incoming, None) => contact,
+        (TextDirection::Outgoing, _) => "You",
+    }
+}
+
+/// The Markdown of a message, parsed the first time it's shown and after every edit
+fn cached_markdown<'a>(cache: &'a mut HashMap<(u64,TextDirection),(String,Markdown)>, message: &TextInfo) -> &'a Markdown
+{
+    let (text, markdown) = cache.entry((message.nonce, message.direction.clone()))
+        .or_insert_with(|| (message.text.clone(), Markdown::parse(&message.text)));
+    if *text != message.text
+    {
+        *text = message.text.clone();
+        *markdown = Markdown::parse(text);
+    }
+    markdown
+}
+
+/// One line summary of a message, used for quoted replies
+fn message_preview(message: &TextInfo, contact: &str) -> String
+{
+    let text = if message.deleted {"message deleted".to_string()} else {message.text.replace('\n', " ")};
+    let text = if text.chars().count() > defines::REPLY_PREVIEW_LEN
+    {
+        text.chars().take(defines::REPLY_PREVIEW_LEN).collect::<String>() + "..."
+    }
+    else
+    {
+        text
+    };
+    format!("{}: {}", message_author(message, contact), text)
+}
+
+fn protection_mode_name(mode: Option<ProtectionMode>) -> &'static str
+{
+    match mode
+    {
+        None => "None",
+        Some(ProtectionMode::PrivateKey) => "Keys only",
+        Some(ProtectionMode::Full) => "Whole config",
+    }
+}
+
+fn format_bytes(bytes: u64) -> String
+{
+    match bytes
+    {
+        0..=1023 => format!("{}B", bytes),
+        1024..=1048575 => format!("{:.1}KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1}MiB", bytes as f64 / 1048576.0),
+    }
+}
+
+/// None is the global setting, only offered if allow_default is set
+fn history_retention_editor(ui: &mut Ui, id: &str, retention: &mut Option<HistoryRetention>, allow_default: bool) -> bool
+{
+    let mut changed = false;
+    let name = |retention: &Option<HistoryRetention>| retention.map(|retention| retention.name()).unwrap_or("Default".to_string());
+    let days = match retention
+    {
+        Some(HistoryRetention::Days(days)) => *days,
+        _ => defines::DEFAULT_HISTORY_RETENTION_DAYS,
+    };
+    let mut options = vec![Some(HistoryRetention::Off), Some(HistoryRetention::Forever), Some(HistoryRetention::Days(days))];
+    if allow_default
+    {
+        options.insert(0, None);
+    }
+    ui.horizontal(|ui|{
+        ComboBox::new(id, "")
+        .selected_text(name(retention))
+        .show_ui(ui, |ui|{
+            for option in options
+            {
+                changed |= ui.selectable_value(retention, option, name(&option)).changed();
+            }
+        });
+        if let Some(HistoryRetention::Days(days)) = retention
+        {
+            changed |= ui.add(DragValue::new(days).clamp_range(1..=3650).suffix(" days")).changed();
+        }
+    });
+    changed
 }
\ No newline at end of file
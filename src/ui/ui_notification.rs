@@ -1,5 +1,12 @@
+use std::net::SocketAddr;
+
+use crate::crypto::PublicKey;
+
 pub enum UiNotification
 {
-    IncomingConnection(String),
+    /// name, identity key and address of a peer the acl asks about
+    IncomingConnection(String,PublicKey,SocketAddr),
     IncomingCall(String),
+    /// name, stored identity key, presented identity key
+    IdentityKeyChanged(String,PublicKey,PublicKey),
 }
\ No newline at end of file
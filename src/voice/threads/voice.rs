@@ -3,7 +3,7 @@ use std::{sync::{mpsc::{Receiver, Sender, RecvTimeoutError}, Arc, RwLock, Mutex,
 use cpal::{traits::{HostTrait, DeviceTrait, StreamTrait}, Device, Host};
 use rubato::Resampler;
 
-use crate::{network::{Packet, Content, ConnectionList}, config::{Config, defines}, log::{Logger, MessageKind}, voice::{VoiceRequest, VoiceContext}, ui::UiNotification};
+use crate::{network::{Packet, Content, ConnectionList}, config::{Config, defines}, log::{Logger, MessageKind}, voice::{VoiceRequest, VoiceContext}, ui::UiNotification, hooks::HookEvent};
 
 pub fn run(
     running: Arc<RwLock<bool>>,
@@ -14,6 +14,7 @@ pub fn run(
     ui_notifications: Sender<UiNotification>,
     voice_queue: Receiver<(Packet,SocketAddr)>, 
     sender_queue: Sender<(Content,SocketAddr)>,
+    hook_events: Sender<HookEvent>,
     config: Arc<RwLock<Config>>
 )
 {
@@ -78,6 +79,7 @@ pub fn run(
                                 if !ignore
                                 {
                                     ui_notifications.send(UiNotification::IncomingCall(from_name.to_string())).unwrap();
+                                    hook_events.send(HookEvent::Call(from_name.to_string())).unwrap();
                                 }
                             }
                             else 